@@ -0,0 +1,354 @@
+//! Derive macros for the `Key` and `Value` traits of [redb](https://docs.rs/redb)
+//!
+//! Structs are encoded in the same way as a tuple of their fields, so a struct with fields of
+//! types `A` and `B` is stored identically to `(A, B)`. If every field has a fixed width, so does
+//! the struct. Enums are encoded as a one byte tag, for the index of the variant, followed by the
+//! fields of the variant. The fields of derived types must be owned types, such as `u64` or
+//! `String`, rather than borrowed ones such as `&str`.
+//!
+//! Deriving `Key` orders structs by their fields, in declaration order, and enums by their variant
+//! and then by its fields.
+//!
+//! These macros are also re-exported by redb, when its `derive` feature is enabled.
+//!
+//! ```rust
+//! use redb_derive::{Key, Value};
+//!
+//! #[derive(Debug, Key, Value)]
+//! struct Timestamped {
+//!     time: u64,
+//!     name: String,
+//! }
+//!
+//! #[derive(Debug, Key, Value)]
+//! #[redb(type_name = "my_app::Status")]
+//! enum Status {
+//!     Active,
+//!     Suspended { until: u64 },
+//! }
+//! ```
+//!
+//! The stored type name defaults to the path of the type, including its module. Use
+//! `#[redb(type_name = "...")]` to set a name that is stable if the type is moved or renamed.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr, Result, Type};
+
+// The largest tuple which implements Key and Value
+const MAX_FIELDS: usize = 12;
+
+#[proc_macro_derive(Value, attributes(redb))]
+pub fn derive_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    value_impl(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Key, attributes(redb))]
+pub fn derive_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    key_impl(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// The fields of a struct, or of an enum variant, bound to the variables f0, f1, ...
+struct FieldList {
+    types: Vec<Type>,
+    bindings: Vec<Ident>,
+    // Destructures or constructs the fields, when following the path of the struct or variant
+    pattern: TokenStream2,
+}
+
+impl FieldList {
+    fn new(fields: &Fields) -> Result<Self> {
+        if fields.len() > MAX_FIELDS {
+            return Err(Error::new_spanned(
+                fields,
+                format!("at most {MAX_FIELDS} fields are supported"),
+            ));
+        }
+        let types = fields.iter().map(|field| field.ty.clone()).collect();
+        let bindings: Vec<Ident> = (0..fields.len()).map(|i| format_ident!("f{}", i)).collect();
+        let pattern = match fields {
+            Fields::Named(named) => {
+                let names = named.named.iter().map(|field| &field.ident);
+                quote!({ #(#names: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+            Fields::Unit => quote!(),
+        };
+
+        Ok(Self {
+            types,
+            bindings,
+            pattern,
+        })
+    }
+
+    // The type that the fields are encoded as
+    fn encoded_type(&self) -> TokenStream2 {
+        let types = &self.types;
+        match types.len() {
+            0 => quote!(()),
+            1 => quote!(#(#types)*),
+            _ => quote!((#(#types),*)),
+        }
+    }
+
+    fn fixed_width(&self) -> TokenStream2 {
+        let encoded = self.encoded_type();
+        quote!(<#encoded as ::redb::Value>::fixed_width())
+    }
+
+    // Decodes `data` and constructs `path` from the fields
+    fn decode(&self, path: &TokenStream2, data: &TokenStream2) -> TokenStream2 {
+        let encoded = self.encoded_type();
+        let bindings = &self.bindings;
+        let pattern = &self.pattern;
+        match bindings.len() {
+            0 => quote!(#path #pattern),
+            1 => quote! {{
+                let #(#bindings)* = <#encoded as ::redb::Value>::from_bytes(#data);
+                #path #pattern
+            }},
+            _ => quote! {{
+                let (#(#bindings),*) = <#encoded as ::redb::Value>::from_bytes(#data);
+                #path #pattern
+            }},
+        }
+    }
+
+    // Encodes the fields, which must already be bound to references
+    fn encode(&self) -> TokenStream2 {
+        let types = &self.types;
+        let bindings = &self.bindings;
+        match bindings.len() {
+            0 => quote!(::std::vec::Vec::new()),
+            1 => quote! {
+                ::std::convert::AsRef::<[u8]>::as_ref(
+                    &<#(#types)* as ::redb::Value>::as_bytes(#(#bindings)*)
+                ).to_vec()
+            },
+            _ => {
+                let fixed_width = self.fixed_width();
+                quote! {
+                    ::redb::__private::serialize_tuple_elements(
+                        #fixed_width.is_some(),
+                        &[#(
+                            ::std::convert::AsRef::<[u8]>::as_ref(
+                                &<#types as ::redb::Value>::as_bytes(#bindings)
+                            )
+                        ),*],
+                    )
+                }
+            }
+        }
+    }
+
+    fn compare(&self, data1: &TokenStream2, data2: &TokenStream2) -> TokenStream2 {
+        let encoded = self.encoded_type();
+        quote!(<#encoded as ::redb::Key>::compare(#data1, #data2))
+    }
+}
+
+fn check_supported(input: &DeriveInput) -> Result<()> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "generic types are not supported",
+        ));
+    }
+    if let Data::Union(_) = input.data {
+        return Err(Error::new_spanned(input, "unions are not supported"));
+    }
+    if let Data::Enum(data) = &input.data {
+        if data.variants.is_empty() || data.variants.len() > 256 {
+            return Err(Error::new_spanned(
+                input,
+                "enums must have between 1 and 256 variants",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn type_name(input: &DeriveInput) -> Result<TokenStream2> {
+    let mut name: Option<LitStr> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("redb"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("type_name") {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported redb attribute"))
+            }
+        })?;
+    }
+
+    Ok(if let Some(name) = name {
+        quote!(::redb::TypeName::new(#name))
+    } else {
+        let ident = input.ident.to_string();
+        quote!(::redb::TypeName::new(concat!(module_path!(), "::", #ident)))
+    })
+}
+
+// The variants of an enum, along with their tags
+fn variants(input: &DeriveInput) -> Result<Vec<(u8, Ident, FieldList)>> {
+    let mut result = vec![];
+    if let Data::Enum(data) = &input.data {
+        for (i, variant) in data.variants.iter().enumerate() {
+            let tag = u8::try_from(i).unwrap();
+            result.push((tag, variant.ident.clone(), FieldList::new(&variant.fields)?));
+        }
+    }
+    Ok(result)
+}
+
+fn value_impl(input: &DeriveInput) -> Result<TokenStream2> {
+    check_supported(input)?;
+    let ident = &input.ident;
+    let type_name = type_name(input)?;
+    let data = quote!(data);
+
+    let (fixed_width, from_bytes, as_bytes) = match &input.data {
+        Data::Struct(data_struct) => {
+            let fields = FieldList::new(&data_struct.fields)?;
+            let fixed_width = fields.fixed_width();
+            let from_bytes = fields.decode(&quote!(Self), &data);
+            let encode = fields.encode();
+            let pattern = &fields.pattern;
+            let as_bytes = if fields.bindings.is_empty() {
+                encode
+            } else {
+                quote! {{
+                    let Self #pattern = value;
+                    #encode
+                }}
+            };
+            (fixed_width, from_bytes, as_bytes)
+        }
+        Data::Enum(_) => {
+            let variants = variants(input)?;
+            let widths = variants.iter().map(|(_, _, fields)| fields.fixed_width());
+            let fixed_width = quote! {{
+                // Enums are fixed width if all of their variants have the same fixed width
+                let widths = [#(#widths),*];
+                let width = widths[0]?;
+                if widths.iter().all(|x| *x == Some(width)) {
+                    Some(width + 1)
+                } else {
+                    None
+                }
+            }};
+            let decode = variants.iter().map(|(tag, variant, fields)| {
+                let decoded = fields.decode(&quote!(Self::#variant), &quote!(&data[1..]));
+                quote!(#tag => #decoded,)
+            });
+            let from_bytes = quote! {
+                match data[0] {
+                    #(#decode)*
+                    tag => unreachable!("invalid tag: {}", tag),
+                }
+            };
+            let encode = variants.iter().map(|(tag, variant, fields)| {
+                let pattern = &fields.pattern;
+                let encoded = fields.encode();
+                quote! {
+                    Self::#variant #pattern => {
+                        let mut result = vec![#tag];
+                        result.extend_from_slice(&#encoded);
+                        result
+                    }
+                }
+            });
+            let as_bytes = quote! {
+                match value {
+                    #(#encode)*
+                }
+            };
+            (fixed_width, from_bytes, as_bytes)
+        }
+        Data::Union(_) => unreachable!(),
+    };
+
+    Ok(quote! {
+        impl ::redb::Value for #ident {
+            type SelfType<'a> = #ident
+            where
+                Self: 'a;
+            type AsBytes<'a> = ::std::vec::Vec<u8>
+            where
+                Self: 'a;
+
+            fn fixed_width() -> ::std::option::Option<usize> {
+                #fixed_width
+            }
+
+            fn from_bytes<'a>(data: &'a [u8]) -> #ident
+            where
+                Self: 'a,
+            {
+                #from_bytes
+            }
+
+            fn as_bytes<'a, 'b: 'a>(value: &'a #ident) -> ::std::vec::Vec<u8>
+            where
+                Self: 'a,
+                Self: 'b,
+            {
+                #as_bytes
+            }
+
+            fn type_name() -> ::redb::TypeName {
+                #type_name
+            }
+        }
+    })
+}
+
+fn key_impl(input: &DeriveInput) -> Result<TokenStream2> {
+    check_supported(input)?;
+    let ident = &input.ident;
+
+    let compare = match &input.data {
+        Data::Struct(data_struct) => {
+            FieldList::new(&data_struct.fields)?.compare(&quote!(data1), &quote!(data2))
+        }
+        Data::Enum(_) => {
+            let variants = variants(input)?;
+            let compare = variants.iter().map(|(tag, _, fields)| {
+                let compared = fields.compare(&quote!(&data1[1..]), &quote!(&data2[1..]));
+                quote!(#tag => #compared,)
+            });
+            quote! {
+                match data1[0].cmp(&data2[0]) {
+                    ::std::cmp::Ordering::Equal => {}
+                    order => return order,
+                }
+                match data1[0] {
+                    #(#compare)*
+                    tag => unreachable!("invalid tag: {}", tag),
+                }
+            }
+        }
+        Data::Union(_) => unreachable!(),
+    };
+
+    Ok(quote! {
+        impl ::redb::Key for #ident {
+            fn compare(data1: &[u8], data2: &[u8]) -> ::std::cmp::Ordering {
+                #compare
+            }
+        }
+    })
+}
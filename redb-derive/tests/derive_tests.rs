@@ -0,0 +1,145 @@
+use redb::{Database, Key, ReadableTable, TableDefinition, TypeName, Value};
+use redb_derive::{Key, Value};
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq, Key, Value)]
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[derive(Debug, PartialEq, Key, Value)]
+struct Named(String, i64);
+
+#[derive(Debug, PartialEq, Key, Value)]
+struct Wrapper {
+    inner: u64,
+}
+
+#[derive(Debug, PartialEq, Key, Value)]
+struct Empty;
+
+#[derive(Debug, PartialEq, Key, Value)]
+#[redb(type_name = "test::Shape")]
+enum Shape {
+    Empty,
+    Circle(u32),
+    Rectangle { width: u32, height: u32 },
+    Labeled(String, Point),
+}
+
+#[derive(Debug, PartialEq, Key, Value)]
+enum Direction {
+    North,
+    South,
+}
+
+fn create_tempfile() -> tempfile::NamedTempFile {
+    if cfg!(target_os = "wasi") {
+        tempfile::NamedTempFile::new_in("/tmp").unwrap()
+    } else {
+        tempfile::NamedTempFile::new().unwrap()
+    }
+}
+
+#[test]
+fn struct_encoding() {
+    // Structs are encoded in the same way as a tuple of their fields
+    assert_eq!(Point::fixed_width(), Some(8));
+    let point = Point { x: 1, y: 2 };
+    assert_eq!(
+        Point::as_bytes(&point),
+        <(u32, u32)>::as_bytes(&(1, 2)).as_slice()
+    );
+    assert_eq!(Point::from_bytes(&Point::as_bytes(&point)), point);
+
+    assert_eq!(Named::fixed_width(), None);
+    let named = Named("hello".to_string(), -5);
+    assert_eq!(
+        Named::as_bytes(&named),
+        <(&str, i64)>::as_bytes(&("hello", -5)).as_slice()
+    );
+    assert_eq!(Named::from_bytes(&Named::as_bytes(&named)), named);
+
+    assert_eq!(Wrapper::fixed_width(), Some(8));
+    assert_eq!(Wrapper::as_bytes(&Wrapper { inner: 7 }), 7u64.to_le_bytes());
+    assert_eq!(Empty::fixed_width(), Some(0));
+    assert_eq!(Empty::from_bytes(&[]), Empty);
+
+    assert_eq!(Point::type_name(), TypeName::new("derive_tests::Point"));
+    assert_eq!(Shape::type_name(), TypeName::new("test::Shape"));
+}
+
+#[test]
+fn enum_encoding() {
+    assert_eq!(Direction::fixed_width(), Some(1));
+    assert_eq!(Direction::as_bytes(&Direction::South), vec![1]);
+    assert_eq!(Shape::fixed_width(), None);
+
+    let shapes = [
+        Shape::Empty,
+        Shape::Circle(5),
+        Shape::Rectangle {
+            width: 1,
+            height: 2,
+        },
+        Shape::Labeled("a".to_string(), Point { x: 3, y: 4 }),
+    ];
+    for shape in shapes {
+        assert_eq!(Shape::from_bytes(&Shape::as_bytes(&shape)), shape);
+    }
+}
+
+#[test]
+fn ordering() {
+    let compare = |a: &Shape, b: &Shape| Shape::compare(&Shape::as_bytes(a), &Shape::as_bytes(b));
+    assert_eq!(compare(&Shape::Empty, &Shape::Circle(0)), Ordering::Less);
+    assert_eq!(
+        compare(&Shape::Circle(3), &Shape::Circle(2)),
+        Ordering::Greater
+    );
+    assert_eq!(
+        compare(
+            &Shape::Rectangle {
+                width: 1,
+                height: 5
+            },
+            &Shape::Rectangle {
+                width: 2,
+                height: 0
+            }
+        ),
+        Ordering::Less
+    );
+
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let definition: TableDefinition<Named, Point> = TableDefinition::new("x");
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for (name, id) in [("b", 1), ("a", 2), ("b", -1), ("a", 1)] {
+            table
+                .insert(Named(name.to_string(), id), Point { x: 0, y: 1 })
+                .unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let keys: Vec<Named> = table
+        .iter()
+        .unwrap()
+        .map(|x| x.unwrap().0.value())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            Named("a".to_string(), 1),
+            Named("a".to_string(), 2),
+            Named("b".to_string(), -1),
+            Named("b".to_string(), 1),
+        ]
+    );
+}
@@ -1,2 +1,4 @@
 pub use crate::tree_store::file_backend::FileBackend;
 pub use crate::tree_store::InMemoryBackend;
+#[cfg(feature = "encryption")]
+pub use crate::tree_store::{EncryptedBackend, EncryptionKey};
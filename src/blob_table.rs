@@ -0,0 +1,310 @@
+use crate::sealed::Sealed;
+use crate::types::Key;
+use crate::{AccessGuard, ReadOnlyTable, ReadableTable, Result, StorageError, Table, TableHandle};
+use std::borrow::Borrow;
+use std::cmp::min;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// Blobs are stored as chunks of at most this many bytes, keyed by the blob's key and the offset of
+// the chunk. This leaves room for the key in a 1MiB page
+const CHUNK_SIZE: usize = 1024 * 1024 - 4096;
+
+type ChunkTable<'txn, K> = Table<'txn, (K, u64), &'static [u8]>;
+type ReadOnlyChunkTable<K> = ReadOnlyTable<(K, u64), &'static [u8]>;
+// A chunk of a blob, and its offset
+type Chunk<'a> = (u64, AccessGuard<'a, &'static [u8]>);
+
+fn to_io_error(err: StorageError) -> io::Error {
+    match err {
+        StorageError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
+
+// Returns the chunk of the blob stored under `key` which contains `position`, along with its offset
+fn find_chunk<'t, K: Key + 'static, T: ReadableTable<(K, u64), &'static [u8]>>(
+    table: &'t T,
+    key: &[u8],
+    position: u64,
+) -> Result<Option<Chunk<'t>>> {
+    let start = (K::from_bytes(key), 0);
+    let end = (K::from_bytes(key), position);
+    if let Some(entry) = table.range(start..=end)?.next_back() {
+        let (chunk_key, chunk) = entry?;
+        let offset = chunk_key.value().1;
+        Ok(Some((offset, chunk)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn blob_len<K: Key + 'static, T: ReadableTable<(K, u64), &'static [u8]>>(
+    table: &T,
+    key: &[u8],
+) -> Result<Option<u64>> {
+    Ok(find_chunk::<K, T>(table, key, u64::MAX)?
+        .map(|(offset, chunk)| offset + u64::try_from(chunk.value().len()).unwrap()))
+}
+
+fn reader<'t, K: Key + 'static, T: ReadableTable<(K, u64), &'static [u8]>>(
+    table: &'t T,
+    key: Vec<u8>,
+) -> Result<Option<BlobReader<'t>>> {
+    if let Some(len) = blob_len::<K, T>(table, &key)? {
+        Ok(Some(BlobReader {
+            find_chunk: Box::new(move |position| find_chunk::<K, T>(table, &key, position)),
+            len,
+            position: 0,
+            chunk: None,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// A blob table
+///
+/// Each key maps to a byte string, which is written with a [`BlobWriter`] and read with a
+/// [`BlobReader`], without ever needing to be held in memory all at once. Blobs are not limited
+/// to the maximum size of a value.
+pub struct BlobTable<'txn, K: Key + 'static> {
+    table: ChunkTable<'txn, K>,
+}
+
+impl<'txn, K: Key + 'static> BlobTable<'txn, K> {
+    pub(crate) fn new(table: ChunkTable<'txn, K>) -> Self {
+        Self { table }
+    }
+
+    /// Returns a writer which stores a blob under `key`, replacing any existing blob
+    ///
+    /// Data is written to the table in chunks as it's written to the writer. The remaining data is
+    /// written when the writer is dropped, but any errors are then ignored, so
+    /// [`BlobWriter::finish`] should be called to observe them.
+    pub fn writer<'a>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'a>>,
+    ) -> Result<BlobWriter<'_, 'txn, K>> {
+        let key = K::as_bytes(key.borrow()).as_ref().to_vec();
+        self.remove_chunks(&key)?;
+        // An empty chunk marks that the blob exists, until the first chunk replaces it
+        self.table.insert((K::from_bytes(&key), 0), [].as_slice())?;
+
+        Ok(BlobWriter {
+            table: &mut self.table,
+            key,
+            offset: 0,
+            buffer: vec![],
+        })
+    }
+
+    /// Returns a reader for the blob stored under `key`, if it exists
+    pub fn reader<'a>(&self, key: impl Borrow<K::SelfType<'a>>) -> Result<Option<BlobReader<'_>>> {
+        reader::<K, _>(&self.table, K::as_bytes(key.borrow()).as_ref().to_vec())
+    }
+
+    /// Returns the length of the blob stored under `key`, if it exists
+    pub fn blob_len<'a>(&self, key: impl Borrow<K::SelfType<'a>>) -> Result<Option<u64>> {
+        blob_len::<K, _>(&self.table, K::as_bytes(key.borrow()).as_ref())
+    }
+
+    /// Removes the blob stored under `key`
+    ///
+    /// Returns `true` if the blob existed
+    pub fn remove<'a>(&mut self, key: impl Borrow<K::SelfType<'a>>) -> Result<bool> {
+        let key = K::as_bytes(key.borrow()).as_ref().to_vec();
+        let existed = blob_len::<K, _>(&self.table, &key)?.is_some();
+        self.remove_chunks(&key)?;
+
+        Ok(existed)
+    }
+
+    fn remove_chunks(&mut self, key: &[u8]) -> Result {
+        let start = (K::from_bytes(key), 0);
+        let end = (K::from_bytes(key), u64::MAX);
+        self.table.retain_in(start..=end, |_, _| false)
+    }
+}
+
+impl<K: Key + 'static> TableHandle for BlobTable<'_, K> {
+    fn name(&self) -> &str {
+        self.table.name()
+    }
+}
+
+impl<K: Key + 'static> Sealed for BlobTable<'_, K> {}
+
+impl<K: Key + 'static> Debug for BlobTable<'_, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobTable")
+            .field("name", &self.table.name())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A read-only blob table
+pub struct ReadOnlyBlobTable<K: Key + 'static> {
+    table: ReadOnlyChunkTable<K>,
+}
+
+impl<K: Key + 'static> ReadOnlyBlobTable<K> {
+    pub(crate) fn new(table: ReadOnlyChunkTable<K>) -> Self {
+        Self { table }
+    }
+
+    /// Returns a reader for the blob stored under `key`, if it exists
+    pub fn reader<'a>(&self, key: impl Borrow<K::SelfType<'a>>) -> Result<Option<BlobReader<'_>>> {
+        reader::<K, _>(&self.table, K::as_bytes(key.borrow()).as_ref().to_vec())
+    }
+
+    /// Returns the length of the blob stored under `key`, if it exists
+    pub fn blob_len<'a>(&self, key: impl Borrow<K::SelfType<'a>>) -> Result<Option<u64>> {
+        blob_len::<K, _>(&self.table, K::as_bytes(key.borrow()).as_ref())
+    }
+}
+
+impl<K: Key + 'static> Debug for ReadOnlyBlobTable<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOnlyBlobTable").finish_non_exhaustive()
+    }
+}
+
+/// Writes a blob to a [`BlobTable`]
+pub struct BlobWriter<'a, 'txn, K: Key + 'static> {
+    table: &'a mut ChunkTable<'txn, K>,
+    key: Vec<u8>,
+    // Offset of the start of the buffer in the blob
+    offset: u64,
+    buffer: Vec<u8>,
+}
+
+impl<'a, 'txn, K: Key + 'static> BlobWriter<'a, 'txn, K> {
+    /// Writes any remaining data to the table, and returns the length of the blob
+    pub fn finish(mut self) -> Result<u64> {
+        self.write_buffer()?;
+        Ok(self.offset)
+    }
+
+    fn write_chunk(&mut self, data: &[u8]) -> Result {
+        self.table
+            .insert((K::from_bytes(&self.key), self.offset), data)?;
+        self.offset += u64::try_from(data.len()).unwrap();
+        Ok(())
+    }
+
+    fn write_buffer(&mut self) -> Result {
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            self.write_chunk(&buffer)?;
+            self.buffer = buffer;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'txn, K: Key + 'static> Write for BlobWriter<'a, 'txn, K> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write full chunks directly, rather than copying them into the buffer
+        if self.buffer.is_empty() && buf.len() >= CHUNK_SIZE {
+            self.write_chunk(&buf[..CHUNK_SIZE]).map_err(to_io_error)?;
+            return Ok(CHUNK_SIZE);
+        }
+        let len = min(buf.len(), CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_buffer().map_err(to_io_error)?;
+        }
+        Ok(len)
+    }
+
+    /// Writes any buffered data to the table as a chunk, which may be smaller than the others
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer().map_err(to_io_error)
+    }
+}
+
+impl<'a, 'txn, K: Key + 'static> Drop for BlobWriter<'a, 'txn, K> {
+    fn drop(&mut self) {
+        let _ = self.write_buffer();
+    }
+}
+
+/// Reads a blob from a [`BlobTable`] or [`ReadOnlyBlobTable`]
+pub struct BlobReader<'a> {
+    find_chunk: Box<dyn Fn(u64) -> Result<Option<Chunk<'a>>> + 'a>,
+    len: u64,
+    position: u64,
+    // The chunk which was last read from, and its offset
+    chunk: Option<Chunk<'a>>,
+}
+
+impl<'a> BlobReader<'a> {
+    /// Returns the length of the blob
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a> Read for BlobReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+        let position = self.position;
+        let in_chunk = |(offset, chunk): &Chunk| {
+            *offset <= position && position - offset < u64::try_from(chunk.value().len()).unwrap()
+        };
+        if !self.chunk.as_ref().map(in_chunk).unwrap_or(false) {
+            self.chunk = (self.find_chunk)(position).map_err(to_io_error)?;
+        }
+        let (offset, chunk) = match self.chunk {
+            Some(ref chunk) if in_chunk(chunk) => chunk,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Blob has no data at offset {position}"),
+                ))
+            }
+        };
+
+        let data = &chunk.value()[usize::try_from(position - offset).unwrap()..];
+        let len = min(buf.len(), data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.position += u64::try_from(len).unwrap();
+        Ok(len)
+    }
+}
+
+impl<'a> Seek for BlobReader<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(x) => Some(x),
+            SeekFrom::End(x) => self.len.checked_add_signed(x),
+            SeekFrom::Current(x) => self.position.checked_add_signed(x),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+impl<'a> Debug for BlobReader<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobReader")
+            .field("len", &self.len)
+            .field("position", &self.position)
+            .finish_non_exhaustive()
+    }
+}
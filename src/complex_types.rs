@@ -1,4 +1,5 @@
-use crate::types::{TypeName, Value};
+use crate::types::{Key, TypeName, Value};
+use std::cmp::Ordering;
 
 // Encode len as a varint and store it at the end of output
 fn encode_varint_len(len: usize, output: &mut Vec<u8>) {
@@ -32,6 +33,23 @@ fn decode_varint_len(data: &[u8]) -> (usize, usize) {
     }
 }
 
+// Returns the encoded elements of a Vec<T>
+fn encoded_elements<T: Value>(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let (elements, mut offset) = decode_varint_len(data);
+    (0..elements).map(move |_| {
+        let element_len = if let Some(len) = T::fixed_width() {
+            len
+        } else {
+            let (len, consumed) = decode_varint_len(&data[offset..]);
+            offset += consumed;
+            len
+        };
+        let element = &data[offset..(offset + element_len)];
+        offset += element_len;
+        element
+    })
+}
+
 impl<T: Value> Value for Vec<T> {
     type SelfType<'a> = Vec<T::SelfType<'a>>
     where
@@ -91,3 +109,22 @@ impl<T: Value> Value for Vec<T> {
         TypeName::internal(&format!("Vec<{}>", T::type_name().name()))
     }
 }
+
+impl<T: Key> Key for Vec<T> {
+    // Vecs are ordered lexicographically by their elements
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        let mut elements1 = encoded_elements::<T>(data1);
+        let mut elements2 = encoded_elements::<T>(data2);
+        loop {
+            match (elements1.next(), elements2.next()) {
+                (Some(element1), Some(element2)) => match T::compare(element1, element2) {
+                    Ordering::Equal => {}
+                    order => return order,
+                },
+                (Some(_), None) => return Ordering::Greater,
+                (None, Some(_)) => return Ordering::Less,
+                (None, None) => return Ordering::Equal,
+            }
+        }
+    }
+}
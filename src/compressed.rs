@@ -0,0 +1,72 @@
+use crate::types::{TypeName, Value};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+// The first byte of each value records how the rest of it is compressed
+const UNCOMPRESSED: u8 = 0;
+const LZ4: u8 = 1;
+
+/// Stores values of type `V` compressed with LZ4
+///
+/// Values whose encoding is shorter than `MIN_SIZE` bytes, or which don't become smaller when
+/// compressed, are stored uncompressed. Each value is prefixed with a byte recording whether, and
+/// how, it was compressed, so `MIN_SIZE` can be changed without affecting existing values.
+///
+/// Decompressed values can't be borrowed from the database, so `V` must be a type which is read as
+/// an owned value, such as `String`, `Vec<u8>`, or `redb::serde::Json`.
+#[derive(Debug)]
+pub struct Compressed<V, const MIN_SIZE: usize = 64>(PhantomData<V>);
+
+impl<V, T, const MIN_SIZE: usize> Value for Compressed<V, MIN_SIZE>
+where
+    V: for<'a> Value<SelfType<'a> = T> + 'static,
+    T: Debug + 'static,
+{
+    type SelfType<'a> = T
+    where
+        Self: 'a;
+    type AsBytes<'a> = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> T
+    where
+        Self: 'a,
+    {
+        match data[0] {
+            UNCOMPRESSED => V::from_bytes(&data[1..]),
+            LZ4 => V::from_bytes(&lz4_flex::decompress_size_prepended(&data[1..]).unwrap()),
+            algorithm => unreachable!("unknown compression algorithm: {algorithm}"),
+        }
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a T) -> Vec<u8>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let bytes = V::as_bytes(value);
+        let bytes = bytes.as_ref();
+        if bytes.len() >= MIN_SIZE {
+            let compressed = lz4_flex::compress_prepend_size(bytes);
+            if compressed.len() < bytes.len() {
+                let mut result = Vec::with_capacity(compressed.len() + 1);
+                result.push(LZ4);
+                result.extend_from_slice(&compressed);
+                return result;
+            }
+        }
+        let mut result = Vec::with_capacity(bytes.len() + 1);
+        result.push(UNCOMPRESSED);
+        result.extend_from_slice(bytes);
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal(&format!("redb::Compressed<{}>", V::type_name().name()))
+    }
+}
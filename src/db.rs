@@ -1,12 +1,14 @@
+use crate::merge::MergeOperator;
 use crate::transaction_tracker::{SavepointId, TransactionId, TransactionTracker};
 use crate::tree_store::{
-    AllPageNumbersBtreeIter, BtreeHeader, BtreeRangeIter, FreedPageList, FreedTableKey,
-    InternalTableDefinition, PageHint, PageNumber, RawBtree, SerializedSavepoint, TableTreeMut,
-    TableType, TransactionalMemory, PAGE_SIZE,
+    AllPageNumbersBtreeIter, BtreeHeader, BtreeRangeIter, ChecksumAlgorithm, FreedPageList,
+    FreedTableKey, InternalTableDefinition, PageHint, PageNumber, RawBtree, SerializedSavepoint,
+    TableTreeMut, TableType, TransactionalMemory, PAGE_SIZE,
 };
 use crate::types::{Key, Value};
 use crate::{
-    CompactionError, DatabaseError, Durability, ReadOnlyTable, SavepointError, StorageError,
+    CompactionError, DatabaseError, Durability, ReadOnlyTable, SavepointError, Schema, SchemaError,
+    StorageError,
 };
 use crate::{ReadTransaction, Result, WriteTransaction};
 use std::fmt::{Debug, Display, Formatter};
@@ -109,6 +111,7 @@ impl Sealed for UntypedMultimapTableHandle {}
 /// that is stored or retreived from the table
 pub struct TableDefinition<'a, K: Key + 'static, V: Value + 'static> {
     name: &'a str,
+    merge_operator: Option<&'static dyn MergeOperator<V>>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -123,10 +126,25 @@ impl<'a, K: Key + 'static, V: Value + 'static> TableDefinition<'a, K, V> {
         assert!(!name.is_empty());
         Self {
             name,
+            merge_operator: None,
             _key_type: PhantomData,
             _value_type: PhantomData,
         }
     }
+
+    /// Registers `operator` as the merge operator of the table, which is used by
+    /// [`crate::Table::merge`]
+    ///
+    /// The merge operator is not stored in the database, so it must be registered each time the
+    /// table is opened
+    pub const fn with_merge_operator(mut self, operator: &'static dyn MergeOperator<V>) -> Self {
+        self.merge_operator = Some(operator);
+        self
+    }
+
+    pub(crate) fn merge_operator(&self) -> Option<&'static dyn MergeOperator<V>> {
+        self.merge_operator
+    }
 }
 
 impl<'a, K: Key + 'static, V: Value + 'static> TableHandle for TableDefinition<'a, K, V> {
@@ -212,6 +230,54 @@ impl<'a, K: Key + 'static, V: Key + 'static> Display for MultimapTableDefinition
     }
 }
 
+/// Defines the name and key type of a blob table
+///
+/// A [`BlobTableDefinition`] should be opened for use by calling [`ReadTransaction::open_blob_table`] or [`WriteTransaction::open_blob_table`]
+///
+/// Blob tables map each key to a byte string which is read and written as a stream, so that it
+/// never needs to be held in memory all at once
+pub struct BlobTableDefinition<'a, K: Key + 'static> {
+    name: &'a str,
+    _key_type: PhantomData<K>,
+}
+
+impl<'a, K: Key + 'static> BlobTableDefinition<'a, K> {
+    /// Construct a new blob table with given `name`
+    ///
+    /// ## Invariant
+    ///
+    /// `name` must not be empty.
+    pub const fn new(name: &'a str) -> Self {
+        assert!(!name.is_empty());
+        Self {
+            name,
+            _key_type: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: Key + 'static> TableHandle for BlobTableDefinition<'a, K> {
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+impl<K: Key> Sealed for BlobTableDefinition<'_, K> {}
+
+impl<'a, K: Key + 'static> Clone for BlobTableDefinition<'a, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K: Key + 'static> Copy for BlobTableDefinition<'a, K> {}
+
+impl<'a, K: Key + 'static> Display for BlobTableDefinition<'a, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}<{}>", self.name, K::type_name().name())
+    }
+}
+
 pub(crate) struct TransactionGuard {
     transaction_tracker: Option<Arc<TransactionTracker>>,
     transaction_id: Option<TransactionId>,
@@ -402,6 +468,23 @@ impl Database {
         Ok(was_clean)
     }
 
+    /// Applies a [`Schema`] to the database, in a single write transaction
+    ///
+    /// If the database was written with an older version of the schema, the registered migrations
+    /// are run, in order, to bring it up to date. Then every table in the schema is created, if it
+    /// does not exist, or checked against its definition. A database to which no schema has been
+    /// applied is at the schema's initial version, unless it has no tables, in which case it is
+    /// stamped with the schema's version.
+    ///
+    /// Nothing is committed if an error is returned
+    pub fn apply_schema(&self, schema: &Schema) -> Result<(), SchemaError> {
+        let txn = self.begin_write().map_err(|e| e.into_storage_error())?;
+        schema.apply(&txn)?;
+        txn.commit().map_err(|e| e.into_storage_error())?;
+
+        Ok(())
+    }
+
     /// Compacts the database file
     ///
     /// Returns `true` if compaction was performed, and `false` if no futher compaction was possible
@@ -581,7 +664,10 @@ impl Database {
         for entry in iter {
             let definition = entry?.value();
             definition.visit_all_pages(mem.clone(), |path| {
-                mem.mark_page_allocated(path.page_number());
+                // Pages shared by cloned tables are visited once for each table
+                if !mem.is_allocated(path.page_number()) {
+                    mem.mark_page_allocated(path.page_number());
+                }
                 Ok(())
             })?;
         }
@@ -677,12 +763,14 @@ impl Database {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new(
         file: Box<dyn StorageBackend>,
         page_size: usize,
         region_size: Option<u64>,
         read_cache_size_bytes: usize,
         write_cache_size_bytes: usize,
+        checksum: ChecksumAlgorithm,
         repair_callback: &(dyn Fn(&mut RepairSession) + 'static),
     ) -> Result<Self, DatabaseError> {
         #[cfg(feature = "logging")]
@@ -695,6 +783,7 @@ impl Database {
             region_size,
             read_cache_size_bytes,
             write_cache_size_bytes,
+            checksum,
         )?;
         let mut mem = Arc::new(mem);
         if mem.needs_repair()? {
@@ -821,7 +910,9 @@ pub struct Builder {
     region_size: Option<u64>,
     read_cache_size_bytes: usize,
     write_cache_size_bytes: usize,
+    checksum: ChecksumAlgorithm,
     repair_callback: Box<dyn Fn(&mut RepairSession)>,
+    schema: Option<Schema<'static>>,
 }
 
 impl Builder {
@@ -842,7 +933,9 @@ impl Builder {
             read_cache_size_bytes: 0,
             // TODO: Default should probably take into account the total system memory
             write_cache_size_bytes: 0,
+            checksum: ChecksumAlgorithm::Xxh3,
             repair_callback: Box::new(|_| {}),
+            schema: None,
         };
 
         result.set_cache_size(1024 * 1024 * 1024);
@@ -886,6 +979,41 @@ impl Builder {
         self
     }
 
+    /// Compute page and commit slot checksums with BLAKE3, keyed with `key`, instead of XXH3
+    ///
+    /// XXH3 detects accidental corruption, but is not collision resistant, so someone who can
+    /// modify the file can also forge the checksums. Keyed checksums can't be forged without the
+    /// key, so modifications are detected when committing with [`Durability::Paranoid`] or by
+    /// [`Database::check_integrity`].
+    ///
+    /// This only takes effect when a new database is created, and the same key must be set every
+    /// time the database is opened. Opening it without the key, or with a different key, fails
+    /// with [`DatabaseError::InvalidChecksumKey`].
+    ///
+    /// ## Defaults
+    ///
+    /// Default to XXH3 checksums
+    #[cfg(feature = "keyed_checksums")]
+    pub fn set_checksum_key(&mut self, key: [u8; 32]) -> &mut Self {
+        self.checksum = ChecksumAlgorithm::Blake3(key);
+        self
+    }
+
+    /// Set a [`Schema`] which is applied every time the database is opened
+    ///
+    /// Opening the database fails with [`DatabaseError::Schema`] if the schema can't be applied
+    pub fn set_schema(&mut self, schema: Schema<'static>) -> &mut Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    fn apply_schema(&self, db: Database) -> Result<Database, DatabaseError> {
+        if let Some(schema) = &self.schema {
+            db.apply_schema(schema)?;
+        }
+        Ok(db)
+    }
+
     #[cfg(any(test, fuzzing))]
     pub fn set_region_size(&mut self, size: u64) -> &mut Self {
         assert!(size.is_power_of_two());
@@ -911,8 +1039,10 @@ impl Builder {
             self.region_size,
             self.read_cache_size_bytes,
             self.write_cache_size_bytes,
+            self.checksum.clone(),
             &self.repair_callback,
         )
+        .and_then(|db| self.apply_schema(db))
     }
 
     /// Opens an existing redb database.
@@ -929,8 +1059,10 @@ impl Builder {
             None,
             self.read_cache_size_bytes,
             self.write_cache_size_bytes,
+            self.checksum.clone(),
             &self.repair_callback,
         )
+        .and_then(|db| self.apply_schema(db))
     }
 
     /// Open an existing or create a new database in the given `file`.
@@ -943,8 +1075,10 @@ impl Builder {
             self.region_size,
             self.read_cache_size_bytes,
             self.write_cache_size_bytes,
+            self.checksum.clone(),
             &self.repair_callback,
         )
+        .and_then(|db| self.apply_schema(db))
     }
 
     /// Open an existing or create a new database with the given backend.
@@ -958,8 +1092,10 @@ impl Builder {
             self.region_size,
             self.read_cache_size_bytes,
             self.write_cache_size_bytes,
+            self.checksum.clone(),
             &self.repair_callback,
         )
+        .and_then(|db| self.apply_schema(db))
     }
 }
 
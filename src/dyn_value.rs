@@ -0,0 +1,271 @@
+use crate::types::{Key, TypeName, Value};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// Each value starts with a tag, and the tags are ordered so that values of different variants
+// compare in the order the variants are declared. 0 is reserved for terminating lists and maps
+const NULL: u8 = 1;
+const FALSE: u8 = 2;
+const TRUE: u8 = 3;
+const INTEGER: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const BYTES: u8 = 7;
+const LIST: u8 = 8;
+const MAP: u8 = 9;
+
+const TERMINATOR: u8 = 0;
+const MAP_ENTRY: u8 = 1;
+// Zero bytes in strings and byte strings are escaped, so that a terminator can't occur in them
+const ESCAPE: u8 = 0xFF;
+
+/// A self-describing value, for storing data whose structure is not known in advance
+///
+/// `DynValue` can be used as both a key and a value. Values are encoded so that comparing the
+/// encoded bytes gives the same result as comparing the values: values of different variants are
+/// ordered by the variant (null < bool < integer < float < string < bytes < list < map), and
+/// values of the same variant are ordered by their contents. Floats are ordered by
+/// [`f64::total_cmp`], and lists and maps are ordered lexicographically by their elements.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<DynValue>),
+    Map(BTreeMap<String, DynValue>),
+}
+
+impl DynValue {
+    fn encode(&self, output: &mut Vec<u8>) {
+        match self {
+            DynValue::Null => output.push(NULL),
+            DynValue::Bool(false) => output.push(FALSE),
+            DynValue::Bool(true) => output.push(TRUE),
+            DynValue::Integer(value) => {
+                output.push(INTEGER);
+                // Flip the sign bit, so that negative numbers are ordered before positive ones
+                let mut bytes = value.to_be_bytes();
+                bytes[0] ^= 0x80;
+                output.extend_from_slice(&bytes);
+            }
+            DynValue::Float(value) => {
+                output.push(FLOAT);
+                // Same transformation as f64::total_cmp(): flip all the bits of negative numbers,
+                // so that they're ordered by magnitude in reverse, and the sign bit of the rest
+                let bits = value.to_bits();
+                let bits = if bits >> 63 == 1 {
+                    !bits
+                } else {
+                    bits ^ (1 << 63)
+                };
+                output.extend_from_slice(&bits.to_be_bytes());
+            }
+            DynValue::String(value) => {
+                output.push(STRING);
+                encode_escaped(value.as_bytes(), output);
+            }
+            DynValue::Bytes(value) => {
+                output.push(BYTES);
+                encode_escaped(value, output);
+            }
+            DynValue::List(values) => {
+                output.push(LIST);
+                for value in values {
+                    value.encode(output);
+                }
+                output.push(TERMINATOR);
+            }
+            DynValue::Map(entries) => {
+                output.push(MAP);
+                for (key, value) in entries {
+                    output.push(MAP_ENTRY);
+                    encode_escaped(key.as_bytes(), output);
+                    value.encode(output);
+                }
+                output.push(TERMINATOR);
+            }
+        }
+    }
+
+    // Decodes the value at the start of data, and returns it along with the number of bytes consumed
+    fn decode(data: &[u8]) -> (DynValue, usize) {
+        match data[0] {
+            NULL => (DynValue::Null, 1),
+            FALSE => (DynValue::Bool(false), 1),
+            TRUE => (DynValue::Bool(true), 1),
+            INTEGER => {
+                let mut bytes: [u8; 8] = data[1..9].try_into().unwrap();
+                bytes[0] ^= 0x80;
+                (DynValue::Integer(i64::from_be_bytes(bytes)), 9)
+            }
+            FLOAT => {
+                let bits = u64::from_be_bytes(data[1..9].try_into().unwrap());
+                let bits = if bits >> 63 == 1 {
+                    bits ^ (1 << 63)
+                } else {
+                    !bits
+                };
+                (DynValue::Float(f64::from_bits(bits)), 9)
+            }
+            STRING => {
+                let (value, consumed) = decode_escaped(&data[1..]);
+                (
+                    DynValue::String(String::from_utf8(value).unwrap()),
+                    consumed + 1,
+                )
+            }
+            BYTES => {
+                let (value, consumed) = decode_escaped(&data[1..]);
+                (DynValue::Bytes(value), consumed + 1)
+            }
+            LIST => {
+                let mut offset = 1;
+                let mut values = vec![];
+                while data[offset] != TERMINATOR {
+                    let (value, consumed) = DynValue::decode(&data[offset..]);
+                    values.push(value);
+                    offset += consumed;
+                }
+                (DynValue::List(values), offset + 1)
+            }
+            MAP => {
+                let mut offset = 1;
+                let mut entries = BTreeMap::new();
+                while data[offset] != TERMINATOR {
+                    assert_eq!(data[offset], MAP_ENTRY);
+                    offset += 1;
+                    let (key, consumed) = decode_escaped(&data[offset..]);
+                    offset += consumed;
+                    let (value, consumed) = DynValue::decode(&data[offset..]);
+                    offset += consumed;
+                    entries.insert(String::from_utf8(key).unwrap(), value);
+                }
+                (DynValue::Map(entries), offset + 1)
+            }
+            tag => unreachable!("invalid DynValue tag: {tag}"),
+        }
+    }
+}
+
+// Writes data followed by a terminator, with any zero bytes escaped. A shorter string is then
+// ordered before any string that it's a prefix of, since the terminator sorts before any byte
+fn encode_escaped(data: &[u8], output: &mut Vec<u8>) {
+    for byte in data {
+        output.push(*byte);
+        if *byte == TERMINATOR {
+            output.push(ESCAPE);
+        }
+    }
+    output.push(TERMINATOR);
+}
+
+// Returns the unescaped data, and the number of bytes consumed including the terminator
+fn decode_escaped(data: &[u8]) -> (Vec<u8>, usize) {
+    let mut result = vec![];
+    let mut offset = 0;
+    loop {
+        let byte = data[offset];
+        offset += 1;
+        if byte == TERMINATOR {
+            if data.get(offset) == Some(&ESCAPE) {
+                offset += 1;
+            } else {
+                return (result, offset);
+            }
+        }
+        result.push(byte);
+    }
+}
+
+impl Value for DynValue {
+    type SelfType<'a> = DynValue
+    where
+        Self: 'a;
+    type AsBytes<'a> = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> DynValue
+    where
+        Self: 'a,
+    {
+        let (value, consumed) = DynValue::decode(data);
+        assert_eq!(consumed, data.len());
+        value
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a DynValue) -> Vec<u8>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut result = vec![];
+        value.encode(&mut result);
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("redb::DynValue")
+    }
+}
+
+impl Key for DynValue {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
+
+impl From<bool> for DynValue {
+    fn from(value: bool) -> Self {
+        DynValue::Bool(value)
+    }
+}
+
+impl From<i64> for DynValue {
+    fn from(value: i64) -> Self {
+        DynValue::Integer(value)
+    }
+}
+
+impl From<f64> for DynValue {
+    fn from(value: f64) -> Self {
+        DynValue::Float(value)
+    }
+}
+
+impl From<&str> for DynValue {
+    fn from(value: &str) -> Self {
+        DynValue::String(value.to_string())
+    }
+}
+
+impl From<String> for DynValue {
+    fn from(value: String) -> Self {
+        DynValue::String(value)
+    }
+}
+
+impl From<&[u8]> for DynValue {
+    fn from(value: &[u8]) -> Self {
+        DynValue::Bytes(value.to_vec())
+    }
+}
+
+impl From<Vec<DynValue>> for DynValue {
+    fn from(value: Vec<DynValue>) -> Self {
+        DynValue::List(value)
+    }
+}
+
+impl From<BTreeMap<String, DynValue>> for DynValue {
+    fn from(value: BTreeMap<String, DynValue>) -> Self {
+        DynValue::Map(value)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::tree_store::{FILE_FORMAT_VERSION2, MAX_VALUE_LENGTH};
+use crate::tree_store::{FILE_FORMAT_VERSION3, MAX_VALUE_LENGTH};
 use crate::{ReadTransaction, TypeName};
 use std::fmt::{Display, Formatter};
 use std::sync::PoisonError;
@@ -15,6 +15,8 @@ pub enum StorageError {
     Io(io::Error),
     PreviousIo,
     LockPoisoned(&'static panic::Location<'static>),
+    /// The table was opened without a merge operator
+    MissingMergeOperator(String),
 }
 
 impl<T> From<PoisonError<T>> for StorageError {
@@ -37,6 +39,7 @@ impl From<StorageError> for Error {
             StorageError::Io(x) => Error::Io(x),
             StorageError::PreviousIo => Error::PreviousIo,
             StorageError::LockPoisoned(location) => Error::LockPoisoned(location),
+            StorageError::MissingMergeOperator(table) => Error::MissingMergeOperator(table),
         }
     }
 }
@@ -66,6 +69,9 @@ impl Display for StorageError {
             StorageError::LockPoisoned(location) => {
                 write!(f, "Poisoned internal lock: {location}")
             }
+            StorageError::MissingMergeOperator(table) => {
+                write!(f, "Table '{table}' has no merge operator")
+            }
         }
     }
 }
@@ -93,6 +99,27 @@ pub enum TableError {
     },
     /// Table name does not match any table in database
     TableDoesNotExist(String),
+    /// A table with the given name already exists
+    TableAlreadyExists(String),
+    /// The table was modified in the current transaction, so it can't be cloned until committed
+    TableHasUncommittedChanges(String),
+    /// The table's key type has no known ordering, so it can't be opened without its types
+    UnorderableKeyType {
+        table: String,
+        key: TypeName,
+    },
+    /// The key doesn't have the width of the table's fixed width key type
+    InvalidKeyWidth {
+        table: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The value doesn't have the width of the table's fixed width value type
+    InvalidValueWidth {
+        table: String,
+        expected: usize,
+        actual: usize,
+    },
     // Tables cannot be opened for writing multiple times, since they could retrieve immutable &
     // mutable references to the same dirty pages, or multiple mutable references via insert_reserve()
     TableAlreadyOpen(String, &'static panic::Location<'static>),
@@ -108,6 +135,11 @@ impl TableError {
             | TableError::TableIsNotMultimap(_)
             | TableError::TypeDefinitionChanged { .. }
             | TableError::TableDoesNotExist(_)
+            | TableError::TableAlreadyExists(_)
+            | TableError::TableHasUncommittedChanges(_)
+            | TableError::UnorderableKeyType { .. }
+            | TableError::InvalidKeyWidth { .. }
+            | TableError::InvalidValueWidth { .. }
             | TableError::TableAlreadyOpen(_, _) => {
                 StorageError::Corrupted(format!("{}: {}", msg, &self))
             }
@@ -134,6 +166,31 @@ impl From<TableError> for Error {
             TableError::TableIsMultimap(table) => Error::TableIsMultimap(table),
             TableError::TableIsNotMultimap(table) => Error::TableIsNotMultimap(table),
             TableError::TableDoesNotExist(table) => Error::TableDoesNotExist(table),
+            TableError::TableAlreadyExists(table) => Error::TableAlreadyExists(table),
+            TableError::TableHasUncommittedChanges(table) => {
+                Error::TableHasUncommittedChanges(table)
+            }
+            TableError::UnorderableKeyType { table, key } => {
+                Error::UnorderableKeyType { table, key }
+            }
+            TableError::InvalidKeyWidth {
+                table,
+                expected,
+                actual,
+            } => Error::InvalidKeyWidth {
+                table,
+                expected,
+                actual,
+            },
+            TableError::InvalidValueWidth {
+                table,
+                expected,
+                actual,
+            } => Error::InvalidValueWidth {
+                table,
+                expected,
+                actual,
+            },
             TableError::TableAlreadyOpen(name, location) => Error::TableAlreadyOpen(name, location),
             TableError::Storage(storage) => storage.into(),
         }
@@ -179,6 +236,39 @@ impl Display for TableError {
             TableError::TableDoesNotExist(table) => {
                 write!(f, "Table '{table}' does not exist")
             }
+            TableError::TableAlreadyExists(table) => {
+                write!(f, "Table '{table}' already exists")
+            }
+            TableError::TableHasUncommittedChanges(table) => {
+                write!(f, "Table '{table}' has uncommitted changes")
+            }
+            TableError::UnorderableKeyType { table, key } => {
+                write!(
+                    f,
+                    "Table '{table}' has key type {}, which has no known ordering",
+                    key.name()
+                )
+            }
+            TableError::InvalidKeyWidth {
+                table,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Table '{table}' has {expected} byte keys, but the key is {actual} bytes"
+                )
+            }
+            TableError::InvalidValueWidth {
+                table,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Table '{table}' has {expected} byte values, but the value is {actual} bytes"
+                )
+            }
             TableError::TableAlreadyOpen(name, location) => {
                 write!(f, "Table '{name}' already opened at: {location}")
             }
@@ -199,6 +289,16 @@ pub enum DatabaseError {
     RepairAborted,
     /// The database file is in an old file format and must be manually upgraded
     UpgradeRequired(u8),
+    /// The database file uses keyed checksums, but the `keyed_checksums` feature is not enabled
+    ChecksumUnsupported,
+    /// The key supplied to `EncryptedBackend` is not the one that the database was
+    /// encrypted with
+    InvalidEncryptionKey,
+    /// The database uses keyed checksums, and the key set with `Builder::set_checksum_key` is
+    /// missing or incorrect, or a key was set for a database which doesn't use keyed checksums
+    InvalidChecksumKey,
+    /// The schema set with `Builder::set_schema` could not be applied
+    Schema(SchemaError),
     /// Error from underlying storage
     Storage(StorageError),
 }
@@ -209,6 +309,10 @@ impl From<DatabaseError> for Error {
             DatabaseError::DatabaseAlreadyOpen => Error::DatabaseAlreadyOpen,
             DatabaseError::RepairAborted => Error::RepairAborted,
             DatabaseError::UpgradeRequired(x) => Error::UpgradeRequired(x),
+            DatabaseError::ChecksumUnsupported => Error::ChecksumUnsupported,
+            DatabaseError::InvalidEncryptionKey => Error::InvalidEncryptionKey,
+            DatabaseError::InvalidChecksumKey => Error::InvalidChecksumKey,
+            DatabaseError::Schema(err) => err.into(),
             DatabaseError::Storage(storage) => storage.into(),
         }
     }
@@ -226,11 +330,29 @@ impl From<StorageError> for DatabaseError {
     }
 }
 
+impl From<SchemaError> for DatabaseError {
+    fn from(err: SchemaError) -> DatabaseError {
+        DatabaseError::Schema(err)
+    }
+}
+
 impl Display for DatabaseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             DatabaseError::UpgradeRequired(actual) => {
-                write!(f, "Manual upgrade required. Expected file format version {FILE_FORMAT_VERSION2}, but file is version {actual}")
+                write!(f, "Manual upgrade required. Expected file format version {FILE_FORMAT_VERSION3}, but file is version {actual}")
+            }
+            DatabaseError::ChecksumUnsupported => {
+                write!(
+                    f,
+                    "Database uses keyed checksums, but the keyed_checksums feature is not enabled."
+                )
+            }
+            DatabaseError::InvalidEncryptionKey => {
+                write!(f, "Invalid encryption key.")
+            }
+            DatabaseError::InvalidChecksumKey => {
+                write!(f, "Checksum key is missing or does not match the database.")
             }
             DatabaseError::RepairAborted => {
                 write!(f, "Database repair aborted.")
@@ -238,6 +360,7 @@ impl Display for DatabaseError {
             DatabaseError::DatabaseAlreadyOpen => {
                 write!(f, "Database already open. Cannot acquire lock.")
             }
+            DatabaseError::Schema(schema) => schema.fmt(f),
             DatabaseError::Storage(storage) => storage.fmt(f),
         }
     }
@@ -434,6 +557,167 @@ impl Display for CommitError {
 
 impl std::error::Error for CommitError {}
 
+/// Errors related to importing a database export
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ImportError {
+    /// The data is not a valid export, or has been corrupted
+    InvalidFormat(String),
+    /// The export was written in a format version which is not supported
+    UnsupportedVersion(u32),
+    /// A table in the export already exists in the database
+    TableAlreadyExists(String),
+    /// Error from underlying storage
+    Storage(StorageError),
+}
+
+impl From<ImportError> for Error {
+    fn from(err: ImportError) -> Error {
+        match err {
+            ImportError::InvalidFormat(msg) => Error::InvalidImport(msg),
+            ImportError::UnsupportedVersion(version) => {
+                Error::InvalidImport(format!("unsupported format version {version}"))
+            }
+            ImportError::TableAlreadyExists(table) => Error::TableAlreadyExists(table),
+            ImportError::Storage(storage) => storage.into(),
+        }
+    }
+}
+
+impl From<StorageError> for ImportError {
+    fn from(err: StorageError) -> ImportError {
+        ImportError::Storage(err)
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::InvalidFormat(msg) => {
+                write!(f, "Invalid import data: {msg}")
+            }
+            ImportError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported import format version: {version}")
+            }
+            ImportError::TableAlreadyExists(table) => {
+                write!(f, "Table '{table}' already exists")
+            }
+            ImportError::Storage(storage) => storage.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Errors related to bulk loading a table
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BulkLoadError {
+    /// The table already contains entries
+    TableNotEmpty(String),
+    /// The entries are not in strictly ascending order by key
+    UnsortedInput,
+    /// Error from underlying storage
+    Storage(StorageError),
+}
+
+impl From<BulkLoadError> for Error {
+    fn from(err: BulkLoadError) -> Error {
+        match err {
+            BulkLoadError::TableNotEmpty(table) => Error::TableNotEmpty(table),
+            BulkLoadError::UnsortedInput => Error::UnsortedInput,
+            BulkLoadError::Storage(storage) => storage.into(),
+        }
+    }
+}
+
+impl From<StorageError> for BulkLoadError {
+    fn from(err: StorageError) -> BulkLoadError {
+        BulkLoadError::Storage(err)
+    }
+}
+
+impl Display for BulkLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BulkLoadError::TableNotEmpty(table) => {
+                write!(f, "Table '{table}' is not empty")
+            }
+            BulkLoadError::UnsortedInput => {
+                write!(f, "Entries are not sorted by key")
+            }
+            BulkLoadError::Storage(storage) => storage.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BulkLoadError {}
+
+/// Errors related to applying a [`crate::Schema`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SchemaError {
+    /// The database was written with a newer version of the schema
+    UnsupportedVersion { stored: u64, supported: u64 },
+    /// No migration is registered from the given version
+    MissingMigration(u64),
+    /// The migration from the given version returned an error
+    MigrationFailed { from: u64, error: Box<Error> },
+    /// A table does not match its definition in the schema
+    Table(TableError),
+    /// Error from underlying storage
+    Storage(StorageError),
+}
+
+impl From<SchemaError> for Error {
+    fn from(err: SchemaError) -> Error {
+        match err {
+            SchemaError::UnsupportedVersion { stored, supported } => {
+                Error::UnsupportedSchemaVersion { stored, supported }
+            }
+            SchemaError::MissingMigration(from) => Error::MissingSchemaMigration(from),
+            SchemaError::MigrationFailed { error, .. } => *error,
+            SchemaError::Table(table) => table.into(),
+            SchemaError::Storage(storage) => storage.into(),
+        }
+    }
+}
+
+impl From<TableError> for SchemaError {
+    fn from(err: TableError) -> SchemaError {
+        SchemaError::Table(err)
+    }
+}
+
+impl From<StorageError> for SchemaError {
+    fn from(err: StorageError) -> SchemaError {
+        SchemaError::Storage(err)
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::UnsupportedVersion { stored, supported } => {
+                write!(
+                    f,
+                    "Database has schema version {stored}, which is newer than the supported version {supported}"
+                )
+            }
+            SchemaError::MissingMigration(from) => {
+                write!(f, "No migration from schema version {from}")
+            }
+            SchemaError::MigrationFailed { from, error } => {
+                write!(f, "Migration from schema version {from} failed: {error}")
+            }
+            SchemaError::Table(table) => table.fmt(f),
+            SchemaError::Storage(storage) => storage.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
 /// Superset of all other errors that can occur. Convenience enum so that users can convert all errors into a single type
 #[derive(Debug)]
 #[non_exhaustive]
@@ -457,6 +741,14 @@ pub enum Error {
     Corrupted(String),
     /// The database file is in an old file format and must be manually upgraded
     UpgradeRequired(u8),
+    /// The database file uses keyed checksums, but the `keyed_checksums` feature is not enabled
+    ChecksumUnsupported,
+    /// The key supplied to `EncryptedBackend` is not the one that the database was
+    /// encrypted with
+    InvalidEncryptionKey,
+    /// The database uses keyed checksums, and the key set with `Builder::set_checksum_key` is
+    /// missing or incorrect, or a key was set for a database which doesn't use keyed checksums
+    InvalidChecksumKey,
     /// The value being inserted exceeds the maximum of 3GiB
     ValueTooLarge(usize),
     /// Table types didn't match.
@@ -476,6 +768,42 @@ pub enum Error {
     },
     /// Table name does not match any table in database
     TableDoesNotExist(String),
+    /// A table with the given name already exists
+    TableAlreadyExists(String),
+    /// The table was modified in the current transaction, so it can't be cloned until committed
+    TableHasUncommittedChanges(String),
+    /// The table's key type has no known ordering, so it can't be opened without its types
+    UnorderableKeyType {
+        table: String,
+        key: TypeName,
+    },
+    /// The key doesn't have the width of the table's fixed width key type
+    InvalidKeyWidth {
+        table: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The value doesn't have the width of the table's fixed width value type
+    InvalidValueWidth {
+        table: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The table was opened without a merge operator
+    MissingMergeOperator(String),
+    /// The data being imported is not a valid export
+    InvalidImport(String),
+    /// The database was written with a newer version of the schema
+    UnsupportedSchemaVersion {
+        stored: u64,
+        supported: u64,
+    },
+    /// No schema migration is registered from the given version
+    MissingSchemaMigration(u64),
+    /// The table can't be bulk loaded, because it already contains entries
+    TableNotEmpty(String),
+    /// The entries being bulk loaded are not in strictly ascending order by key
+    UnsortedInput,
     // Tables cannot be opened for writing multiple times, since they could retrieve immutable &
     // mutable references to the same dirty pages, or multiple mutable references via insert_reserve()
     TableAlreadyOpen(String, &'static panic::Location<'static>),
@@ -506,7 +834,19 @@ impl Display for Error {
                 write!(f, "DB corrupted: {msg}")
             }
             Error::UpgradeRequired(actual) => {
-                write!(f, "Manual upgrade required. Expected file format version {FILE_FORMAT_VERSION2}, but file is version {actual}")
+                write!(f, "Manual upgrade required. Expected file format version {FILE_FORMAT_VERSION3}, but file is version {actual}")
+            }
+            Error::ChecksumUnsupported => {
+                write!(
+                    f,
+                    "Database uses keyed checksums, but the keyed_checksums feature is not enabled."
+                )
+            }
+            Error::InvalidEncryptionKey => {
+                write!(f, "Invalid encryption key.")
+            }
+            Error::InvalidChecksumKey => {
+                write!(f, "Checksum key is missing or does not match the database.")
             }
             Error::ValueTooLarge(len) => {
                 write!(
@@ -545,6 +885,60 @@ impl Display for Error {
             Error::TableDoesNotExist(table) => {
                 write!(f, "Table '{table}' does not exist")
             }
+            Error::TableAlreadyExists(table) => {
+                write!(f, "Table '{table}' already exists")
+            }
+            Error::TableHasUncommittedChanges(table) => {
+                write!(f, "Table '{table}' has uncommitted changes")
+            }
+            Error::UnorderableKeyType { table, key } => {
+                write!(
+                    f,
+                    "Table '{table}' has key type {}, which has no known ordering",
+                    key.name()
+                )
+            }
+            Error::InvalidKeyWidth {
+                table,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Table '{table}' has {expected} byte keys, but the key is {actual} bytes"
+                )
+            }
+            Error::InvalidValueWidth {
+                table,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Table '{table}' has {expected} byte values, but the value is {actual} bytes"
+                )
+            }
+            Error::MissingMergeOperator(table) => {
+                write!(f, "Table '{table}' has no merge operator")
+            }
+            Error::InvalidImport(msg) => {
+                write!(f, "Invalid import data: {msg}")
+            }
+            Error::UnsupportedSchemaVersion { stored, supported } => {
+                write!(
+                    f,
+                    "Database has schema version {stored}, which is newer than the supported version {supported}"
+                )
+            }
+            Error::MissingSchemaMigration(from) => {
+                write!(f, "No migration from schema version {from}")
+            }
+            Error::TableNotEmpty(table) => {
+                write!(f, "Table '{table}' is not empty")
+            }
+            Error::UnsortedInput => {
+                write!(f, "Entries are not sorted by key")
+            }
             Error::TableAlreadyOpen(name, location) => {
                 write!(f, "Table '{name}' already opened at: {location}")
             }
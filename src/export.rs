@@ -0,0 +1,595 @@
+use crate::multimap_table::{SortedMultimapBuilder, UntypedMultiBtree};
+use crate::table::{KeyComparator, UntypedTable};
+use crate::tree_store::{
+    xxh3_checksum, InternalTableDefinition, PagePath, RawBtree, SortedBtreeBuilder, TableTree,
+    TableTreeMut, TableType, TransactionalMemory, UntypedBtree,
+};
+use crate::{ImportError, Key, Result, StorageError, TypeName};
+use std::cmp::Ordering;
+use std::io::{Read, Write};
+use std::mem::size_of;
+use std::sync::Arc;
+
+// Stream layout:
+// 8 bytes: magic number
+// 4 bytes: format version
+// repeating:
+// * 4 bytes: length of record
+// * n bytes: record
+// * 16 bytes: xxh3 checksum of record
+//
+// Each record begins with a one byte record type. A table is written as a TABLE record, followed
+// by ENTRIES records which contain its key & value pairs, in the order they are stored in the
+// database. Multimap tables are flattened into one pair per value. The stream ends with an END
+// record containing the number of tables written.
+//
+// All integers are little-endian. Keys & values are copied verbatim in their stored encoding,
+// along with the type names and widths of the table, so a dump can only be imported with the same
+// type definitions
+const MAGICNUMBER: [u8; 8] = *b"redbdump";
+const FORMAT_VERSION: u32 = 1;
+
+const END: u8 = 0;
+const TABLE: u8 = 1;
+const ENTRIES: u8 = 2;
+
+const NORMAL_TABLE: u8 = 1;
+const MULTIMAP_TABLE: u8 = 2;
+
+// Target size of an ENTRIES record. Bounds the memory used while exporting or importing
+const ENTRIES_RECORD_SIZE: usize = 64 * 1024;
+
+fn write_record(writer: &mut impl Write, record: &[u8]) -> Result {
+    let len: u32 = record.len().try_into().unwrap();
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(record)?;
+    writer.write_all(&xxh3_checksum(record).to_le_bytes())?;
+    Ok(())
+}
+
+fn push_bytes(record: &mut Vec<u8>, bytes: &[u8]) {
+    let len: u32 = bytes.len().try_into().unwrap();
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(bytes);
+}
+
+fn push_width(record: &mut Vec<u8>, width: Option<usize>) {
+    if let Some(width) = width {
+        record.push(1);
+        let width: u32 = width.try_into().unwrap();
+        record.extend_from_slice(&width.to_le_bytes());
+    } else {
+        record.push(0);
+        record.extend_from_slice(&0u32.to_le_bytes());
+    }
+}
+
+struct EntriesWriter<'a, W: Write> {
+    writer: &'a mut W,
+    record: Vec<u8>,
+    count: u32,
+}
+
+impl<'a, W: Write> EntriesWriter<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        Self {
+            writer,
+            record: vec![],
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, key: &[u8], value: &[u8]) -> Result {
+        if self.count == 0 {
+            self.record.push(ENTRIES);
+            self.record.extend_from_slice(&0u32.to_le_bytes());
+        }
+        push_bytes(&mut self.record, key);
+        push_bytes(&mut self.record, value);
+        self.count += 1;
+        if self.record.len() >= ENTRIES_RECORD_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result {
+        if self.count > 0 {
+            self.record[1..5].copy_from_slice(&self.count.to_le_bytes());
+            write_record(self.writer, &self.record)?;
+            self.record.clear();
+            self.count = 0;
+        }
+        Ok(())
+    }
+}
+
+fn export_table(
+    name: &str,
+    definition: &InternalTableDefinition,
+    mem: &Arc<TransactionalMemory>,
+    writer: &mut impl Write,
+) -> Result {
+    let mut record = vec![TABLE];
+    record.push(match definition.get_type() {
+        TableType::Normal => NORMAL_TABLE,
+        TableType::Multimap => MULTIMAP_TABLE,
+    });
+    push_bytes(&mut record, name.as_bytes());
+    push_bytes(&mut record, &definition.key_type().to_bytes());
+    push_bytes(&mut record, &definition.value_type().to_bytes());
+    push_width(&mut record, definition.get_fixed_key_size());
+    push_width(&mut record, definition.get_fixed_value_size());
+    record.extend_from_slice(&definition.get_length().to_le_bytes());
+    write_record(writer, &record)?;
+
+    let mut entries = EntriesWriter::new(writer);
+    match definition.get_type() {
+        TableType::Normal => {
+            let tree = RawBtree::new(
+                definition.get_root(),
+                definition.get_fixed_key_size(),
+                definition.get_fixed_value_size(),
+                mem.clone(),
+            );
+            tree.visit_entries(|key, value| entries.push(key, value))?;
+        }
+        TableType::Multimap => {
+            let tree = UntypedMultiBtree::new(
+                definition.get_root(),
+                mem.clone(),
+                definition.get_fixed_key_size(),
+                definition.get_fixed_value_size(),
+            );
+            tree.visit_entries(|key, value| entries.push(key, value))?;
+        }
+    }
+    entries.flush()
+}
+
+pub(crate) fn export_tables(
+    tree: &TableTree,
+    mem: &Arc<TransactionalMemory>,
+    mut writer: impl Write,
+) -> Result {
+    writer.write_all(&MAGICNUMBER)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+    let mut tables = 0u64;
+    for table_type in [TableType::Normal, TableType::Multimap] {
+        for name in tree.list_tables(table_type)? {
+            let definition = tree
+                .get_table_untyped(&name, table_type)
+                .map_err(|e| e.into_storage_error_or_corrupted("Table tree is corrupted"))?
+                .unwrap();
+            export_table(&name, &definition, mem, &mut writer)?;
+            tables += 1;
+        }
+    }
+
+    let mut record = vec![END];
+    record.extend_from_slice(&tables.to_le_bytes());
+    write_record(&mut writer, &record)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn invalid(message: &str) -> ImportError {
+    ImportError::InvalidFormat(message.to_string())
+}
+
+fn read_record(reader: &mut impl Read) -> std::result::Result<Vec<u8>, ImportError> {
+    let mut len = [0; size_of::<u32>()];
+    reader.read_exact(&mut len).map_err(map_read_error)?;
+    let len = u32::from_le_bytes(len) as usize;
+    let mut record = vec![];
+    reader
+        .take(len as u64)
+        .read_to_end(&mut record)
+        .map_err(StorageError::from)?;
+    if record.len() != len {
+        return Err(invalid("unexpected end of stream"));
+    }
+    let mut checksum = [0; size_of::<u128>()];
+    reader.read_exact(&mut checksum).map_err(map_read_error)?;
+    if u128::from_le_bytes(checksum) != xxh3_checksum(&record) {
+        return Err(invalid("record checksum mismatch"));
+    }
+    if record.is_empty() {
+        return Err(invalid("empty record"));
+    }
+
+    Ok(record)
+}
+
+fn map_read_error(err: std::io::Error) -> ImportError {
+    if err.kind() == std::io::ErrorKind::UnexpectedEof {
+        invalid("unexpected end of stream")
+    } else {
+        ImportError::Storage(err.into())
+    }
+}
+
+// Cursor over the contents of a record, which has already been verified by its checksum
+struct RecordReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RecordReader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], ImportError> {
+        if self.data.len() < len {
+            return Err(invalid("truncated record"));
+        }
+        let (result, remaining) = self.data.split_at(len);
+        self.data = remaining;
+        Ok(result)
+    }
+
+    fn u8(&mut self) -> std::result::Result<u8, ImportError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, ImportError> {
+        Ok(u32::from_le_bytes(
+            self.take(size_of::<u32>())?.try_into().unwrap(),
+        ))
+    }
+
+    fn u64(&mut self) -> std::result::Result<u64, ImportError> {
+        Ok(u64::from_le_bytes(
+            self.take(size_of::<u64>())?.try_into().unwrap(),
+        ))
+    }
+
+    fn bytes(&mut self) -> std::result::Result<&'a [u8], ImportError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn width(&mut self) -> std::result::Result<Option<usize>, ImportError> {
+        let present = self.u8()?;
+        let width = self.u32()? as usize;
+        match present {
+            0 => Ok(None),
+            1 => Ok(Some(width)),
+            _ => Err(invalid("invalid fixed width")),
+        }
+    }
+
+    fn type_name(&mut self) -> std::result::Result<TypeName, ImportError> {
+        let bytes = self.bytes()?;
+        if bytes.is_empty()
+            || !(bytes[0] == 1 || bytes[0] == 2)
+            || std::str::from_utf8(&bytes[1..]).is_err()
+        {
+            return Err(invalid("invalid type name"));
+        }
+        Ok(TypeName::from_bytes(bytes))
+    }
+
+    fn finish(&self) -> std::result::Result<(), ImportError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid("trailing data in record"))
+        }
+    }
+}
+
+/// The orderings of key types, used to check the order of imported table entries
+///
+/// The orderings of the builtin key types, and of types wrapped in [`crate::Memcmp`], are always
+/// known. Other types must be added to be checked. See [`crate::WriteTransaction::import_with`]
+#[derive(Default)]
+pub struct KeyOrderings {
+    comparators: Vec<(TypeName, KeyComparator)>,
+}
+
+impl KeyOrderings {
+    /// Create a set containing only the builtin orderings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the ordering of `K`
+    ///
+    /// It is used for tables whose key type, or multimap value type, is `K`
+    pub fn add<K: Key + 'static>(&mut self) -> &mut Self {
+        self.comparators.push((K::type_name(), K::compare));
+        self
+    }
+
+    fn comparator(&self, type_name: &TypeName) -> Option<KeyComparator> {
+        self.comparators
+            .iter()
+            .find(|(name, _)| name == type_name)
+            .map(|(_, comparator)| *comparator)
+            .or_else(|| UntypedTable::builtin_comparator(type_name))
+    }
+}
+
+// Used in place of the ordering of a type which is unknown. Only checks that an entry isn't a
+// duplicate of the one before it
+fn unknown_order(previous: &[u8], current: &[u8]) -> Ordering {
+    if previous == current {
+        Ordering::Equal
+    } else {
+        Ordering::Less
+    }
+}
+
+enum TableBuilder<'a> {
+    Normal(SortedBtreeBuilder<'a>),
+    Multimap(SortedMultimapBuilder<'a>),
+}
+
+fn import_table(
+    record: &mut RecordReader,
+    table_tree: &mut TableTreeMut,
+    mem: &Arc<TransactionalMemory>,
+    reader: &mut impl Read,
+    orderings: &KeyOrderings,
+) -> std::result::Result<(), ImportError> {
+    let table_type = match record.u8()? {
+        NORMAL_TABLE => TableType::Normal,
+        MULTIMAP_TABLE => TableType::Multimap,
+        _ => return Err(invalid("unknown table type")),
+    };
+    let name = std::str::from_utf8(record.bytes()?)
+        .map_err(|_| invalid("table name is not valid utf-8"))?
+        .to_string();
+    let key_type = record.type_name()?;
+    let value_type = record.type_name()?;
+    let fixed_key_size = record.width()?;
+    let fixed_value_size = record.width()?;
+    let length = record.u64()?;
+    record.finish()?;
+
+    // Check before building the tree, so that no pages are allocated for a table which can't be
+    // created
+    if table_tree.contains_table(&name)? {
+        return Err(ImportError::TableAlreadyExists(name));
+    }
+    // The entries are checked to be in the order that the table stores them in, if the ordering
+    // of the key type, and of the value type of a multimap table, is known
+    let key_order = orderings
+        .comparator(&key_type)
+        .unwrap_or(unknown_order as KeyComparator);
+    let value_order = match table_type {
+        TableType::Normal => None,
+        TableType::Multimap => Some(
+            orderings
+                .comparator(&value_type)
+                .unwrap_or(unknown_order as KeyComparator),
+        ),
+    };
+
+    let mut builder = match table_type {
+        TableType::Normal => TableBuilder::Normal(SortedBtreeBuilder::new(
+            mem,
+            fixed_key_size,
+            fixed_value_size,
+        )),
+        TableType::Multimap => TableBuilder::Multimap(SortedMultimapBuilder::new(
+            mem,
+            fixed_key_size,
+            fixed_value_size,
+        )),
+    };
+    let result = (|| -> std::result::Result<(), ImportError> {
+        let mut previous: Option<(Vec<u8>, Vec<u8>)> = None;
+        let mut remaining = length;
+        while remaining > 0 {
+            let data = read_record(reader)?;
+            let mut record = RecordReader { data: &data };
+            if record.u8()? != ENTRIES {
+                return Err(invalid("missing table entries"));
+            }
+            let count = record.u32()?;
+            if u64::from(count) > remaining {
+                return Err(invalid("too many table entries"));
+            }
+            for _ in 0..count {
+                let key = record.bytes()?;
+                let value = record.bytes()?;
+                if fixed_key_size.map(|x| x != key.len()).unwrap_or(false)
+                    || fixed_value_size.map(|x| x != value.len()).unwrap_or(false)
+                {
+                    return Err(invalid("entry does not match the table's fixed width"));
+                }
+                if let Some((previous_key, previous_value)) = previous.as_mut() {
+                    let order = match key_order(previous_key, key) {
+                        Ordering::Equal => value_order.map_or(Ordering::Equal, |value_order| {
+                            value_order(previous_value, value)
+                        }),
+                        order => order,
+                    };
+                    if order != Ordering::Less {
+                        return Err(invalid("table entries are not in ascending order"));
+                    }
+                    previous_key.clear();
+                    previous_key.extend_from_slice(key);
+                    previous_value.clear();
+                    previous_value.extend_from_slice(value);
+                } else {
+                    previous = Some((key.to_vec(), value.to_vec()));
+                }
+                match builder {
+                    TableBuilder::Normal(ref mut builder) => builder.push(key, value)?,
+                    TableBuilder::Multimap(ref mut builder) => builder.push(key, value)?,
+                }
+            }
+            record.finish()?;
+            remaining -= u64::from(count);
+        }
+        Ok(())
+    })();
+
+    let root = match builder {
+        TableBuilder::Normal(builder) => builder.finish()?,
+        TableBuilder::Multimap(builder) => builder.finish()?.0,
+    };
+    if let Err(err) = result {
+        // Free the pages of the partially built table, which will never be referenced
+        let mut pages = vec![];
+        let mut visitor = |path: &PagePath| {
+            pages.push(path.page_number());
+            Ok(())
+        };
+        match table_type {
+            TableType::Normal => {
+                UntypedBtree::new(root, mem.clone(), fixed_key_size, fixed_value_size)
+                    .visit_all_pages(&mut visitor)?;
+            }
+            TableType::Multimap => {
+                UntypedMultiBtree::new(root, mem.clone(), fixed_key_size, fixed_value_size)
+                    .visit_all_pages(&mut visitor)?;
+            }
+        }
+        for page in pages {
+            mem.free(page);
+        }
+        return Err(err);
+    }
+    let definition = InternalTableDefinition::new_untyped(
+        table_type,
+        root,
+        length,
+        fixed_key_size,
+        fixed_value_size,
+        key_type,
+        value_type,
+    );
+    if !table_tree.create_table(&name, definition)? {
+        return Err(ImportError::TableAlreadyExists(name));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn import_tables(
+    table_tree: &mut TableTreeMut,
+    mem: &Arc<TransactionalMemory>,
+    mut reader: impl Read,
+    orderings: &KeyOrderings,
+) -> std::result::Result<(), ImportError> {
+    let mut header = [0; MAGICNUMBER.len() + size_of::<u32>()];
+    reader.read_exact(&mut header).map_err(map_read_error)?;
+    if header[..MAGICNUMBER.len()] != MAGICNUMBER {
+        return Err(invalid("not a redb export"));
+    }
+    let version = u32::from_le_bytes(header[MAGICNUMBER.len()..].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(ImportError::UnsupportedVersion(version));
+    }
+
+    let mut tables = 0u64;
+    loop {
+        let data = read_record(&mut reader)?;
+        let mut record = RecordReader { data: &data };
+        match record.u8()? {
+            TABLE => {
+                import_table(&mut record, table_tree, mem, &mut reader, orderings)?;
+                tables += 1;
+            }
+            END => {
+                let expected = record.u64()?;
+                record.finish()?;
+                if expected != tables {
+                    return Err(invalid("number of tables does not match"));
+                }
+                return Ok(());
+            }
+            _ => return Err(invalid("unexpected record")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::{
+        push_bytes, push_width, write_record, EntriesWriter, KeyOrderings, END, FORMAT_VERSION,
+        MAGICNUMBER, NORMAL_TABLE, TABLE,
+    };
+    use crate::{Database, ImportError, Reverse, TypeName, Value};
+
+    // Writes an export of a table named "x" with u64 values, and keys of the given type
+    fn export_data(key_type: TypeName, keys: &[u64]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&MAGICNUMBER);
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        let mut record = vec![TABLE, NORMAL_TABLE];
+        push_bytes(&mut record, b"x");
+        push_bytes(&mut record, &key_type.to_bytes());
+        push_bytes(&mut record, &<u64 as Value>::type_name().to_bytes());
+        push_width(&mut record, <u64 as Value>::fixed_width());
+        push_width(&mut record, <u64 as Value>::fixed_width());
+        let length = u64::try_from(keys.len()).unwrap();
+        record.extend_from_slice(&length.to_le_bytes());
+        write_record(&mut data, &record).unwrap();
+        let mut entries = EntriesWriter::new(&mut data);
+        for key in keys {
+            entries
+                .push(&key.to_le_bytes(), &key.to_le_bytes())
+                .unwrap();
+        }
+        entries.flush().unwrap();
+        let mut record = vec![END];
+        record.extend_from_slice(&1u64.to_le_bytes());
+        write_record(&mut data, &record).unwrap();
+        data
+    }
+
+    #[test]
+    fn import_unsorted() {
+        let tmpfile = crate::create_tempfile();
+        let mut db = Database::create(tmpfile.path()).unwrap();
+
+        let keys: Vec<u64> = (0..10_000).chain([5]).collect();
+        let data = export_data(<u64 as Value>::type_name(), &keys);
+
+        let write_txn = db.begin_write().unwrap();
+        assert!(matches!(
+            write_txn.import(data.as_slice()).unwrap_err(),
+            ImportError::InvalidFormat(_)
+        ));
+        assert_eq!(write_txn.list_tables().unwrap().count(), 0);
+        write_txn.commit().unwrap();
+
+        // The pages of the partially imported table were freed
+        assert!(db.check_integrity().unwrap());
+    }
+
+    #[test]
+    fn import_unknown_order() {
+        let tmpfile = crate::create_tempfile();
+        let db = Database::create(tmpfile.path()).unwrap();
+        let key_type = <Reverse<u64> as Value>::type_name();
+
+        // Without its ordering, only duplicate entries are detected
+        let data = export_data(key_type.clone(), &[1, 2, 2]);
+        let write_txn = db.begin_write().unwrap();
+        assert!(matches!(
+            write_txn.import(data.as_slice()).unwrap_err(),
+            ImportError::InvalidFormat(_)
+        ));
+        write_txn.abort().unwrap();
+
+        let data = export_data(key_type, &[1, 2, 3]);
+        let write_txn = db.begin_write().unwrap();
+        write_txn.import(data.as_slice()).unwrap();
+        write_txn.abort().unwrap();
+
+        let mut orderings = KeyOrderings::new();
+        orderings.add::<Reverse<u64>>();
+        let write_txn = db.begin_write().unwrap();
+        assert!(matches!(
+            write_txn
+                .import_with(data.as_slice(), &orderings)
+                .unwrap_err(),
+            ImportError::InvalidFormat(_)
+        ));
+        write_txn.abort().unwrap();
+    }
+}
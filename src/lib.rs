@@ -53,39 +53,66 @@
 //! [lmdb]: https://www.lmdb.tech/doc/
 //! [design]: https://github.com/cberner/redb/blob/master/docs/design.md
 
+pub use blob_table::{BlobReader, BlobTable, BlobWriter, ReadOnlyBlobTable};
+#[cfg(feature = "compression")]
+pub use compressed::Compressed;
 pub use db::{
-    Builder, Database, MultimapTableDefinition, MultimapTableHandle, RepairSession, StorageBackend,
-    TableDefinition, TableHandle, UntypedMultimapTableHandle, UntypedTableHandle,
+    BlobTableDefinition, Builder, Database, MultimapTableDefinition, MultimapTableHandle,
+    RepairSession, StorageBackend, TableDefinition, TableHandle, UntypedMultimapTableHandle,
+    UntypedTableHandle,
 };
+pub use dyn_value::DynValue;
 pub use error::{
-    CommitError, CompactionError, DatabaseError, Error, SavepointError, StorageError, TableError,
-    TransactionError,
+    BulkLoadError, CommitError, CompactionError, DatabaseError, Error, ImportError, SavepointError,
+    SchemaError, StorageError, TableError, TransactionError,
 };
+pub use export::KeyOrderings;
+pub use memcmp::{Memcmp, MemcmpKey};
 pub use multimap_table::{
     MultimapRange, MultimapTable, MultimapValue, ReadOnlyMultimapTable,
     ReadOnlyUntypedMultimapTable, ReadableMultimapTable,
 };
+pub use schema::Schema;
 pub use table::{
-    ExtractIf, Range, ReadOnlyTable, ReadOnlyUntypedTable, ReadableTable, ReadableTableMetadata,
-    Table, TableStats,
+    Entry, ExtractIf, OccupiedEntry, Range, ReadOnlyTable, ReadOnlyUntypedTable, ReadableTable,
+    ReadableTableMetadata, Table, TableInfo, TableStats, UntypedRange, UntypedTable, VacantEntry,
 };
 pub use transactions::{DatabaseStats, Durability, ReadTransaction, WriteTransaction};
 pub use tree_store::{AccessGuard, AccessGuardMut, Savepoint};
-pub use types::{Key, MutInPlaceValue, TypeName, Value};
+pub use types::{Key, MutInPlaceValue, Reverse, TypeName, Value};
 
 pub type Result<T = (), E = StorageError> = std::result::Result<T, E>;
 
+#[cfg(feature = "derive")]
+pub use redb_derive::{Key, Value};
+
+// Used by the code generated by redb-derive. Not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::tuple_types::serialize_tuple_elements;
+}
+
 #[cfg(feature = "python")]
 pub use crate::python::redb;
 
 pub mod backends;
+mod blob_table;
 mod complex_types;
+#[cfg(feature = "compression")]
+mod compressed;
 mod db;
+mod dyn_value;
 mod error;
+mod export;
+mod memcmp;
+pub mod merge;
 mod multimap_table;
 #[cfg(feature = "python")]
 mod python;
+mod schema;
 mod sealed;
+#[cfg(any(feature = "bincode", feature = "json", feature = "postcard"))]
+pub mod serde;
 mod table;
 mod transaction_tracker;
 mod transactions;
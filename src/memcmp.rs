@@ -0,0 +1,331 @@
+use crate::types::{Key, Reverse, TypeName, Value};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+// Zero bytes in strings and byte strings are escaped as [0, ESCAPE], and they are terminated with
+// [0, TERMINATOR]. A string is then ordered before any longer string that it's a prefix of,
+// regardless of what follows it
+const ESCAPE: u8 = 0xFF;
+const TERMINATOR: u8 = 0x01;
+
+/// A key type which has an order-preserving byte encoding
+///
+/// Comparing the encodings of two values with `memcmp` must give the same result as
+/// [`Key::compare`], and the encodings must be prefix free: no encoding may be a prefix of the
+/// encoding of a different value.
+pub trait MemcmpKey: Key {
+    /// Append the order-preserving encoding of `value` to `output`
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a;
+
+    /// Returns the length of the encoding which starts at the beginning of `data`
+    fn memcmp_len(data: &[u8]) -> usize;
+}
+
+/// Stores keys of type `K` in an order-preserving encoding, so that they are compared with
+/// `memcmp` rather than with [`Key::compare`]
+///
+/// Tables with `Memcmp` keys can be range scanned correctly by tools which don't know the key
+/// type, such as [`crate::ReadOnlyUntypedTable`]. Each key is stored as its order-preserving
+/// encoding, followed by its regular encoding, so that it can be read without copying, which makes
+/// keys larger than if `K` were used directly.
+#[derive(Debug)]
+pub struct Memcmp<K>(PhantomData<K>);
+
+impl<K: MemcmpKey> Value for Memcmp<K> {
+    type SelfType<'a> = K::SelfType<'a>
+    where
+        Self: 'a;
+    type AsBytes<'a> = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> K::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        K::from_bytes(&data[K::memcmp_len(data)..])
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Vec<u8>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut result = vec![];
+        K::encode_memcmp(value, &mut result);
+        result.extend_from_slice(K::as_bytes(value).as_ref());
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal(&format!("redb::Memcmp<{}>", K::type_name().name()))
+    }
+}
+
+impl<K: MemcmpKey> Key for Memcmp<K> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        // The order-preserving encodings are prefix free, so the regular encodings which follow
+        // them are only compared if the keys are equal
+        data1.cmp(data2)
+    }
+}
+
+impl MemcmpKey for () {
+    fn encode_memcmp<'a>(_value: &Self::SelfType<'a>, _output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+    }
+
+    fn memcmp_len(_data: &[u8]) -> usize {
+        0
+    }
+}
+
+impl MemcmpKey for bool {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        output.push(u8::from(*value));
+    }
+
+    fn memcmp_len(_data: &[u8]) -> usize {
+        1
+    }
+}
+
+impl MemcmpKey for char {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        // The highest byte is always zero
+        output.extend_from_slice(&u32::from(*value).to_be_bytes()[1..]);
+    }
+
+    fn memcmp_len(_data: &[u8]) -> usize {
+        3
+    }
+}
+
+macro_rules! unsigned_memcmp {
+    ($t:ty) => {
+        impl MemcmpKey for $t {
+            fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+            where
+                Self: 'a,
+            {
+                output.extend_from_slice(&value.to_be_bytes());
+            }
+
+            fn memcmp_len(_data: &[u8]) -> usize {
+                std::mem::size_of::<$t>()
+            }
+        }
+    };
+}
+
+macro_rules! signed_memcmp {
+    ($t:ty) => {
+        impl MemcmpKey for $t {
+            fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+            where
+                Self: 'a,
+            {
+                // Flip the sign bit, so that negative numbers are ordered before positive ones
+                let mut bytes = value.to_be_bytes();
+                bytes[0] ^= 0x80;
+                output.extend_from_slice(&bytes);
+            }
+
+            fn memcmp_len(_data: &[u8]) -> usize {
+                std::mem::size_of::<$t>()
+            }
+        }
+    };
+}
+
+macro_rules! float_memcmp {
+    ($t:ty, $bits:ty) => {
+        impl MemcmpKey for $t {
+            fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+            where
+                Self: 'a,
+            {
+                // Same transformation as total_cmp(): flip all the bits of negative numbers, so
+                // that they're ordered by magnitude in reverse, and the sign bit of the rest
+                let bits = value.to_bits();
+                let sign: $bits = 1 << (<$bits>::BITS - 1);
+                let bits = if bits & sign != 0 { !bits } else { bits ^ sign };
+                output.extend_from_slice(&bits.to_be_bytes());
+            }
+
+            fn memcmp_len(_data: &[u8]) -> usize {
+                std::mem::size_of::<$t>()
+            }
+        }
+    };
+}
+
+unsigned_memcmp!(u8);
+unsigned_memcmp!(u16);
+unsigned_memcmp!(u32);
+unsigned_memcmp!(u64);
+unsigned_memcmp!(u128);
+signed_memcmp!(i8);
+signed_memcmp!(i16);
+signed_memcmp!(i32);
+signed_memcmp!(i64);
+signed_memcmp!(i128);
+float_memcmp!(f32, u32);
+float_memcmp!(f64, u64);
+
+fn encode_escaped(data: &[u8], output: &mut Vec<u8>) {
+    for byte in data {
+        output.push(*byte);
+        if *byte == 0 {
+            output.push(ESCAPE);
+        }
+    }
+    output.extend_from_slice(&[0, TERMINATOR]);
+}
+
+fn escaped_len(data: &[u8]) -> usize {
+    let mut offset = 0;
+    loop {
+        if data[offset] == 0 {
+            if data[offset + 1] == TERMINATOR {
+                return offset + 2;
+            }
+            offset += 2;
+        } else {
+            offset += 1;
+        }
+    }
+}
+
+impl MemcmpKey for &str {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        encode_escaped(value.as_bytes(), output);
+    }
+
+    fn memcmp_len(data: &[u8]) -> usize {
+        escaped_len(data)
+    }
+}
+
+impl MemcmpKey for String {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        encode_escaped(value.as_bytes(), output);
+    }
+
+    fn memcmp_len(data: &[u8]) -> usize {
+        escaped_len(data)
+    }
+}
+
+impl MemcmpKey for &[u8] {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        encode_escaped(value, output);
+    }
+
+    fn memcmp_len(data: &[u8]) -> usize {
+        escaped_len(data)
+    }
+}
+
+impl<T: MemcmpKey> MemcmpKey for Option<T> {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        if let Some(x) = value {
+            output.push(1);
+            T::encode_memcmp(x, output);
+        } else {
+            output.push(0);
+        }
+    }
+
+    fn memcmp_len(data: &[u8]) -> usize {
+        if data[0] == 0 {
+            1
+        } else {
+            1 + T::memcmp_len(&data[1..])
+        }
+    }
+}
+
+// The encoding of K with every bit inverted. Since encodings are prefix free, two of them differ at
+// some byte which both contain, so inverting them reverses their order
+impl<K: MemcmpKey> MemcmpKey for Reverse<K> {
+    fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+    where
+        Self: 'a,
+    {
+        let start = output.len();
+        K::encode_memcmp(value, output);
+        for byte in &mut output[start..] {
+            *byte = !*byte;
+        }
+    }
+
+    fn memcmp_len(data: &[u8]) -> usize {
+        let inverted: Vec<u8> = data.iter().map(|x| !x).collect();
+        K::memcmp_len(&inverted)
+    }
+}
+
+// Tuples are encoded as the concatenation of their elements' encodings
+macro_rules! tuple_memcmp {
+    ( $($t:ident, $i:tt),+ ) => {
+        impl<$($t: MemcmpKey),+> MemcmpKey for ($($t,)+) {
+            fn encode_memcmp<'a>(value: &Self::SelfType<'a>, output: &mut Vec<u8>)
+            where
+                Self: 'a,
+            {
+                $(
+                    $t::encode_memcmp(&value.$i, output);
+                )+
+            }
+
+            fn memcmp_len(data: &[u8]) -> usize {
+                let mut len = 0;
+                $(
+                    len += $t::memcmp_len(&data[len..]);
+                )+
+                len
+            }
+        }
+    };
+}
+
+tuple_memcmp!(T0, 0, T1, 1);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8, 8);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8, 8, T9, 9);
+tuple_memcmp!(T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8, 8, T9, 9, T10, 10);
+tuple_memcmp!(
+    T0, 0, T1, 1, T2, 2, T3, 3, T4, 4, T5, 5, T6, 6, T7, 7, T8, 8, T9, 9, T10, 10, T11, 11
+);
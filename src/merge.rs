@@ -0,0 +1,91 @@
+//! Merge operators, which combine the value stored in a table with a delta
+//!
+//! A merge operator is registered for a table with [`TableDefinition::with_merge_operator`], and
+//! then used by [`Table::merge`]
+//!
+//! [`TableDefinition::with_merge_operator`]: crate::TableDefinition::with_merge_operator
+//! [`Table::merge`]: crate::Table::merge
+
+use crate::types::{Key, Value};
+use std::cmp::Ordering;
+
+/// Combines the value stored under a key with a delta
+pub trait MergeOperator<V: Value + 'static>: Sync {
+    /// Returns the serialized value to store, given the existing value, if any, and the delta
+    ///
+    /// If `None` is returned, the key is removed
+    fn merge(&self, existing: Option<V::SelfType<'_>>, delta: V::SelfType<'_>) -> Option<Vec<u8>>;
+}
+
+/// Adds the delta to the existing value. Integers wrap on overflow
+#[derive(Copy, Clone, Debug)]
+pub struct Add;
+
+/// Keeps the greater of the existing value and the delta, ordered by [`Key::compare`]
+#[derive(Copy, Clone, Debug)]
+pub struct Max;
+
+/// Appends the delta to the existing value
+#[derive(Copy, Clone, Debug)]
+pub struct Append;
+
+macro_rules! add_impl {
+    ($t:ty, $add:expr) => {
+        impl MergeOperator<$t> for Add {
+            fn merge(&self, existing: Option<$t>, delta: $t) -> Option<Vec<u8>> {
+                let add: fn($t, $t) -> $t = $add;
+                let value = existing.map_or(delta, |x| add(x, delta));
+                Some(value.to_le_bytes().to_vec())
+            }
+        }
+    };
+}
+
+add_impl!(u8, u8::wrapping_add);
+add_impl!(u16, u16::wrapping_add);
+add_impl!(u32, u32::wrapping_add);
+add_impl!(u64, u64::wrapping_add);
+add_impl!(u128, u128::wrapping_add);
+add_impl!(i8, i8::wrapping_add);
+add_impl!(i16, i16::wrapping_add);
+add_impl!(i32, i32::wrapping_add);
+add_impl!(i64, i64::wrapping_add);
+add_impl!(i128, i128::wrapping_add);
+add_impl!(f32, |x, y| x + y);
+add_impl!(f64, |x, y| x + y);
+
+impl<V: Key + 'static> MergeOperator<V> for Max {
+    fn merge(&self, existing: Option<V::SelfType<'_>>, delta: V::SelfType<'_>) -> Option<Vec<u8>> {
+        let delta = V::as_bytes(&delta).as_ref().to_vec();
+        if let Some(existing) = existing {
+            let existing = V::as_bytes(&existing);
+            if V::compare(existing.as_ref(), &delta) == Ordering::Greater {
+                return Some(existing.as_ref().to_vec());
+            }
+        }
+        Some(delta)
+    }
+}
+
+macro_rules! append_impl {
+    ($t:ty) => {
+        impl MergeOperator<$t> for Append {
+            fn merge(
+                &self,
+                existing: Option<<$t as Value>::SelfType<'_>>,
+                delta: <$t as Value>::SelfType<'_>,
+            ) -> Option<Vec<u8>> {
+                let mut value = vec![];
+                if let Some(existing) = existing {
+                    value.extend_from_slice(<$t as Value>::as_bytes(&existing).as_ref());
+                }
+                value.extend_from_slice(<$t as Value>::as_bytes(&delta).as_ref());
+                Some(value)
+            }
+        }
+    };
+}
+
+append_impl!(&'static [u8]);
+append_impl!(&'static str);
+append_impl!(String);
@@ -5,8 +5,9 @@ use crate::table::{ReadableTableMetadata, TableStats};
 use crate::tree_store::{
     btree_stats, AllPageNumbersBtreeIter, BranchAccessor, BranchMutator, Btree, BtreeHeader,
     BtreeMut, BtreeRangeIter, BtreeStats, CachePriority, Checksum, LeafAccessor, LeafMutator, Page,
-    PageHint, PageNumber, PagePath, RawBtree, RawLeafBuilder, TransactionalMemory, UntypedBtree,
-    UntypedBtreeMut, BRANCH, DEFERRED, LEAF, MAX_PAIR_LENGTH, MAX_VALUE_LENGTH,
+    PageHint, PageNumber, PagePath, RawBtree, RawLeafBuilder, SortedBtreeBuilder,
+    TransactionalMemory, UntypedBtree, UntypedBtreeMut, BRANCH, DEFERRED, LEAF, MAX_PAIR_LENGTH,
+    MAX_VALUE_LENGTH,
 };
 use crate::types::{Key, TypeName, Value};
 use crate::{AccessGuard, MultimapTableHandle, Result, StorageError, WriteTransaction};
@@ -425,6 +426,153 @@ impl UntypedMultiBtree {
 
         Ok(())
     }
+
+    // Applies visitor to every key & value pair in the tree, in order
+    pub(crate) fn visit_entries<F>(&self, mut visitor: F) -> Result
+    where
+        F: FnMut(&[u8], &[u8]) -> Result,
+    {
+        let tree = RawBtree::new(
+            self.root,
+            self.key_width,
+            UntypedDynamicCollection::fixed_width_with(self.value_width),
+            self.mem.clone(),
+        );
+        tree.visit_entries(|key, collection| {
+            let collection = UntypedDynamicCollection::from_bytes(collection);
+            match collection.collection_type() {
+                Inline => {
+                    let accessor = LeafAccessor::new(
+                        collection.as_inline(),
+                        self.value_width,
+                        <() as Value>::fixed_width(),
+                    );
+                    for i in 0..accessor.num_pairs() {
+                        visitor(key, accessor.entry(i).unwrap().key())?;
+                    }
+                }
+                SubtreeV2 => {
+                    let subtree = RawBtree::new(
+                        Some(collection.as_subtree()),
+                        self.value_width,
+                        <() as Value>::fixed_width(),
+                        self.mem.clone(),
+                    );
+                    subtree.visit_entries(|value, _| visitor(key, value))?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+// Builds a new multimap tree from a stream of key & value pairs, which must be sorted by key and
+// then by value. Values are stored inline, unless they grow too large, in which case the
+// collection is built as a subtree
+pub(crate) struct SortedMultimapBuilder<'a> {
+    mem: &'a TransactionalMemory,
+    value_width: Option<usize>,
+    tree: SortedBtreeBuilder<'a>,
+    current_key: Option<Vec<u8>>,
+    inline_values: Vec<Vec<u8>>,
+    inline_bytes: usize,
+    subtree: Option<SortedBtreeBuilder<'a>>,
+    num_values: u64,
+}
+
+impl<'a> SortedMultimapBuilder<'a> {
+    pub(crate) fn new(
+        mem: &'a TransactionalMemory,
+        key_width: Option<usize>,
+        value_width: Option<usize>,
+    ) -> Self {
+        Self {
+            mem,
+            value_width,
+            tree: SortedBtreeBuilder::new(
+                mem,
+                key_width,
+                UntypedDynamicCollection::fixed_width_with(value_width),
+            ),
+            current_key: None,
+            inline_values: vec![],
+            inline_bytes: 0,
+            subtree: None,
+            num_values: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, key: &[u8], value: &[u8]) -> Result {
+        if self.current_key.as_deref() != Some(key) {
+            self.finish_collection()?;
+            self.current_key = Some(key.to_vec());
+        }
+        self.num_values += 1;
+        if let Some(ref mut subtree) = self.subtree {
+            return subtree.push(value, &[]);
+        }
+
+        self.inline_bytes += value.len();
+        self.inline_values.push(value.to_vec());
+        let required_inline_bytes = RawLeafBuilder::required_bytes(
+            self.inline_values.len(),
+            self.inline_bytes,
+            self.value_width,
+            <() as Value>::fixed_width(),
+        );
+        if required_inline_bytes >= self.mem.get_page_size() / 2 {
+            let mut subtree =
+                SortedBtreeBuilder::new(self.mem, self.value_width, <() as Value>::fixed_width());
+            for value in mem::take(&mut self.inline_values) {
+                subtree.push(&value, &[])?;
+            }
+            self.inline_bytes = 0;
+            self.subtree = Some(subtree);
+        }
+
+        Ok(())
+    }
+
+    fn finish_collection(&mut self) -> Result {
+        let key = if let Some(key) = self.current_key.take() {
+            key
+        } else {
+            return Ok(());
+        };
+        let collection = if let Some(subtree) = self.subtree.take() {
+            UntypedDynamicCollection::make_subtree_data(subtree.finish()?.unwrap())
+        } else {
+            let values = mem::take(&mut self.inline_values);
+            let required_inline_bytes = RawLeafBuilder::required_bytes(
+                values.len(),
+                self.inline_bytes,
+                self.value_width,
+                <() as Value>::fixed_width(),
+            );
+            let mut data = vec![0; required_inline_bytes];
+            let mut builder = RawLeafBuilder::new(
+                &mut data,
+                values.len(),
+                self.value_width,
+                <() as Value>::fixed_width(),
+                self.inline_bytes,
+            );
+            for value in values.iter() {
+                builder.append(value, <() as Value>::as_bytes(&()).as_ref());
+            }
+            drop(builder);
+            self.inline_bytes = 0;
+            UntypedDynamicCollection::make_inline_data(&data)
+        };
+        self.tree.push(&key, &collection)
+    }
+
+    // Returns the root of the tree, and the total number of values
+    pub(crate) fn finish(mut self) -> Result<(Option<BtreeHeader>, u64)> {
+        self.finish_collection()?;
+        let num_values = self.num_values;
+        Ok((self.tree.finish()?, num_values))
+    }
 }
 
 pub(crate) struct LeafKeyIter<'a, V: Key + 'static> {
@@ -705,6 +853,13 @@ impl UntypedDynamicCollection {
         unsafe { mem::transmute(data) }
     }
 
+    fn make_inline_data(data: &[u8]) -> Vec<u8> {
+        let mut result = vec![Inline.into()];
+        result.extend_from_slice(data);
+
+        result
+    }
+
     fn make_subtree_data(header: BtreeHeader) -> Vec<u8> {
         let mut result = vec![SubtreeV2.into()];
         result.extend_from_slice(&header.to_le_bytes());
@@ -893,8 +1048,8 @@ impl<'a, K: Key + 'static, V: Key + 'static> Iterator for MultimapRange<'a, K, V
         match self.inner.next()? {
             Ok(entry) => {
                 let key = AccessGuard::with_owned_value(entry.key_data());
-                let (page, _, value_range) = entry.into_raw();
-                let collection = AccessGuard::with_page(page, value_range);
+                let (_, _, value_page, value_range) = entry.into_raw();
+                let collection = AccessGuard::with_page(value_page, value_range);
                 Some(
                     DynamicCollection::iter(
                         collection,
@@ -914,8 +1069,8 @@ impl<'a, K: Key + 'static, V: Key + 'static> DoubleEndedIterator for MultimapRan
         match self.inner.next_back()? {
             Ok(entry) => {
                 let key = AccessGuard::with_owned_value(entry.key_data());
-                let (page, _, value_range) = entry.into_raw();
-                let collection = AccessGuard::with_page(page, value_range);
+                let (_, _, value_page, value_range) = entry.into_raw();
+                let collection = AccessGuard::with_page(value_page, value_range);
                 Some(
                     DynamicCollection::iter(
                         collection,
@@ -1274,6 +1429,42 @@ impl<'txn, K: Key + 'static, V: Key + 'static> MultimapTable<'txn, K, V> {
         Ok(existed)
     }
 
+    /// Add the given value to the mapping of the key, if the key has no values
+    ///
+    /// Returns `true` if the value was inserted
+    pub fn insert_if_absent<'k, 'v>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'k>>,
+        value: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<bool> {
+        if self.tree.get(key.borrow())?.is_some() {
+            return Ok(false);
+        }
+        self.insert(key, value)?;
+        Ok(true)
+    }
+
+    /// Replaces the value `expected` in the mapping of the key with `new`. If `expected` is
+    /// `None`, `new` is only inserted if the key has no values
+    ///
+    /// Returns `true` if `expected` was present, and has been replaced
+    pub fn compare_and_swap<'k, 'e, 'v>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'k>>,
+        expected: Option<&V::SelfType<'e>>,
+        new: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<bool> {
+        if let Some(expected) = expected {
+            if !self.remove(key.borrow(), expected)? {
+                return Ok(false);
+            }
+            self.insert(key, new)?;
+            Ok(true)
+        } else {
+            self.insert_if_absent(key, new)
+        }
+    }
+
     /// Removes all values for the given key
     ///
     /// Returns an iterator over the removed values. Values are in ascending order.
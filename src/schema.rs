@@ -0,0 +1,153 @@
+use crate::{
+    Error, Key, MultimapTableDefinition, SchemaError, TableDefinition, TableError, Value,
+    WriteTransaction,
+};
+use std::collections::BTreeMap;
+
+type TableValidator<'a> = Box<dyn Fn(&WriteTransaction) -> Result<(), TableError> + 'a>;
+type Migration<'a> = Box<dyn Fn(&WriteTransaction) -> Result<(), Error> + 'a>;
+
+/// The layout of a database: its tables, and a version number
+///
+/// A schema is applied with [`crate::Database::apply_schema`], or whenever the database is opened
+/// if it is set with [`crate::Builder::set_schema`]. Applying it runs the registered migrations to
+/// upgrade a database written with an older version, and then checks that every table matches its
+/// definition.
+///
+/// A database to which no schema has been applied is at the initial version, which is 0 unless
+/// set with [`Schema::set_initial_version`]. If it doesn't contain any tables it is new instead,
+/// and is stamped with the schema's version without running any migrations.
+///
+/// ```rust
+/// use redb::{Database, Schema, TableDefinition};
+/// # use tempfile::NamedTempFile;
+///
+/// const USERS: TableDefinition<u64, &str> = TableDefinition::new("users");
+///
+/// # fn main() -> Result<(), redb::Error> {
+/// # let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+/// # let filename = tmpfile.path();
+/// let db = Database::create(filename)?;
+/// let mut schema = Schema::new(2);
+/// schema
+///     .set_initial_version(1)
+///     .add_table(USERS)
+///     .add_migration(1, |txn| {
+///         txn.delete_table(TableDefinition::<u64, u64>::new("legacy"))?;
+///         Ok(())
+///     });
+/// db.apply_schema(&schema)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Schema<'a> {
+    version: u64,
+    initial_version: u64,
+    tables: Vec<TableValidator<'a>>,
+    migrations: BTreeMap<u64, Migration<'a>>,
+}
+
+impl<'a> Schema<'a> {
+    /// Create an empty schema with the given version
+    pub fn new(version: u64) -> Self {
+        Self {
+            version,
+            initial_version: 0,
+            tables: vec![],
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// The version of this schema
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Set the version of an existing database to which no schema has been applied
+    ///
+    /// Migrations are run from this version, so it should be the version of the layout that was
+    /// in use before schemas were adopted. A database without any tables is not migrated
+    ///
+    /// ## Defaults
+    ///
+    /// Default to 0
+    pub fn set_initial_version(&mut self, version: u64) -> &mut Self {
+        self.initial_version = version;
+        self
+    }
+
+    /// Add a table to the schema
+    ///
+    /// The table is created, if it does not exist, when the schema is applied
+    pub fn add_table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'a, K, V>,
+    ) -> &mut Self {
+        self.tables
+            .push(Box::new(move |txn| txn.open_table(definition).map(|_| ())));
+        self
+    }
+
+    /// Add a multimap table to the schema
+    ///
+    /// The table is created, if it does not exist, when the schema is applied
+    pub fn add_multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'a, K, V>,
+    ) -> &mut Self {
+        self.tables.push(Box::new(move |txn| {
+            txn.open_multimap_table(definition).map(|_| ())
+        }));
+        self
+    }
+
+    /// Register the migration from version `from` to version `from + 1`
+    ///
+    /// Replaces any migration previously registered for `from`
+    pub fn add_migration(
+        &mut self,
+        from: u64,
+        migration: impl Fn(&WriteTransaction) -> Result<(), Error> + 'a,
+    ) -> &mut Self {
+        self.migrations.insert(from, Box::new(migration));
+        self
+    }
+
+    pub(crate) fn apply(&self, txn: &WriteTransaction) -> Result<(), SchemaError> {
+        let stored = match txn.schema_version()? {
+            Some(version) => version,
+            None if Self::is_new(txn)? => self.version,
+            None => self.initial_version,
+        };
+        if stored > self.version {
+            return Err(SchemaError::UnsupportedVersion {
+                stored,
+                supported: self.version,
+            });
+        }
+        // Check that every step is available, before running any of them
+        for from in stored..self.version {
+            if !self.migrations.contains_key(&from) {
+                return Err(SchemaError::MissingMigration(from));
+            }
+        }
+        for from in stored..self.version {
+            let migration = &self.migrations[&from];
+            migration(txn).map_err(|error| SchemaError::MigrationFailed {
+                from,
+                error: Box::new(error),
+            })?;
+        }
+
+        for validate in self.tables.iter() {
+            validate(txn)?;
+        }
+        txn.set_schema_version(self.version)?;
+
+        Ok(())
+    }
+
+    fn is_new(txn: &WriteTransaction) -> Result<bool, SchemaError> {
+        Ok(txn.list_tables()?.next().is_none() && txn.list_multimap_tables()?.next().is_none())
+    }
+}
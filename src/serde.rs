@@ -0,0 +1,146 @@
+//! [`Value`] implementations for types which implement [serde](https://serde.rs)'s `Serialize` and
+//! `Deserialize`
+//!
+//! Each wrapper is enabled by the cargo feature of the same name: `bincode`, `json`, or
+//! `postcard`. The wrappers are only used in table definitions, and values are read and written as
+//! the wrapped type:
+//!
+//! ```rust,ignore
+//! use redb::serde::Bincode;
+//! use redb::TableDefinition;
+//!
+//! #[derive(Debug, serde::Serialize, serde::Deserialize)]
+//! struct User {
+//!     name: String,
+//!     age: u32,
+//! }
+//!
+//! const USERS: TableDefinition<u64, Bincode<User>> = TableDefinition::new("users");
+//! ```
+//!
+//! The stored [`TypeName`] of a wrapper identifies its encoding, but not the wrapped type, because
+//! Rust type names are not stable between compiler versions. Renaming or moving the wrapped type
+//! is therefore allowed, but changing its fields may make existing values impossible to decode.
+//! Use [`Versioned`] to record a format version with each value, so that values written with an
+//! older version of a type can be recognized when they are read, or the table converted with
+//! [`WriteTransaction::migrate_table`](crate::WriteTransaction::migrate_table).
+
+use crate::types::{TypeName, Value};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+macro_rules! serde_value {
+    ($(#[$attr:meta])* $name:ident, $feature:literal, $serialize:path, $deserialize:path) => {
+        $(#[$attr])*
+        #[cfg(feature = $feature)]
+        #[derive(Debug)]
+        pub struct $name<T>(PhantomData<T>);
+
+        #[cfg(feature = $feature)]
+        impl<T> Value for $name<T>
+        where
+            T: Debug + ::serde::Serialize + ::serde::de::DeserializeOwned,
+        {
+            type SelfType<'a> = T
+            where
+                Self: 'a;
+            type AsBytes<'a> = Vec<u8>
+            where
+                Self: 'a;
+
+            fn fixed_width() -> Option<usize> {
+                None
+            }
+
+            fn from_bytes<'a>(data: &'a [u8]) -> T
+            where
+                Self: 'a,
+            {
+                $deserialize(data).unwrap()
+            }
+
+            fn as_bytes<'a, 'b: 'a>(value: &'a T) -> Vec<u8>
+            where
+                Self: 'a,
+                Self: 'b,
+            {
+                $serialize(value).unwrap()
+            }
+
+            fn type_name() -> TypeName {
+                TypeName::internal(concat!("redb::serde::", stringify!($name)))
+            }
+        }
+    };
+}
+
+serde_value!(
+    /// Stores a value of type `T` encoded with [bincode](https://docs.rs/bincode)
+    Bincode,
+    "bincode",
+    bincode::serialize,
+    bincode::deserialize
+);
+
+serde_value!(
+    /// Stores a value of type `T` encoded as JSON, with [serde_json](https://docs.rs/serde_json)
+    Json,
+    "json",
+    serde_json::to_vec,
+    serde_json::from_slice
+);
+
+serde_value!(
+    /// Stores a value of type `T` encoded with [postcard](https://docs.rs/postcard)
+    Postcard,
+    "postcard",
+    postcard::to_allocvec,
+    postcard::from_bytes
+);
+
+/// Prefixes each value encoded by `V` with a format version byte
+///
+/// Values are read and written as a `(version, value)` pair, so the version of each stored value
+/// is returned along with it. `V` must be able to decode the values of every version stored in
+/// the table: to change the encoding, use a `V` such as `&[u8]` and decode the value according to
+/// its version, or convert the table with
+/// [`WriteTransaction::migrate_table`](crate::WriteTransaction::migrate_table).
+#[derive(Debug)]
+pub struct Versioned<V>(PhantomData<V>);
+
+impl<V: Value> Value for Versioned<V> {
+    type SelfType<'a> = (u8, V::SelfType<'a>)
+    where
+        Self: 'a;
+    type AsBytes<'a> = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        V::fixed_width().map(|x| x + 1)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> (u8, V::SelfType<'a>)
+    where
+        Self: 'a,
+    {
+        (data[0], V::from_bytes(&data[1..]))
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Vec<u8>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut result = vec![value.0];
+        result.extend_from_slice(V::as_bytes(&value.1).as_ref());
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal(&format!(
+            "redb::serde::Versioned<{}>",
+            V::type_name().name()
+        ))
+    }
+}
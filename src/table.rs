@@ -1,17 +1,22 @@
 use crate::db::TransactionGuard;
+use crate::merge::MergeOperator;
 use crate::sealed::Sealed;
 use crate::tree_store::{
-    AccessGuardMut, Btree, BtreeExtractIf, BtreeHeader, BtreeMut, BtreeRangeIter, PageHint,
-    PageNumber, RawBtree, TransactionalMemory, MAX_PAIR_LENGTH, MAX_VALUE_LENGTH,
+    AccessGuardMut, Btree, BtreeExtractIf, BtreeHeader, BtreeMut, BtreeRangeIter,
+    InternalTableDefinition, PageHint, PageNumber, RawBtree, TableType, TransactionalMemory,
+    MAX_PAIR_LENGTH, MAX_VALUE_LENGTH,
 };
-use crate::types::{Key, MutInPlaceValue, Value};
-use crate::{AccessGuard, StorageError, WriteTransaction};
+use crate::types::{Key, MutInPlaceValue, TypeName, Value};
+use crate::{AccessGuard, BulkLoadError, StorageError, TableError, WriteTransaction};
 use crate::{Result, TableHandle};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
-use std::ops::RangeBounds;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::{Bound, RangeBounds};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 /// Informational storage stats about a table
 #[derive(Debug)]
@@ -57,11 +62,88 @@ impl TableStats {
     }
 }
 
+/// The stored definition of a table, which describes it without needing its Rust types
+#[derive(Debug, Clone)]
+pub struct TableInfo {
+    name: String,
+    multimap: bool,
+    key_type: TypeName,
+    value_type: TypeName,
+    fixed_key_size: Option<usize>,
+    fixed_value_size: Option<usize>,
+    length: u64,
+    root_page: Option<PageNumber>,
+}
+
+impl TableInfo {
+    pub(crate) fn new(name: &str, definition: &InternalTableDefinition) -> Self {
+        Self {
+            name: name.to_string(),
+            multimap: definition.get_type() == TableType::Multimap,
+            key_type: definition.key_type(),
+            value_type: definition.value_type(),
+            fixed_key_size: definition.get_fixed_key_size(),
+            fixed_value_size: definition.get_fixed_value_size(),
+            length: definition.get_length(),
+            root_page: definition.get_root().map(|header| header.root),
+        }
+    }
+
+    /// Name of the table
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` if this is a multimap table
+    pub fn is_multimap(&self) -> bool {
+        self.multimap
+    }
+
+    /// Type of the table's keys
+    pub fn key_type(&self) -> &TypeName {
+        &self.key_type
+    }
+
+    /// Type of the table's values
+    pub fn value_type(&self) -> &TypeName {
+        &self.value_type
+    }
+
+    /// Width of the table's keys, or None if they are variable width
+    pub fn fixed_key_width(&self) -> Option<usize> {
+        self.fixed_key_size
+    }
+
+    /// Width of the table's values, or None if they are variable width
+    pub fn fixed_value_width(&self) -> Option<usize> {
+        self.fixed_value_size
+    }
+
+    /// Number of entries in the table
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns `true` if the table is empty
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Identifier of the page at the root of the table's btree, or None if the table is empty.
+    /// Tables which share a root page (see [`crate::WriteTransaction::clone_table`]) have the
+    /// same contents
+    pub fn root_page(&self) -> Option<u64> {
+        self.root_page
+            .map(|page| u64::from_le_bytes(page.to_le_bytes()))
+    }
+}
+
 /// A table containing key-value mappings
 pub struct Table<'txn, K: Key + 'static, V: Value + 'static> {
     name: String,
     transaction: &'txn WriteTransaction,
     tree: BtreeMut<'txn, K, V>,
+    merge_operator: Option<&'static dyn MergeOperator<V>>,
 }
 
 impl<K: Key + 'static, V: Value + 'static> TableHandle for Table<'_, K, V> {
@@ -74,6 +156,7 @@ impl<'txn, K: Key + 'static, V: Value + 'static> Table<'txn, K, V> {
     pub(crate) fn new(
         name: &str,
         table_root: Option<BtreeHeader>,
+        merge_operator: Option<&'static dyn MergeOperator<V>>,
         freed_pages: Arc<Mutex<Vec<PageNumber>>>,
         mem: Arc<TransactionalMemory>,
         transaction: &'txn WriteTransaction,
@@ -87,6 +170,7 @@ impl<'txn, K: Key + 'static, V: Value + 'static> Table<'txn, K, V> {
                 mem,
                 freed_pages,
             ),
+            merge_operator,
         }
     }
 
@@ -193,17 +277,10 @@ impl<'txn, K: Key + 'static, V: Value + 'static> Table<'txn, K, V> {
         key: impl Borrow<K::SelfType<'k>>,
         value: impl Borrow<V::SelfType<'v>>,
     ) -> Result<Option<AccessGuard<V>>> {
-        let value_len = V::as_bytes(value.borrow()).as_ref().len();
-        if value_len > MAX_VALUE_LENGTH {
-            return Err(StorageError::ValueTooLarge(value_len));
-        }
-        let key_len = K::as_bytes(key.borrow()).as_ref().len();
-        if key_len > MAX_VALUE_LENGTH {
-            return Err(StorageError::ValueTooLarge(key_len));
-        }
-        if value_len + key_len > MAX_PAIR_LENGTH {
-            return Err(StorageError::ValueTooLarge(value_len + key_len));
-        }
+        check_lengths(
+            K::as_bytes(key.borrow()).as_ref().len(),
+            V::as_bytes(value.borrow()).as_ref().len(),
+        )?;
         self.tree.insert(key.borrow(), value.borrow())
     }
 
@@ -216,6 +293,176 @@ impl<'txn, K: Key + 'static, V: Value + 'static> Table<'txn, K, V> {
     ) -> Result<Option<AccessGuard<V>>> {
         self.tree.remove(key.borrow())
     }
+
+    /// Inserts mapping of the given key to the given value, if the key is not present
+    ///
+    /// Returns `true` if the value was inserted
+    pub fn insert_if_absent<'k, 'v>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'k>>,
+        value: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<bool> {
+        let key = key.borrow();
+        if self.tree.get(key)?.is_some() {
+            return Ok(false);
+        }
+        self.insert(key, value)?;
+        Ok(true)
+    }
+
+    /// Replaces the value of the given key with `new`, if its current value is `expected`. If
+    /// `expected` is `None`, `new` is only inserted if the key is not present
+    ///
+    /// Values are compared by their serialized bytes
+    ///
+    /// Returns `true` if the value was replaced
+    pub fn compare_and_swap<'k, 'e, 'v>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'k>>,
+        expected: Option<&V::SelfType<'e>>,
+        new: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<bool> {
+        let key = key.borrow();
+        let current = self.tree.get(key)?;
+        let matches = match (&current, expected) {
+            (Some(current), Some(expected)) => {
+                V::as_bytes(&current.value()).as_ref() == V::as_bytes(expected).as_ref()
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        drop(current);
+        if matches {
+            self.insert(key, new)?;
+        }
+        Ok(matches)
+    }
+
+    /// Replaces the value of the given key with the result of `f`, which is passed the current
+    /// value, if any. If `f` returns `None`, the key is removed
+    ///
+    /// The key is looked up and modified in a single traversal of the table
+    ///
+    /// Returns the old value, if the key was present in the table
+    pub fn update<'a, 'v, T: Borrow<V::SelfType<'v>>>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'a>>,
+        f: impl for<'f> FnOnce(Option<V::SelfType<'f>>) -> Option<T>,
+    ) -> Result<Option<AccessGuard<'_, V>>> {
+        let key = key.borrow();
+        let key_len = K::as_bytes(key).as_ref().len();
+        self.tree.update(key, |old_value| {
+            if let Some(value) = f(old_value.map(V::from_bytes)) {
+                let value = V::as_bytes(value.borrow()).as_ref().to_vec();
+                check_lengths(key_len, value.len())?;
+                Ok(Some(value))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Merges `delta` into the value of the given key, using the table's merge operator
+    ///
+    /// The key is looked up and modified in a single traversal of the table
+    ///
+    /// Returns [`StorageError::MissingMergeOperator`] if the table was opened without a merge
+    /// operator. See [`TableDefinition::with_merge_operator`]
+    ///
+    /// [`TableDefinition::with_merge_operator`]: crate::TableDefinition::with_merge_operator
+    pub fn merge<'a, 'd>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'a>>,
+        delta: impl Borrow<V::SelfType<'d>>,
+    ) -> Result {
+        let operator = self
+            .merge_operator
+            .ok_or_else(|| StorageError::MissingMergeOperator(self.name.clone()))?;
+        let key = key.borrow();
+        let key_len = K::as_bytes(key).as_ref().len();
+        let delta = V::as_bytes(delta.borrow());
+        self.tree.update(key, |old_value| {
+            let new_value =
+                operator.merge(old_value.map(V::from_bytes), V::from_bytes(delta.as_ref()));
+            if let Some(ref value) = new_value {
+                check_lengths(key_len, value.len())?;
+            }
+            Ok(new_value)
+        })?;
+        Ok(())
+    }
+
+    /// Loads `entries` into the table, which must be empty
+    ///
+    /// The entries must be in strictly ascending order by key. Rather than inserting them one at
+    /// a time, the table is built bottom-up from full leaf and branch pages, which is much faster
+    /// for large numbers of entries. If an error is returned, the table is left empty
+    pub fn bulk_load<'k, 'v, KI, VI>(
+        &mut self,
+        entries: impl IntoIterator<Item = (KI, VI)>,
+    ) -> Result<(), BulkLoadError>
+    where
+        KI: Borrow<K::SelfType<'k>>,
+        VI: Borrow<V::SelfType<'v>>,
+    {
+        if self.tree.get_root().is_some() {
+            return Err(BulkLoadError::TableNotEmpty(self.name.clone()));
+        }
+        self.tree.build_sorted(|builder| {
+            let mut previous_key: Option<Vec<u8>> = None;
+            for (key, value) in entries {
+                let key_bytes = K::as_bytes(key.borrow());
+                let key_bytes = key_bytes.as_ref();
+                let value_bytes = V::as_bytes(value.borrow());
+                let value_bytes = value_bytes.as_ref();
+                check_lengths(key_bytes.len(), value_bytes.len())?;
+                if let Some(ref mut previous) = previous_key {
+                    if K::compare(previous, key_bytes) != Ordering::Less {
+                        return Err(BulkLoadError::UnsortedInput);
+                    }
+                    previous.clear();
+                    previous.extend_from_slice(key_bytes);
+                } else {
+                    previous_key = Some(key_bytes.to_vec());
+                }
+                builder.push(key_bytes, value_bytes)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Gets the entry for the given key, which can be used to inspect and modify it
+    ///
+    /// The key is looked up once, and an occupied entry holds a reference to its value, so reading
+    /// the value does not look it up again. Each modification of the entry writes the key in a
+    /// single traversal of the table, and holds a reference to the written value
+    pub fn entry<'a>(
+        &mut self,
+        key: impl Borrow<K::SelfType<'a>>,
+    ) -> Result<Entry<'_, 'txn, K, V>> {
+        let value = self.tree.get_detached(key.borrow())?;
+        let key = K::as_bytes(key.borrow()).as_ref().to_vec();
+        Ok(match value {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                table: self,
+                key,
+                value,
+            }),
+            None => Entry::Vacant(VacantEntry { table: self, key }),
+        })
+    }
+
+    // Inserts the value of an entry, and returns the inserted value
+    fn insert_entry<'v>(
+        &mut self,
+        key: &[u8],
+        value: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<AccessGuard<'static, V>> {
+        let value = value.borrow();
+        check_lengths(key.len(), V::as_bytes(value).as_ref().len())?;
+        self.tree.insert_and_get(&K::from_bytes(key), value)
+    }
 }
 
 impl<'txn, K: Key + 'static, V: MutInPlaceValue + 'static> Table<'txn, K, V> {
@@ -267,6 +514,17 @@ impl<'txn, K: Key + 'static, V: Value + 'static> ReadableTable<K, V> for Table<'
         self.tree.get(key.borrow())
     }
 
+    fn get_many<'a>(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<K::SelfType<'a>>>,
+    ) -> Result<Vec<Option<AccessGuard<'_, V>>>> {
+        let keys: Vec<Vec<u8>> = keys
+            .into_iter()
+            .map(|key| K::as_bytes(key.borrow()).as_ref().to_vec())
+            .collect();
+        self.tree.get_many(&keys)
+    }
+
     fn range<'a, KR>(&self, range: impl RangeBounds<KR> + 'a) -> Result<Range<K, V>>
     where
         KR: Borrow<K::SelfType<'a>> + 'a,
@@ -338,6 +596,373 @@ impl<K: Key + 'static, V: Value + 'static> Debug for Table<'_, K, V> {
     }
 }
 
+// Keys of an untyped table, accessed as raw bytes but ordered the same way as K
+#[derive(Debug)]
+pub(crate) struct OrderedBytes<K: Key + 'static>(PhantomData<K>);
+
+impl<K: Key + 'static> Value for OrderedBytes<K> {
+    type SelfType<'a> = &'a [u8]
+    where
+        Self: 'a;
+    type AsBytes<'a> = &'a [u8]
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        K::fixed_width()
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> &'a [u8]
+    where
+        Self: 'a,
+    {
+        data
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> &'a [u8]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value
+    }
+
+    fn type_name() -> TypeName {
+        K::type_name()
+    }
+}
+
+impl<K: Key + 'static> Key for OrderedBytes<K> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        K::compare(data1, data2)
+    }
+}
+
+type UntypedEntry<'a> = (
+    AccessGuard<'a, &'static [u8]>,
+    AccessGuard<'a, &'static [u8]>,
+);
+type UntypedEntryIter = Box<dyn DoubleEndedIterator<Item = Result<UntypedEntry<'static>>>>;
+
+// The operations of an untyped table, which are independent of the type used to order its keys
+trait UntypedTableOps {
+    fn insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<'_, &'static [u8]>>>;
+
+    fn remove(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>>;
+
+    fn get(&self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>>;
+
+    fn range(&self, range: (Bound<&[u8]>, Bound<&[u8]>)) -> Result<UntypedEntryIter>;
+
+    fn stats(&self) -> Result<TableStats>;
+
+    fn len(&self) -> Result<u64>;
+}
+
+impl<K: Key + 'static> UntypedTableOps for Table<'_, OrderedBytes<K>, &'static [u8]> {
+    fn insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        Table::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        Table::remove(self, key)
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        ReadableTable::get(self, key)
+    }
+
+    fn range(&self, range: (Bound<&[u8]>, Bound<&[u8]>)) -> Result<UntypedEntryIter> {
+        let iter = self.tree.range::<_, &[u8]>(&range)?.map(|x| {
+            x.map(|entry| {
+                let (page, key_range, value_page, value_range) = entry.into_raw();
+                let key = AccessGuard::with_page(page, key_range);
+                let value = AccessGuard::with_page(value_page, value_range);
+                (key, value)
+            })
+        });
+        Ok(Box::new(iter))
+    }
+
+    fn stats(&self) -> Result<TableStats> {
+        ReadableTableMetadata::stats(self)
+    }
+
+    fn len(&self) -> Result<u64> {
+        ReadableTableMetadata::len(self)
+    }
+}
+
+pub(crate) type KeyComparator = fn(&[u8], &[u8]) -> Ordering;
+
+// Checks that a key & value of the given lengths can be stored
+fn check_lengths(key_len: usize, value_len: usize) -> Result {
+    if value_len > MAX_VALUE_LENGTH {
+        return Err(StorageError::ValueTooLarge(value_len));
+    }
+    if key_len > MAX_VALUE_LENGTH {
+        return Err(StorageError::ValueTooLarge(key_len));
+    }
+    if value_len + key_len > MAX_PAIR_LENGTH {
+        return Err(StorageError::ValueTooLarge(value_len + key_len));
+    }
+    Ok(())
+}
+
+// Invokes the given macro with the builtin key types, whose ordering is known from their type name
+macro_rules! builtin_key_types {
+    ($m:ident) => {
+        $m!(
+            (),
+            bool,
+            char,
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            f32,
+            f64,
+            &str,
+            String,
+            &[u8],
+            Duration,
+            SystemTime,
+            Ipv4Addr,
+            Ipv6Addr,
+            IpAddr
+        )
+    };
+}
+
+// Memcmp keys are ordered by their bytes, whatever type they wrap
+fn is_memcmp(key_type: &TypeName) -> bool {
+    key_type.name().starts_with("redb::Memcmp<") && *key_type == TypeName::internal(key_type.name())
+}
+
+pub(crate) type UntypedTableConstructor = for<'txn> fn(
+    &str,
+    Option<BtreeHeader>,
+    Option<usize>,
+    Arc<Mutex<Vec<PageNumber>>>,
+    Arc<TransactionalMemory>,
+    &'txn WriteTransaction,
+) -> UntypedTable<'txn>;
+
+fn new_untyped_table<'txn, K: Key + 'static>(
+    name: &str,
+    table_root: Option<BtreeHeader>,
+    fixed_value_size: Option<usize>,
+    freed_pages: Arc<Mutex<Vec<PageNumber>>>,
+    mem: Arc<TransactionalMemory>,
+    transaction: &'txn WriteTransaction,
+) -> UntypedTable<'txn> {
+    let table: Table<'txn, OrderedBytes<K>, &'static [u8]> = Table {
+        name: name.to_string(),
+        transaction,
+        tree: BtreeMut::with_fixed_value_size(
+            table_root,
+            transaction.transaction_guard(),
+            mem,
+            freed_pages,
+            fixed_value_size,
+        ),
+        merge_operator: None,
+    };
+
+    UntypedTable {
+        name: name.to_string(),
+        fixed_key_size: K::fixed_width(),
+        fixed_value_size,
+        transaction,
+        inner: Box::new(table),
+    }
+}
+
+/// A writable table whose keys and values are accessed as raw bytes
+///
+/// Keys are kept in the order defined by the table's key type, so the table can be read back
+/// with its typed definition.
+pub struct UntypedTable<'txn> {
+    name: String,
+    fixed_key_size: Option<usize>,
+    fixed_value_size: Option<usize>,
+    transaction: &'txn WriteTransaction,
+    inner: Box<dyn UntypedTableOps + 'txn>,
+}
+
+impl TableHandle for UntypedTable<'_> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<'txn> UntypedTable<'txn> {
+    // Returns the constructor for a table whose keys are ordered by K
+    pub(crate) fn ordered_by<K: Key + 'static>() -> UntypedTableConstructor {
+        new_untyped_table::<K>
+    }
+
+    // Returns the constructor for a table whose keys have the given type, if it is a builtin
+    // type whose ordering is known
+    pub(crate) fn builtin_ordering(key_type: &TypeName) -> Option<UntypedTableConstructor> {
+        macro_rules! builtin_keys {
+            ($($t:ty),*) => {
+                $(
+                    if *key_type == <$t as Value>::type_name() {
+                        return Some(Self::ordered_by::<$t>());
+                    }
+                )*
+            };
+        }
+
+        builtin_key_types!(builtin_keys);
+        if is_memcmp(key_type) {
+            return Some(Self::ordered_by::<&[u8]>());
+        }
+        None
+    }
+
+    // Returns the comparison function of the given key type, if it is a builtin type whose
+    // ordering is known
+    pub(crate) fn builtin_comparator(key_type: &TypeName) -> Option<KeyComparator> {
+        macro_rules! builtin_keys {
+            ($($t:ty),*) => {
+                $(
+                    if *key_type == <$t as Value>::type_name() {
+                        return Some(<$t as Key>::compare);
+                    }
+                )*
+            };
+        }
+
+        builtin_key_types!(builtin_keys);
+        if is_memcmp(key_type) {
+            return Some(<&[u8] as Key>::compare);
+        }
+        None
+    }
+
+    fn check_widths(&self, key: &[u8], value: &[u8]) -> Result<(), TableError> {
+        if let Some(width) = self.fixed_key_size {
+            if key.len() != width {
+                return Err(TableError::InvalidKeyWidth {
+                    table: self.name.clone(),
+                    expected: width,
+                    actual: key.len(),
+                });
+            }
+        }
+        if let Some(width) = self.fixed_value_size {
+            if value.len() != width {
+                return Err(TableError::InvalidValueWidth {
+                    table: self.name.clone(),
+                    expected: width,
+                    actual: value.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert mapping of the given key to the given value
+    ///
+    /// If key is already present it is replaced
+    ///
+    /// Returns the old value, if the key was present in the table, otherwise None is returned
+    ///
+    /// Returns [`TableError::InvalidKeyWidth`] or [`TableError::InvalidValueWidth`] if the key or
+    /// value does not have the width of a fixed width type
+    pub fn insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<'_, &'static [u8]>>, TableError> {
+        self.check_widths(key, value)?;
+        Ok(self.inner.insert(key, value)?)
+    }
+
+    /// Removes the given key
+    ///
+    /// Returns the old value, if the key was present in the table
+    pub fn remove(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        self.inner.remove(key)
+    }
+
+    /// Returns the value corresponding to the given key
+    pub fn get(&self, key: &[u8]) -> Result<Option<AccessGuard<'_, &'static [u8]>>> {
+        self.inner.get(key)
+    }
+
+    /// Returns a double-ended iterator over a range of elements in the table
+    pub fn range<'a>(&self, range: impl RangeBounds<&'a [u8]>) -> Result<UntypedRange<'_>> {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        Ok(UntypedRange {
+            inner: self.inner.range(bounds)?,
+            _transaction_guard: self.transaction.transaction_guard(),
+            _lifetime: Default::default(),
+        })
+    }
+
+    /// Returns a double-ended iterator over all elements in the table
+    pub fn iter(&self) -> Result<UntypedRange<'_>> {
+        self.range(..)
+    }
+}
+
+impl ReadableTableMetadata for UntypedTable<'_> {
+    fn stats(&self) -> Result<TableStats> {
+        self.inner.stats()
+    }
+
+    fn len(&self) -> Result<u64> {
+        self.inner.len()
+    }
+}
+
+impl Sealed for UntypedTable<'_> {}
+
+impl Debug for UntypedTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UntypedTable [ name: \"{}\" ]", self.name)
+    }
+}
+
+/// An iterator over the raw bytes of the entries in an [`UntypedTable`]
+pub struct UntypedRange<'a> {
+    inner: UntypedEntryIter,
+    _transaction_guard: Arc<TransactionGuard>,
+    // This lifetime is here so that `&` can be held on `UntypedTable` preventing concurrent mutation
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Iterator for UntypedRange<'a> {
+    type Item = Result<UntypedEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<'a> DoubleEndedIterator for UntypedRange<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
 pub trait ReadableTableMetadata {
     /// Retrieves information about storage usage for the table
     fn stats(&self) -> Result<TableStats>;
@@ -355,6 +980,17 @@ pub trait ReadableTable<K: Key + 'static, V: Value + 'static>: ReadableTableMeta
     /// Returns the value corresponding to the given key
     fn get<'a>(&self, key: impl Borrow<K::SelfType<'a>>) -> Result<Option<AccessGuard<V>>>;
 
+    /// Returns the values corresponding to the given keys, in the same order as the keys
+    ///
+    /// This is faster than calling [`ReadableTable::get`] for each key, since the keys are looked
+    /// up in sorted order, and pages shared by neighbouring keys are only read once
+    fn get_many<'a>(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<K::SelfType<'a>>>,
+    ) -> Result<Vec<Option<AccessGuard<'_, V>>>> {
+        keys.into_iter().map(|key| self.get(key)).collect()
+    }
+
     /// Returns a double-ended iterator over a range of elements in the table
     ///
     /// # Examples
@@ -512,6 +1148,17 @@ impl<K: Key + 'static, V: Value + 'static> ReadableTable<K, V> for ReadOnlyTable
         self.tree.get(key.borrow())
     }
 
+    fn get_many<'a>(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<K::SelfType<'a>>>,
+    ) -> Result<Vec<Option<AccessGuard<'_, V>>>> {
+        let keys: Vec<Vec<u8>> = keys
+            .into_iter()
+            .map(|key| K::as_bytes(key.borrow()).as_ref().to_vec())
+            .collect();
+        self.tree.get_many(&keys)
+    }
+
     fn range<'a, KR>(&self, range: impl RangeBounds<KR> + 'a) -> Result<Range<K, V>>
     where
         KR: Borrow<K::SelfType<'a>> + 'a,
@@ -530,6 +1177,171 @@ impl<K: Key + 'static, V: Value + 'static> Debug for ReadOnlyTable<K, V> {
     }
 }
 
+/// An entry in a [`Table`], which is either occupied or vacant
+///
+/// Returned by [`Table::entry`]
+pub enum Entry<'a, 'txn, K: Key + 'static, V: Value + 'static> {
+    Occupied(OccupiedEntry<'a, 'txn, K, V>),
+    Vacant(VacantEntry<'a, 'txn, K, V>),
+}
+
+impl<'a, 'txn, K: Key + 'static, V: Value + 'static> Entry<'a, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> K::SelfType<'_> {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant
+    ///
+    /// Returns the value of the entry
+    pub fn or_insert<'v>(
+        self,
+        default: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<AccessGuard<'a, V>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_value()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `default` if the entry is vacant
+    ///
+    /// Returns the value of the entry
+    pub fn or_insert_with<'v, T: Borrow<V::SelfType<'v>>>(
+        self,
+        default: impl FnOnce() -> T,
+    ) -> Result<AccessGuard<'a, V>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_value()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Replaces the value of the entry with the result of `f`, if the entry is occupied
+    pub fn and_modify<'v, T: Borrow<V::SelfType<'v>>>(
+        self,
+        f: impl for<'f> FnOnce(V::SelfType<'f>) -> T,
+    ) -> Result<Self> {
+        match self {
+            Entry::Occupied(entry) => {
+                let value = f(entry.get());
+                Ok(Entry::Occupied(entry.replace(value)?))
+            }
+            Entry::Vacant(entry) => Ok(Entry::Vacant(entry)),
+        }
+    }
+}
+
+impl<K: Key + 'static, V: Value + 'static> Debug for Entry<'_, '_, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+/// An occupied entry in a [`Table`]
+pub struct OccupiedEntry<'a, 'txn, K: Key + 'static, V: Value + 'static> {
+    table: &'a mut Table<'txn, K, V>,
+    key: Vec<u8>,
+    value: AccessGuard<'a, V>,
+}
+
+impl<'a, 'txn, K: Key + 'static, V: Value + 'static> OccupiedEntry<'a, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> K::SelfType<'_> {
+        K::from_bytes(&self.key)
+    }
+
+    /// Returns the value of this entry
+    pub fn get(&self) -> V::SelfType<'_> {
+        self.value.value()
+    }
+
+    /// Replaces the value of this entry
+    ///
+    /// Returns the old value
+    pub fn insert<'v>(
+        &mut self,
+        value: impl Borrow<V::SelfType<'v>>,
+    ) -> Result<AccessGuard<'_, V>> {
+        // The old value may be stored in a page which is modified by the insert, so it is copied
+        // out first
+        let old_value = V::as_bytes(&self.value.value()).as_ref().to_vec();
+        self.value = AccessGuard::with_owned_value(old_value);
+        let value = self.table.insert_entry(&self.key, value)?;
+        Ok(std::mem::replace(&mut self.value, value))
+    }
+
+    /// Removes this entry from the table
+    ///
+    /// Returns the removed value
+    pub fn remove(self) -> Result<AccessGuard<'a, V>> {
+        let OccupiedEntry { table, key, value } = self;
+        // Release the value's page before it is modified
+        drop(value);
+        let value = table.tree.remove(&K::from_bytes(&key))?;
+        value.ok_or_else(|| StorageError::Corrupted("entry removed from the table".to_string()))
+    }
+
+    fn into_value(self) -> AccessGuard<'a, V> {
+        self.value
+    }
+
+    // Replaces the value of this entry, without returning the old one
+    fn replace<'v>(self, value: impl Borrow<V::SelfType<'v>>) -> Result<Self> {
+        let OccupiedEntry {
+            table,
+            key,
+            value: old_value,
+        } = self;
+        drop(old_value);
+        let value = table.insert_entry(&key, value)?;
+        Ok(OccupiedEntry { table, key, value })
+    }
+}
+
+impl<K: Key + 'static, V: Value + 'static> Debug for OccupiedEntry<'_, '_, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", &self.key())
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+/// A vacant entry in a [`Table`]
+pub struct VacantEntry<'a, 'txn, K: Key + 'static, V: Value + 'static> {
+    table: &'a mut Table<'txn, K, V>,
+    key: Vec<u8>,
+}
+
+impl<'a, 'txn, K: Key + 'static, V: Value + 'static> VacantEntry<'a, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> K::SelfType<'_> {
+        K::from_bytes(&self.key)
+    }
+
+    /// Inserts `value` into the table at this entry's key
+    ///
+    /// Returns the inserted value
+    pub fn insert<'v>(self, value: impl Borrow<V::SelfType<'v>>) -> Result<AccessGuard<'a, V>> {
+        self.table.insert_entry(&self.key, value)
+    }
+}
+
+impl<K: Key + 'static, V: Value + 'static> Debug for VacantEntry<'_, '_, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VacantEntry")
+            .field("key", &self.key())
+            .finish()
+    }
+}
+
 pub struct ExtractIf<
     'a,
     K: Key + 'static,
@@ -563,9 +1375,9 @@ impl<
     fn next(&mut self) -> Option<Self::Item> {
         let entry = self.inner.next()?;
         Some(entry.map(|entry| {
-            let (page, key_range, value_range) = entry.into_raw();
-            let key = AccessGuard::with_page(page.clone(), key_range);
-            let value = AccessGuard::with_page(page, value_range);
+            let (page, key_range, value_page, value_range) = entry.into_raw();
+            let key = AccessGuard::with_page(page, key_range);
+            let value = AccessGuard::with_page(value_page, value_range);
             (key, value)
         }))
     }
@@ -581,9 +1393,9 @@ impl<
     fn next_back(&mut self) -> Option<Self::Item> {
         let entry = self.inner.next_back()?;
         Some(entry.map(|entry| {
-            let (page, key_range, value_range) = entry.into_raw();
-            let key = AccessGuard::with_page(page.clone(), key_range);
-            let value = AccessGuard::with_page(page, value_range);
+            let (page, key_range, value_page, value_range) = entry.into_raw();
+            let key = AccessGuard::with_page(page, key_range);
+            let value = AccessGuard::with_page(value_page, value_range);
             (key, value)
         }))
     }
@@ -613,9 +1425,9 @@ impl<'a, K: Key + 'static, V: Value + 'static> Iterator for Range<'a, K, V> {
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|x| {
             x.map(|entry| {
-                let (page, key_range, value_range) = entry.into_raw();
-                let key = AccessGuard::with_page(page.clone(), key_range);
-                let value = AccessGuard::with_page(page, value_range);
+                let (page, key_range, value_page, value_range) = entry.into_raw();
+                let key = AccessGuard::with_page(page, key_range);
+                let value = AccessGuard::with_page(value_page, value_range);
                 (key, value)
             })
         })
@@ -626,9 +1438,9 @@ impl<'a, K: Key + 'static, V: Value + 'static> DoubleEndedIterator for Range<'a,
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|x| {
             x.map(|entry| {
-                let (page, key_range, value_range) = entry.into_raw();
-                let key = AccessGuard::with_page(page.clone(), key_range);
-                let value = AccessGuard::with_page(page, value_range);
+                let (page, key_range, value_page, value_range) = entry.into_raw();
+                let key = AccessGuard::with_page(page, key_range);
+                let value = AccessGuard::with_page(value_page, value_range);
                 (key, value)
             })
         })
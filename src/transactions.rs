@@ -1,20 +1,23 @@
-use crate::db::TransactionGuard;
+use crate::blob_table::{BlobTable, ReadOnlyBlobTable};
+use crate::db::{BlobTableDefinition, TransactionGuard};
 use crate::error::CommitError;
+use crate::export::{export_tables, import_tables, KeyOrderings};
 use crate::multimap_table::ReadOnlyUntypedMultimapTable;
 use crate::sealed::Sealed;
-use crate::table::ReadOnlyUntypedTable;
+use crate::table::{ReadOnlyUntypedTable, TableInfo, UntypedTableConstructor};
 use crate::transaction_tracker::{SavepointId, TransactionId, TransactionTracker};
 use crate::tree_store::{
-    Btree, BtreeHeader, BtreeMut, CachePriority, FreedPageList, FreedTableKey,
-    InternalTableDefinition, Page, PageHint, PageNumber, SerializedSavepoint, TableTree,
-    TableTreeMut, TableType, TransactionalMemory, MAX_PAIR_LENGTH, MAX_VALUE_LENGTH,
+    leaf_overflow_refs, BranchAccessor, Btree, BtreeHeader, BtreeMut, CachePriority, FreedPageList,
+    FreedTableKey, InternalTableDefinition, Page, PageHint, PageNumber, SerializedSavepoint,
+    TableTree, TableTreeMut, TableType, TransactionalMemory, BRANCH, MAX_PAIR_LENGTH,
+    MAX_VALUE_LENGTH,
 };
-use crate::types::{Key, Value};
+use crate::types::{Key, TypeName, Value};
 use crate::{
-    AccessGuard, MultimapTable, MultimapTableDefinition, MultimapTableHandle, Range,
-    ReadOnlyMultimapTable, ReadOnlyTable, Result, Savepoint, SavepointError, StorageError, Table,
-    TableDefinition, TableError, TableHandle, TransactionError, UntypedMultimapTableHandle,
-    UntypedTableHandle,
+    AccessGuard, ImportError, MultimapTable, MultimapTableDefinition, MultimapTableHandle, Range,
+    ReadOnlyMultimapTable, ReadOnlyTable, ReadableMultimapTable, ReadableTable, Result, Savepoint,
+    SavepointError, StorageError, Table, TableDefinition, TableError, TableHandle,
+    TransactionError, UntypedMultimapTableHandle, UntypedTable, UntypedTableHandle,
 };
 #[cfg(feature = "logging")]
 use log::{debug, warn};
@@ -22,6 +25,7 @@ use std::borrow::Borrow;
 use std::cmp::min;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::ops::RangeBounds;
 #[cfg(any(test, fuzzing))]
@@ -35,6 +39,12 @@ const NEXT_SAVEPOINT_TABLE: SystemTableDefinition<(), SavepointId> =
     SystemTableDefinition::new("next_savepoint_id");
 pub(crate) const SAVEPOINT_TABLE: SystemTableDefinition<SavepointId, SerializedSavepoint> =
     SystemTableDefinition::new("persistent_savepoints");
+const SCHEMA_VERSION_TABLE: SystemTableDefinition<(), u64> =
+    SystemTableDefinition::new("schema_version");
+// Pages that are referenced by more than one table, because of clone_table(), mapped to the
+// number of references they have beyond the first
+const SHARED_PAGES_TABLE: SystemTableDefinition<PageNumber, u64> =
+    SystemTableDefinition::new("shared_pages");
 
 pub struct SystemTableDefinition<'a, K: Key + 'static, V: Value + 'static> {
     name: &'a str,
@@ -166,6 +176,8 @@ pub enum Durability {
     /// attacker with an extremely high degree of control over the database's workload, including
     /// the ability to cause the database process to crash, can cause invalid data to be written
     /// with a valid checksum, leaving the database in an invalid, attacker-controlled state.
+    /// Databases created with `Builder::set_checksum_key` use a keyed cryptographic hash instead,
+    /// whose checksums can't be forged without the key.
     Immediate,
     /// Commits with this durability level have the same gaurantees as [Durability::Immediate]
     ///
@@ -376,6 +388,57 @@ impl<'db> TableNamespace<'db> {
         Ok(Table::new(
             definition.name(),
             root,
+            definition.merge_operator(),
+            transaction.freed_pages.clone(),
+            transaction.mem.clone(),
+            transaction,
+        ))
+    }
+
+    // Opens a table without its types. Keys are ordered by `key_order`, which must match the
+    // table's key type, or by the builtin ordering of the stored key type if it's None
+    #[track_caller]
+    fn open_untyped_table<'txn>(
+        &mut self,
+        transaction: &'txn WriteTransaction,
+        name: &str,
+        key_order: Option<(TypeName, UntypedTableConstructor)>,
+    ) -> Result<UntypedTable<'txn>, TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Opening untyped table: {}", name);
+        if let Some(location) = self.open_tables.get(name) {
+            return Err(TableError::TableAlreadyOpen(name.to_string(), location));
+        }
+        let definition = self
+            .table_tree
+            .get_table_untyped(name, TableType::Normal)?
+            .ok_or_else(|| TableError::TableDoesNotExist(name.to_string()))?;
+        let constructor = match key_order {
+            Some((key_type, constructor)) => {
+                if key_type != definition.key_type() {
+                    return Err(TableError::TableTypeMismatch {
+                        table: name.to_string(),
+                        key: definition.key_type(),
+                        value: definition.value_type(),
+                    });
+                }
+                constructor
+            }
+            None => UntypedTable::builtin_ordering(&definition.key_type()).ok_or_else(|| {
+                TableError::UnorderableKeyType {
+                    table: name.to_string(),
+                    key: definition.key_type(),
+                }
+            })?,
+        };
+        self.open_tables
+            .insert(name.to_string(), panic::Location::caller());
+        transaction.dirty.store(true, Ordering::Release);
+
+        Ok(constructor(
+            name,
+            definition.get_root(),
+            definition.get_fixed_value_size(),
             transaction.freed_pages.clone(),
             transaction.mem.clone(),
             transaction,
@@ -415,6 +478,149 @@ impl<'db> TableNamespace<'db> {
         self.inner_delete(name, TableType::Multimap)
     }
 
+    #[track_caller]
+    fn rename_table(
+        &mut self,
+        transaction: &WriteTransaction,
+        name: &str,
+        new_name: &str,
+        table_type: TableType,
+    ) -> Result<(), TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Renaming table: {} to {}", name, new_name);
+        for open_name in [name, new_name] {
+            if let Some(location) = self.open_tables.get(open_name) {
+                return Err(TableError::TableAlreadyOpen(
+                    open_name.to_string(),
+                    location,
+                ));
+            }
+        }
+        transaction.dirty.store(true, Ordering::Release);
+        self.table_tree.rename_table(name, new_name, table_type)
+    }
+
+    #[track_caller]
+    fn swap_tables(
+        &mut self,
+        transaction: &WriteTransaction,
+        a: &str,
+        b: &str,
+    ) -> Result<(), TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Swapping tables: {} and {}", a, b);
+        for name in [a, b] {
+            if let Some(location) = self.open_tables.get(name) {
+                return Err(TableError::TableAlreadyOpen(name.to_string(), location));
+            }
+        }
+        transaction.dirty.store(true, Ordering::Release);
+        self.table_tree.swap_tables(a, b, TableType::Normal)
+    }
+
+    #[track_caller]
+    fn replace_table(
+        &mut self,
+        transaction: &WriteTransaction,
+        target: &str,
+        source: &str,
+    ) -> Result<(), TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Replacing table: {} with {}", target, source);
+        for name in [target, source] {
+            if let Some(location) = self.open_tables.get(name) {
+                return Err(TableError::TableAlreadyOpen(name.to_string(), location));
+            }
+        }
+        transaction.dirty.store(true, Ordering::Release);
+        self.table_tree
+            .replace_table(target, source, TableType::Normal)
+    }
+
+    #[track_caller]
+    fn clone_table(
+        &mut self,
+        transaction: &WriteTransaction,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<PageNumber>, TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Cloning table: {} to {}", source, destination);
+        for name in [source, destination] {
+            if let Some(location) = self.open_tables.get(name) {
+                return Err(TableError::TableAlreadyOpen(name.to_string(), location));
+            }
+        }
+        transaction.dirty.store(true, Ordering::Release);
+        self.table_tree.clone_table(source, destination)
+    }
+
+    // Checks that the table `name` can be migrated to `new_name`, and returns its root and length,
+    // and the name under which the migrated table should be built
+    #[track_caller]
+    fn begin_migration<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        name: &str,
+        new_name: &str,
+        table_type: TableType,
+    ) -> Result<(Option<BtreeHeader>, u64, String), TableError> {
+        #[cfg(feature = "logging")]
+        debug!("Migrating table: {} to {}", name, new_name);
+        for name in [name, new_name] {
+            if let Some(location) = self.open_tables.get(name) {
+                return Err(TableError::TableAlreadyOpen(name.to_string(), location));
+            }
+        }
+        let definition = self
+            .table_tree
+            .get_table::<K, V>(name, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(name.to_string()))?;
+        let root = definition.get_root();
+        let length = definition.get_length();
+        if name != new_name {
+            if self.table_tree.contains_table(new_name)? {
+                return Err(TableError::TableAlreadyExists(new_name.to_string()));
+            }
+            return Ok((root, length, new_name.to_string()));
+        }
+        // The old table must stay intact until the migration succeeds, so the migrated table is
+        // built under an unused temporary name
+        let mut suffix = 0u64;
+        loop {
+            let temporary_name = format!("{name}.migration{suffix}");
+            if !self.table_tree.contains_table(&temporary_name)? {
+                return Ok((root, length, temporary_name));
+            }
+            suffix += 1;
+        }
+    }
+
+    // Replaces the table `name` with the table built by a migration, or deletes the partially
+    // built table if the migration failed, leaving `name` unchanged
+    #[track_caller]
+    fn finish_migration(
+        &mut self,
+        transaction: &WriteTransaction,
+        name: &str,
+        migrated_name: &str,
+        new_name: &str,
+        table_type: TableType,
+        result: Result<(), TableError>,
+    ) -> Result<(), TableError> {
+        transaction.dirty.store(true, Ordering::Release);
+        if let Err(err) = result {
+            self.inner_delete(migrated_name, table_type)?;
+            return Err(err);
+        }
+        self.inner_delete(name, table_type)?;
+        if migrated_name != new_name {
+            self.table_tree
+                .rename_table(migrated_name, new_name, table_type)?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn close_table<K: Key + 'static, V: Value + 'static>(
         &mut self,
         name: &str,
@@ -692,6 +898,26 @@ impl WriteTransaction {
         Ok(savepoints.into_iter())
     }
 
+    pub(crate) fn schema_version(&self) -> Result<Option<u64>> {
+        let mut system_tables = self.system_tables.lock().unwrap();
+        if !system_tables
+            .table_tree
+            .contains_table(SCHEMA_VERSION_TABLE.name())?
+        {
+            return Ok(None);
+        }
+        let table = system_tables.open_system_table(self, SCHEMA_VERSION_TABLE)?;
+        let version = table.get(())?.map(|x| x.value());
+        Ok(version)
+    }
+
+    pub(crate) fn set_schema_version(&self, version: u64) -> Result {
+        let mut system_tables = self.system_tables.lock().unwrap();
+        let mut table = system_tables.open_system_table(self, SCHEMA_VERSION_TABLE)?;
+        table.insert((), version)?;
+        Ok(())
+    }
+
     // TODO: deduplicate this with the one in Database
     fn allocate_read_transaction(&self) -> Result<TransactionGuard> {
         let id = self
@@ -767,8 +993,9 @@ impl WriteTransaction {
             self.transaction_id
         );
         // Restoring a savepoint that reverted a file format or checksum type change could corrupt
-        // the database
-        assert_eq!(self.mem.get_version(), savepoint.get_version());
+        // the database. Savepoints from before an upgrade from version 2 are safe to restore,
+        // because version 3 only adds page types
+        assert!(savepoint.get_version() <= self.mem.get_version());
         self.dirty.store(true, Ordering::Release);
 
         // Restoring a savepoint needs to accomplish the following:
@@ -778,6 +1005,7 @@ impl WriteTransaction {
         //    from the restored table tree root. Here we diff the reachable pages from the old
         //    and new roots
         // 3) update the system tree to remove invalid persistent savepoints.
+        // 4) recompute the reference counts of pages shared by cloned tables
 
         let old_table_tree = TableTreeMut::new(
             savepoint.get_user_root(),
@@ -858,6 +1086,11 @@ impl WriteTransaction {
 
             txn_id += 1;
         }
+        drop(freed_tree);
+        // Also the pages that were freed earlier in this transaction
+        self.freed_pages.lock().unwrap().retain(|p| {
+            !old_root_pages[p.region as usize].is_allocated(p.page_index, p.page_order)
+        });
 
         // 2) free all pages that became unreachable
         let mut freed_pages = self.freed_pages.lock().unwrap();
@@ -884,6 +1117,28 @@ impl WriteTransaction {
             }
         }
 
+        // 4) recompute the reference counts of pages shared by cloned tables, since different
+        // pages may be shared by the restored tables
+        let references = self
+            .tables
+            .lock()
+            .unwrap()
+            .table_tree
+            .shared_page_counts()?;
+        let mut system_tables = self.system_tables.lock().unwrap();
+        system_tables
+            .table_tree
+            .delete_table(SHARED_PAGES_TABLE.name(), TableType::Normal)
+            .map_err(|e| {
+                e.into_storage_error_or_corrupted("Internal error. System table is corrupted")
+            })?;
+        if !references.is_empty() {
+            let mut shared_pages = system_tables.open_system_table(self, SHARED_PAGES_TABLE)?;
+            for (page_number, count) in references {
+                shared_pages.insert(&page_number, &count)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -931,6 +1186,49 @@ impl WriteTransaction {
             .open_multimap_table(self, definition)
     }
 
+    /// Open the given blob table
+    ///
+    /// The table will be created if it does not exist
+    #[track_caller]
+    pub fn open_blob_table<'txn, K: Key + 'static>(
+        &'txn self,
+        definition: BlobTableDefinition<K>,
+    ) -> Result<BlobTable<'txn, K>, TableError> {
+        let table = self.open_table(TableDefinition::new(definition.name()))?;
+        Ok(BlobTable::new(table))
+    }
+
+    /// Open the given table without its types, to read and write its keys and values as raw bytes
+    ///
+    /// Keys are ordered using the table's stored key type. Returns
+    /// [`TableError::UnorderableKeyType`] if that is not one of the builtin key types, in which
+    /// case [`WriteTransaction::open_untyped_table_ordered_by`] can be used instead
+    #[track_caller]
+    pub fn open_untyped_table(
+        &self,
+        handle: impl TableHandle,
+    ) -> Result<UntypedTable<'_>, TableError> {
+        self.tables
+            .lock()
+            .unwrap()
+            .open_untyped_table(self, handle.name(), None)
+    }
+
+    /// Open the given table without its types, ordering its keys with `K`
+    ///
+    /// `K` must be the table's key type
+    #[track_caller]
+    pub fn open_untyped_table_ordered_by<K: Key + 'static>(
+        &self,
+        handle: impl TableHandle,
+    ) -> Result<UntypedTable<'_>, TableError> {
+        self.tables.lock().unwrap().open_untyped_table(
+            self,
+            handle.name(),
+            Some((K::type_name(), UntypedTable::ordered_by::<K>())),
+        )
+    }
+
     pub(crate) fn close_table<K: Key + 'static, V: Value + 'static>(
         &self,
         name: &str,
@@ -966,6 +1264,234 @@ impl WriteTransaction {
             .delete_multimap_table(self, &name)
     }
 
+    /// Rename the given table
+    ///
+    /// Only the table's name is changed, none of its data is copied. Returns an error if the table
+    /// does not exist, a table named `new_name` already exists, or either table is open
+    #[track_caller]
+    pub fn rename_table(
+        &self,
+        definition: impl TableHandle,
+        new_name: &str,
+    ) -> Result<(), TableError> {
+        let name = definition.name().to_string();
+        // Drop the definition so that callers can pass in a `Table` to rename, without getting a TableAlreadyOpen error
+        drop(definition);
+        self.tables
+            .lock()
+            .unwrap()
+            .rename_table(self, &name, new_name, TableType::Normal)
+    }
+
+    /// Rename the given multimap table
+    ///
+    /// Only the table's name is changed, none of its data is copied. Returns an error if the table
+    /// does not exist, a table named `new_name` already exists, or either table is open
+    #[track_caller]
+    pub fn rename_multimap_table(
+        &self,
+        definition: impl MultimapTableHandle,
+        new_name: &str,
+    ) -> Result<(), TableError> {
+        let name = definition.name().to_string();
+        // Drop the definition so that callers can pass in a `MultimapTable` to rename, without getting a TableAlreadyOpen error
+        drop(definition);
+        self.tables
+            .lock()
+            .unwrap()
+            .rename_table(self, &name, new_name, TableType::Multimap)
+    }
+
+    /// Exchange the contents of two tables
+    ///
+    /// Both tables must exist, must not be open, and must have the same key and value types.
+    /// No data is copied
+    #[track_caller]
+    pub fn swap_tables(&self, a: impl TableHandle, b: impl TableHandle) -> Result<(), TableError> {
+        let a_name = a.name().to_string();
+        let b_name = b.name().to_string();
+        // Drop the handles so that callers can pass in a `Table`, without getting a TableAlreadyOpen error
+        drop(a);
+        drop(b);
+        self.tables
+            .lock()
+            .unwrap()
+            .swap_tables(self, &a_name, &b_name)
+    }
+
+    /// Replace the contents of `target` with the contents of `source`, and remove `source`
+    ///
+    /// Both tables must exist, must not be open, and must have the same key and value types.
+    /// No data is copied, and the pages used by the previous contents of `target` are freed.
+    /// This can be used to build a new version of a table in a scratch table, and then publish it
+    #[track_caller]
+    pub fn replace_table(
+        &self,
+        target: impl TableHandle,
+        source: impl TableHandle,
+    ) -> Result<(), TableError> {
+        let target_name = target.name().to_string();
+        let source_name = source.name().to_string();
+        // Drop the handles so that callers can pass in a `Table`, without getting a TableAlreadyOpen error
+        drop(target);
+        drop(source);
+        self.tables
+            .lock()
+            .unwrap()
+            .replace_table(self, &target_name, &source_name)
+    }
+
+    /// Create `destination` as a copy of `source`
+    ///
+    /// This takes constant time, regardless of the size of `source`, because the two tables share
+    /// their pages until they are modified. `source` must exist, must not be open, and must not
+    /// have been modified in this transaction. `destination` must not exist
+    #[track_caller]
+    pub fn clone_table(
+        &self,
+        source: impl TableHandle,
+        destination: impl TableHandle,
+    ) -> Result<(), TableError> {
+        let source_name = source.name().to_string();
+        let destination_name = destination.name().to_string();
+        // Drop the handles so that callers can pass in a `Table`, without getting a TableAlreadyOpen error
+        drop(source);
+        drop(destination);
+        let root =
+            self.tables
+                .lock()
+                .unwrap()
+                .clone_table(self, &source_name, &destination_name)?;
+        if let Some(root) = root {
+            let mut system_tables = self.system_tables.lock().unwrap();
+            let mut shared_pages = system_tables.open_system_table(self, SHARED_PAGES_TABLE)?;
+            let references = shared_pages
+                .get(&root)?
+                .map(|x| x.value())
+                .unwrap_or_default();
+            shared_pages.insert(&root, &(references + 1))?;
+        }
+
+        Ok(())
+    }
+
+    /// Convert the contents of a table to new key and value types
+    ///
+    /// Each entry of `old` is passed to `convert`, and the result is inserted into `new`, which
+    /// replaces `old`. Entries are converted one at a time, so memory usage does not depend on the
+    /// size of the table. `new` may have the same name as `old`, otherwise a table with its name
+    /// must not exist. If several entries are converted to the same key, the last one is kept.
+    /// The converted keys and values may not borrow from the old ones
+    ///
+    /// `old` is only replaced once every entry has been converted, so if an error is returned it
+    /// is left unchanged, and `new` is not created
+    #[track_caller]
+    pub fn migrate_table<'k, 'v, K1, V1, K2, V2, F>(
+        &self,
+        old: TableDefinition<K1, V1>,
+        new: TableDefinition<K2, V2>,
+        mut convert: F,
+    ) -> Result<(), TableError>
+    where
+        K1: Key + 'static,
+        V1: Value + 'static,
+        K2: Key + 'static,
+        V2: Value + 'static,
+        F: FnMut(K1::SelfType<'_>, V1::SelfType<'_>) -> (K2::SelfType<'k>, V2::SelfType<'v>),
+    {
+        let (root, _, migrated_name) = self.tables.lock().unwrap().begin_migration::<K1, V1>(
+            old.name(),
+            new.name(),
+            TableType::Normal,
+        )?;
+        let old_table: ReadOnlyTable<K1, V1> = ReadOnlyTable::new(
+            old.name().to_string(),
+            root,
+            PageHint::None,
+            self.transaction_guard.clone(),
+            self.mem.clone(),
+        )?;
+        let result = (|| -> Result<(), TableError> {
+            let mut new_table = self.open_table(TableDefinition::<K2, V2>::new(&migrated_name))?;
+            for entry in old_table.iter()? {
+                let (key, value) = entry?;
+                let (new_key, new_value) = convert(key.value(), value.value());
+                new_table.insert(new_key, new_value)?;
+            }
+            Ok(())
+        })();
+        drop(old_table);
+
+        self.tables.lock().unwrap().finish_migration(
+            self,
+            old.name(),
+            &migrated_name,
+            new.name(),
+            TableType::Normal,
+            result,
+        )
+    }
+
+    /// Convert the contents of a multimap table to new key and value types
+    ///
+    /// Each key-value pair of `old` is passed to `convert`, and the result is inserted into `new`,
+    /// which replaces `old`. Pairs are converted one at a time, so memory usage does not depend on
+    /// the size of the table. `new` may have the same name as `old`, otherwise a table with its
+    /// name must not exist. The converted keys and values may not borrow from the old ones
+    ///
+    /// `old` is only replaced once every pair has been converted, so if an error is returned it
+    /// is left unchanged, and `new` is not created
+    #[track_caller]
+    pub fn migrate_multimap_table<'k, 'v, K1, V1, K2, V2, F>(
+        &self,
+        old: MultimapTableDefinition<K1, V1>,
+        new: MultimapTableDefinition<K2, V2>,
+        mut convert: F,
+    ) -> Result<(), TableError>
+    where
+        K1: Key + 'static,
+        V1: Key + 'static,
+        K2: Key + 'static,
+        V2: Key + 'static,
+        F: FnMut(K1::SelfType<'_>, V1::SelfType<'_>) -> (K2::SelfType<'k>, V2::SelfType<'v>),
+    {
+        let (root, length, migrated_name) = self.tables.lock().unwrap().begin_migration::<K1, V1>(
+            old.name(),
+            new.name(),
+            TableType::Multimap,
+        )?;
+        let old_table: ReadOnlyMultimapTable<K1, V1> = ReadOnlyMultimapTable::new(
+            root,
+            length,
+            PageHint::None,
+            self.transaction_guard.clone(),
+            self.mem.clone(),
+        )?;
+        let result = (|| -> Result<(), TableError> {
+            let mut new_table =
+                self.open_multimap_table(MultimapTableDefinition::<K2, V2>::new(&migrated_name))?;
+            for entry in old_table.iter()? {
+                let (key, values) = entry?;
+                for value in values {
+                    let value = value?;
+                    let (new_key, new_value) = convert(key.value(), value.value());
+                    new_table.insert(new_key, new_value)?;
+                }
+            }
+            Ok(())
+        })();
+        drop(old_table);
+
+        self.tables.lock().unwrap().finish_migration(
+            self,
+            old.name(),
+            &migrated_name,
+            new.name(),
+            TableType::Multimap,
+            result,
+        )
+    }
+
     /// List all the tables
     pub fn list_tables(&self) -> Result<impl Iterator<Item = UntypedTableHandle> + '_> {
         self.tables
@@ -988,6 +1514,38 @@ impl WriteTransaction {
             .map(|x| x.into_iter().map(UntypedMultimapTableHandle::new))
     }
 
+    /// Import tables from an export, written by [`ReadTransaction::export`]
+    ///
+    /// All tables in the export are created, and it is an error if any of them already exist.
+    /// The stored type names and widths of each table are preserved, so the tables must be opened
+    /// with the same type definitions as in the exported database.
+    ///
+    /// The entries of each table are checked to be in the order defined by its key type, if it is
+    /// a builtin type or wrapped in [`crate::Memcmp`], and likewise for the value type of a
+    /// multimap table. For other types, entries are only checked not to be duplicates of the one
+    /// before them. Use [`WriteTransaction::import_with`] to check their order too.
+    ///
+    /// If an error is returned, the transaction should be aborted, since some tables may have
+    /// been imported
+    pub fn import(&self, reader: impl Read) -> Result<(), ImportError> {
+        self.import_with(reader, &KeyOrderings::new())
+    }
+
+    /// Import tables from an export, checking the order of their entries with `orderings`
+    ///
+    /// See [`WriteTransaction::import`]
+    pub fn import_with(
+        &self,
+        reader: impl Read,
+        orderings: &KeyOrderings,
+    ) -> Result<(), ImportError> {
+        #[cfg(feature = "logging")]
+        debug!("Importing tables");
+        self.dirty.store(true, Ordering::Release);
+        let mut tables = self.tables.lock().unwrap();
+        import_tables(&mut tables.table_tree, &self.mem, reader, orderings)
+    }
+
     /// Commit the transaction
     ///
     /// All writes performed in this transaction will be visible to future transactions, and are
@@ -1062,6 +1620,8 @@ impl WriteTransaction {
     }
 
     pub(crate) fn durable_commit(&mut self, eventual: bool, two_phase: bool) -> Result {
+        self.release_shared_pages()?;
+
         let free_until_transaction = self
             .transaction_tracker
             .oldest_live_read_transaction()
@@ -1120,6 +1680,8 @@ impl WriteTransaction {
 
     // Commit without a durability guarantee
     pub(crate) fn non_durable_commit(&mut self) -> Result {
+        self.release_shared_pages()?;
+
         let user_root = self
             .tables
             .lock()
@@ -1160,6 +1722,7 @@ impl WriteTransaction {
         if self.mem.relocate_region_tracker()? {
             progress = true;
         }
+        let shared_pages = self.shared_page_references()?;
 
         // Find the 1M highest pages
         let mut highest_pages = BTreeMap::new();
@@ -1176,6 +1739,13 @@ impl WriteTransaction {
             if relocation_map.contains_key(&path.page_number()) {
                 continue;
             }
+            // Pages shared by cloned tables are not relocated, since every table that references
+            // them would need to be rewritten
+            if shared_pages.contains_key(&path.page_number())
+                || path.parents().iter().any(|x| shared_pages.contains_key(x))
+            {
+                continue;
+            }
             let old_page = self.mem.get_page(path.page_number())?;
             let mut new_page = self.mem.allocate_lowest(
                 old_page.memory().len(),
@@ -1221,6 +1791,91 @@ impl WriteTransaction {
         Ok(progress)
     }
 
+    fn shared_page_references(&self) -> Result<HashMap<PageNumber, u64>> {
+        let mut references = HashMap::new();
+        let mut system_tables = self.system_tables.lock().unwrap();
+        if system_tables
+            .table_tree
+            .contains_table(SHARED_PAGES_TABLE.name())?
+        {
+            let shared_pages = system_tables.open_system_table(self, SHARED_PAGES_TABLE)?;
+            for entry in shared_pages.range::<PageNumber>(..)? {
+                let (page, count) = entry?;
+                references.insert(page.value(), count.value());
+            }
+        }
+
+        Ok(references)
+    }
+
+    // Dropping a reference to a shared page does not free it. Instead its reference count is
+    // decremented, and each of its children gains a reference, since the page still references
+    // them and the tree which dropped it may reference them from a copy of the page.
+    // Children that were also dropped, for example by delete_table(), then lose that reference again
+    // NOTE: must be called before the system table tree is flushed during commit
+    fn release_shared_pages(&self) -> Result {
+        if self.freed_pages.lock().unwrap().is_empty() {
+            return Ok(());
+        }
+        let mut references = self.shared_page_references()?;
+        if references.is_empty() {
+            return Ok(());
+        }
+
+        let mut pending: Vec<PageNumber> = self.freed_pages.lock().unwrap().drain(..).collect();
+        let mut modified = HashSet::new();
+        loop {
+            let mut progress = false;
+            let mut unreferenced = vec![];
+            for page_number in pending {
+                if let Some(count) = references.get_mut(&page_number) {
+                    *count -= 1;
+                    if *count == 0 {
+                        references.remove(&page_number);
+                    }
+                    modified.insert(page_number);
+                    let page = self.mem.get_page(page_number)?;
+                    if page.memory()[0] == BRANCH {
+                        let accessor = BranchAccessor::new(&page, None);
+                        for i in 0..accessor.count_children() {
+                            let child = accessor.child_page(i).unwrap();
+                            *references.entry(child).or_default() += 1;
+                            modified.insert(child);
+                        }
+                    } else {
+                        for (_, reference) in leaf_overflow_refs(page.memory()) {
+                            *references.entry(reference.page).or_default() += 1;
+                            modified.insert(reference.page);
+                        }
+                    }
+                    progress = true;
+                } else {
+                    unreferenced.push(page_number);
+                }
+            }
+            pending = unreferenced;
+            // Wait until no more references can be added, before freeing anything
+            if !progress {
+                break;
+            }
+        }
+
+        let mut system_tables = self.system_tables.lock().unwrap();
+        let mut shared_pages = system_tables.open_system_table(self, SHARED_PAGES_TABLE)?;
+        for page_number in modified {
+            if let Some(count) = references.get(&page_number) {
+                shared_pages.insert(&page_number, count)?;
+            } else {
+                shared_pages.remove(&page_number)?;
+            }
+        }
+        drop(shared_pages);
+        drop(system_tables);
+        self.freed_pages.lock().unwrap().extend(pending);
+
+        Ok(())
+    }
+
     // NOTE: must be called before store_freed_pages() during commit, since this can create
     // more pages freed by the current transaction
     fn process_freed_pages(&mut self, free_until: TransactionId) -> Result {
@@ -1401,6 +2056,15 @@ impl ReadTransaction {
         }
     }
 
+    /// Open the given blob table
+    pub fn open_blob_table<K: Key + 'static>(
+        &self,
+        definition: BlobTableDefinition<K>,
+    ) -> Result<ReadOnlyBlobTable<K>, TableError> {
+        let table = self.open_table(TableDefinition::new(definition.name()))?;
+        Ok(ReadOnlyBlobTable::new(table))
+    }
+
     /// Open the given table without a type
     pub fn open_untyped_table(
         &self,
@@ -1495,6 +2159,44 @@ impl ReadTransaction {
             .map(|x| x.into_iter().map(UntypedMultimapTableHandle::new))
     }
 
+    /// Returns the stored definition of the given table
+    pub fn table_info(&self, handle: impl TableHandle) -> Result<TableInfo, TableError> {
+        self.inner_table_info(handle.name(), TableType::Normal)
+    }
+
+    /// Returns the stored definition of the given multimap table
+    pub fn multimap_table_info(
+        &self,
+        handle: impl MultimapTableHandle,
+    ) -> Result<TableInfo, TableError> {
+        self.inner_table_info(handle.name(), TableType::Multimap)
+    }
+
+    fn inner_table_info(&self, name: &str, table_type: TableType) -> Result<TableInfo, TableError> {
+        let definition = self
+            .tree
+            .get_table_untyped(name, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(name.to_string()))?;
+        Ok(TableInfo::new(name, &definition))
+    }
+
+    /// List the stored definitions of all tables, including multimap tables
+    pub fn list_table_info(&self) -> Result<impl Iterator<Item = TableInfo>> {
+        self.tree.list_table_definitions().map(|x| {
+            x.into_iter()
+                .map(|(name, definition)| TableInfo::new(&name, &definition))
+        })
+    }
+
+    /// Write the contents of all tables to `writer`
+    ///
+    /// The export is a portable stream containing every table's name, type information, and
+    /// entries. Each record in the stream is checksummed, so that corruption is detected by
+    /// [`WriteTransaction::import`]
+    pub fn export(&self, writer: impl Write) -> Result {
+        export_tables(&self.tree, &self.mem, writer)
+    }
+
     /// Close the transaction
     ///
     /// Transactions are automatically closed when they and all objects referencing them have been dropped,
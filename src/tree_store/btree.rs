@@ -1,16 +1,17 @@
 use crate::db::TransactionGuard;
 use crate::tree_store::btree_base::{
-    branch_checksum, leaf_checksum, BranchAccessor, BranchMutator, BtreeHeader, Checksum,
-    LeafAccessor, BRANCH, DEFERRED, LEAF,
+    branch_checksum, leaf_checksum, leaf_overflow_refs, BranchAccessor, BranchMutator, BtreeHeader,
+    Checksum, LeafAccessor, OverflowRef, BRANCH, DEFERRED, LEAF, LEAF_WITH_OVERFLOW, OVERFLOW,
 };
 use crate::tree_store::btree_iters::BtreeExtractIf;
 use crate::tree_store::btree_mutator::MutateHelper;
 use crate::tree_store::page_store::{Page, PageImpl, PageMut, TransactionalMemory};
 use crate::tree_store::{
     AccessGuardMut, AllPageNumbersBtreeIter, BtreeRangeIter, PageHint, PageNumber,
+    SortedBtreeBuilder,
 };
 use crate::types::{Key, MutInPlaceValue, Value};
-use crate::{AccessGuard, Result};
+use crate::{AccessGuard, Result, StorageError};
 #[cfg(feature = "logging")]
 use log::trace;
 use std::borrow::Borrow;
@@ -107,6 +108,11 @@ impl UntypedBtree {
             LEAF => {
                 // No-op
             }
+            LEAF_WITH_OVERFLOW => {
+                for (_, reference) in leaf_overflow_refs(page.memory()) {
+                    visitor(&path.with_child(reference.page))?;
+                }
+            }
             BRANCH => {
                 let accessor = BranchAccessor::new(&page, self.key_width);
                 for i in 0..accessor.count_children() {
@@ -177,7 +183,9 @@ impl UntypedBtreeMut {
         let mut page = self.mem.get_page_mut(page_number)?;
 
         match page.memory()[0] {
-            LEAF => leaf_checksum(&page, self.key_width, self.value_width),
+            LEAF | LEAF_WITH_OVERFLOW => {
+                leaf_checksum(&page, &self.mem, self.key_width, self.value_width)
+            }
             BRANCH => {
                 let accessor = BranchAccessor::new(&page, self.key_width);
                 let mut new_children = vec![];
@@ -200,7 +208,7 @@ impl UntypedBtreeMut {
                 }
                 drop(mutator);
 
-                branch_checksum(&page, self.key_width)
+                branch_checksum(&page, &self.mem, self.key_width)
             }
             _ => unreachable!(),
         }
@@ -290,9 +298,23 @@ impl UntypedBtreeMut {
 
         let node_mem = old_page.memory();
         match node_mem[0] {
-            LEAF => {
+            LEAF | OVERFLOW => {
                 // No-op
             }
+            LEAF_WITH_OVERFLOW => {
+                for (offset, reference) in leaf_overflow_refs(node_mem) {
+                    if let Some((new_overflow_page, _)) =
+                        self.relocate_helper(reference.page, relocation_map)?
+                    {
+                        let reference = OverflowRef {
+                            page: new_overflow_page,
+                            ..reference
+                        };
+                        new_page.memory_mut()[offset..(offset + OverflowRef::serialized_size())]
+                            .copy_from_slice(&reference.to_le_bytes());
+                    }
+                }
+            }
             BRANCH => {
                 let accessor = BranchAccessor::new(&old_page, self.key_width);
                 let mut mutator = BranchMutator::new(&mut new_page);
@@ -322,6 +344,7 @@ pub(crate) struct BtreeMut<'a, K: Key + 'static, V: Value + 'static> {
     transaction_guard: Arc<TransactionGuard>,
     root: Option<BtreeHeader>,
     freed_pages: Arc<Mutex<Vec<PageNumber>>>,
+    fixed_value_size: Option<usize>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
     _lifetime: PhantomData<&'a ()>,
@@ -333,12 +356,25 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
         guard: Arc<TransactionGuard>,
         mem: Arc<TransactionalMemory>,
         freed_pages: Arc<Mutex<Vec<PageNumber>>>,
+    ) -> Self {
+        Self::with_fixed_value_size(root, guard, mem, freed_pages, V::fixed_width())
+    }
+
+    // Accesses a tree whose values are stored with the given width, which need not match V.
+    // This allows a tree to be accessed as raw bytes, without knowing its value type
+    pub(crate) fn with_fixed_value_size(
+        root: Option<BtreeHeader>,
+        guard: Arc<TransactionGuard>,
+        mem: Arc<TransactionalMemory>,
+        freed_pages: Arc<Mutex<Vec<PageNumber>>>,
+        fixed_value_size: Option<usize>,
     ) -> Self {
         Self {
             mem,
             transaction_guard: guard,
             root,
             freed_pages,
+            fixed_value_size,
             _key_type: Default::default(),
             _value_type: Default::default(),
             _lifetime: Default::default(),
@@ -349,7 +385,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
         RawBtree::new(
             self.get_root(),
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
             self.mem.clone(),
         )
         .verify_checksum()
@@ -361,7 +397,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             self.mem.clone(),
             self.freed_pages.clone(),
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         );
         tree.finalize_dirty_checksums()?;
         self.root = tree.get_root();
@@ -374,7 +410,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             Ok(Some(AllPageNumbersBtreeIter::new(
                 root,
                 K::fixed_width(),
-                V::fixed_width(),
+                self.fixed_value_size,
                 self.mem.clone(),
             )?))
         } else {
@@ -390,7 +426,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             self.root,
             self.mem.clone(),
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         );
         tree.visit_all_pages(visitor)
     }
@@ -408,7 +444,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             self.mem.clone(),
             self.freed_pages.clone(),
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         );
         if tree.relocate(relocation_map)? {
             self.root = tree.get_root();
@@ -431,22 +467,74 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             V::as_bytes(value).as_ref().len()
         );
         let mut freed_pages = self.freed_pages.lock().unwrap();
-        let mut operation: MutateHelper<'_, '_, K, V> =
-            MutateHelper::new(&mut self.root, self.mem.clone(), freed_pages.as_mut());
+        let mut operation: MutateHelper<'_, '_, K, V> = MutateHelper::new(
+            &mut self.root,
+            self.mem.clone(),
+            freed_pages.as_mut(),
+            self.fixed_value_size,
+        );
         let (old_value, _) = operation.insert(key, value)?;
         Ok(old_value)
     }
 
+    // Inserts the given key & value, and returns the inserted value. The guard isn't tied to the
+    // borrow of the tree, so the caller must drop it before the tree is accessed again
+    pub(crate) fn insert_and_get(
+        &mut self,
+        key: &K::SelfType<'_>,
+        value: &V::SelfType<'_>,
+    ) -> Result<AccessGuard<'static, V>> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Inserting {:?} with value of length {}",
+            &self.root,
+            key,
+            V::as_bytes(value).as_ref().len()
+        );
+        let mut freed_pages = self.freed_pages.lock().unwrap();
+        let mut operation: MutateHelper<'static, '_, K, V> = MutateHelper::new(
+            &mut self.root,
+            self.mem.clone(),
+            freed_pages.as_mut(),
+            self.fixed_value_size,
+        );
+        let (_, inserted_value) = operation.insert(key, value)?;
+        Ok(inserted_value.into_guard())
+    }
+
     pub(crate) fn remove(&mut self, key: &K::SelfType<'_>) -> Result<Option<AccessGuard<V>>> {
         #[cfg(feature = "logging")]
         trace!("Btree(root={:?}): Deleting {:?}", &self.root, key);
         let mut freed_pages = self.freed_pages.lock().unwrap();
-        let mut operation: MutateHelper<'_, '_, K, V> =
-            MutateHelper::new(&mut self.root, self.mem.clone(), freed_pages.as_mut());
+        let mut operation: MutateHelper<'_, '_, K, V> = MutateHelper::new(
+            &mut self.root,
+            self.mem.clone(),
+            freed_pages.as_mut(),
+            self.fixed_value_size,
+        );
         let result = operation.delete(key)?;
         Ok(result)
     }
 
+    // Replaces the value of the given key with the result of `f`, in a single descent of the tree.
+    // See MutateHelper::update()
+    pub(crate) fn update(
+        &mut self,
+        key: &K::SelfType<'_>,
+        f: impl FnOnce(Option<&[u8]>) -> Result<Option<Vec<u8>>>,
+    ) -> Result<Option<AccessGuard<'_, V>>> {
+        #[cfg(feature = "logging")]
+        trace!("Btree(root={:?}): Updating {:?}", &self.root, key);
+        let mut freed_pages = self.freed_pages.lock().unwrap();
+        let mut operation: MutateHelper<'_, '_, K, V> = MutateHelper::new(
+            &mut self.root,
+            self.mem.clone(),
+            freed_pages.as_mut(),
+            self.fixed_value_size,
+        );
+        operation.update(key, f)
+    }
+
     #[allow(dead_code)]
     pub(crate) fn print_debug(&self, include_values: bool) -> Result {
         self.read_tree()?.print_debug(include_values)
@@ -457,16 +545,17 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
             self.get_root().map(|x| x.root),
             &self.mem,
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         )
     }
 
     fn read_tree(&self) -> Result<Btree<K, V>> {
-        Btree::new(
+        Btree::with_fixed_value_size(
             self.get_root(),
             PageHint::None,
             self.transaction_guard.clone(),
             self.mem.clone(),
+            self.fixed_value_size,
         )
     }
 
@@ -474,6 +563,19 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
         self.read_tree()?.get(key)
     }
 
+    // Like get(), but the guard isn't tied to the borrow of the tree, so the caller must drop it
+    // before the tree is modified
+    pub(crate) fn get_detached(
+        &self,
+        key: &K::SelfType<'_>,
+    ) -> Result<Option<AccessGuard<'static, V>>> {
+        self.read_tree()?.get(key)
+    }
+
+    pub(crate) fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<AccessGuard<'_, V>>>> {
+        self.read_tree()?.get_many(keys)
+    }
+
     pub(crate) fn range<'a0, T: RangeBounds<KR> + 'a0, KR: Borrow<K::SelfType<'a0>> + 'a0>(
         &self,
         range: &'_ T,
@@ -521,8 +623,12 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
     {
         let iter = self.range(&range)?;
         let mut freed = vec![];
-        let mut operation: MutateHelper<'_, '_, K, V> =
-            MutateHelper::new_do_not_modify(&mut self.root, self.mem.clone(), &mut freed);
+        let mut operation: MutateHelper<'_, '_, K, V> = MutateHelper::new_do_not_modify(
+            &mut self.root,
+            self.mem.clone(),
+            &mut freed,
+            self.fixed_value_size,
+        );
         for entry in iter {
             let entry = entry?;
             if !predicate(entry.key(), entry.value()) {
@@ -537,6 +643,36 @@ impl<K: Key + 'static, V: Value + 'static> BtreeMut<'_, K, V> {
     pub(crate) fn len(&self) -> Result<u64> {
         self.read_tree()?.len()
     }
+
+    // Builds the tree, which must be empty, bottom-up from the entries pushed by `push`. If `push`
+    // fails, the pages of the partially built tree are freed and the tree is left empty
+    pub(crate) fn build_sorted<E: From<StorageError>>(
+        &mut self,
+        push: impl FnOnce(&mut SortedBtreeBuilder) -> std::result::Result<(), E>,
+    ) -> std::result::Result<(), E> {
+        assert!(self.root.is_none());
+        let mut builder =
+            SortedBtreeBuilder::new(&self.mem, K::fixed_width(), self.fixed_value_size);
+        let result = push(&mut builder);
+        let root = builder.finish()?;
+        if result.is_ok() {
+            self.root = root;
+        } else {
+            let mut freed_pages = self.freed_pages.lock().unwrap();
+            let tree = UntypedBtree::new(
+                root,
+                self.mem.clone(),
+                K::fixed_width(),
+                self.fixed_value_size,
+            );
+            tree.visit_all_pages(|path| {
+                freed_pages.push(path.page_number());
+                Ok(())
+            })?;
+        }
+
+        result
+    }
 }
 
 impl<'a, K: Key + 'a, V: MutInPlaceValue + 'a> BtreeMut<'a, K, V> {
@@ -558,9 +694,13 @@ impl<'a, K: Key + 'a, V: MutInPlaceValue + 'a> BtreeMut<'a, K, V> {
         let mut freed_pages = self.freed_pages.lock().unwrap();
         let mut value = vec![0u8; value_length as usize];
         V::initialize(&mut value);
-        let mut operation =
-            MutateHelper::<K, V>::new(&mut self.root, self.mem.clone(), freed_pages.as_mut());
-        let (_, guard) = operation.insert(key, &V::from_bytes(&value))?;
+        let mut operation = MutateHelper::<K, V>::new(
+            &mut self.root,
+            self.mem.clone(),
+            freed_pages.as_mut(),
+            self.fixed_value_size,
+        );
+        let (_, guard) = operation.insert_inline(key, &V::from_bytes(&value))?;
         Ok(guard)
     }
 }
@@ -620,17 +760,25 @@ impl RawBtree {
         let page = self.mem.get_page(page_number)?;
         let node_mem = page.memory();
         Ok(match node_mem[0] {
-            LEAF => {
+            LEAF | LEAF_WITH_OVERFLOW => {
                 if let Ok(computed) =
-                    leaf_checksum(&page, self.fixed_key_size, self.fixed_value_size)
+                    leaf_checksum(&page, &self.mem, self.fixed_key_size, self.fixed_value_size)
                 {
-                    expected_checksum == computed
+                    if expected_checksum != computed {
+                        return Ok(false);
+                    }
                 } else {
-                    false
+                    return Ok(false);
+                }
+                for (_, reference) in leaf_overflow_refs(node_mem) {
+                    if !reference.verify(&self.mem.get_page(reference.page)?, &self.mem) {
+                        return Ok(false);
+                    }
                 }
+                true
             }
             BRANCH => {
-                if let Ok(computed) = branch_checksum(&page, self.fixed_key_size) {
+                if let Ok(computed) = branch_checksum(&page, &self.mem, self.fixed_key_size) {
                     if expected_checksum != computed {
                         return Ok(false);
                     }
@@ -651,6 +799,48 @@ impl RawBtree {
             _ => false,
         })
     }
+
+    // Applies visitor to every key & value in the tree, in order
+    pub(crate) fn visit_entries<F>(&self, mut visitor: F) -> Result
+    where
+        F: FnMut(&[u8], &[u8]) -> Result,
+    {
+        if let Some(header) = self.root {
+            self.visit_entries_helper(header.root, &mut visitor)?;
+        }
+        Ok(())
+    }
+
+    fn visit_entries_helper<F>(&self, page_number: PageNumber, visitor: &mut F) -> Result
+    where
+        F: FnMut(&[u8], &[u8]) -> Result,
+    {
+        let page = self.mem.get_page(page_number)?;
+        match page.memory()[0] {
+            LEAF | LEAF_WITH_OVERFLOW => {
+                let accessor =
+                    LeafAccessor::new(page.memory(), self.fixed_key_size, self.fixed_value_size);
+                for i in 0..accessor.num_pairs() {
+                    let entry = accessor.entry(i).unwrap();
+                    if entry.is_overflow() {
+                        let reference = OverflowRef::from_le_bytes(entry.value());
+                        let value_page = self.mem.get_page(reference.page)?;
+                        visitor(entry.key(), &value_page.memory()[reference.value_range()])?;
+                    } else {
+                        visitor(entry.key(), entry.value())?;
+                    }
+                }
+            }
+            BRANCH => {
+                let accessor = BranchAccessor::new(&page, self.fixed_key_size);
+                for i in 0..accessor.count_children() {
+                    self.visit_entries_helper(accessor.child_page(i).unwrap(), visitor)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
 }
 
 pub(crate) struct Btree<K: Key + 'static, V: Value + 'static> {
@@ -660,6 +850,7 @@ pub(crate) struct Btree<K: Key + 'static, V: Value + 'static> {
     cached_root: Option<PageImpl>,
     root: Option<BtreeHeader>,
     hint: PageHint,
+    fixed_value_size: Option<usize>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -670,6 +861,17 @@ impl<K: Key, V: Value> Btree<K, V> {
         hint: PageHint,
         guard: Arc<TransactionGuard>,
         mem: Arc<TransactionalMemory>,
+    ) -> Result<Self> {
+        Self::with_fixed_value_size(root, hint, guard, mem, V::fixed_width())
+    }
+
+    // Accesses a tree whose values are stored with the given width, which need not match V
+    pub(crate) fn with_fixed_value_size(
+        root: Option<BtreeHeader>,
+        hint: PageHint,
+        guard: Arc<TransactionGuard>,
+        mem: Arc<TransactionalMemory>,
+        fixed_value_size: Option<usize>,
     ) -> Result<Self> {
         let cached_root = if let Some(header) = root {
             Some(mem.get_page_extended(header.root, hint)?)
@@ -682,6 +884,7 @@ impl<K: Key, V: Value> Btree<K, V> {
             cached_root,
             root,
             hint,
+            fixed_value_size,
             _key_type: Default::default(),
             _value_type: Default::default(),
         })
@@ -707,10 +910,19 @@ impl<K: Key, V: Value> Btree<K, V> {
     fn get_helper(&self, page: PageImpl, query: &[u8]) -> Result<Option<AccessGuard<'static, V>>> {
         let node_mem = page.memory();
         match node_mem[0] {
-            LEAF => {
-                let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+            LEAF | LEAF_WITH_OVERFLOW => {
+                let accessor =
+                    LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
                 if let Some(entry_index) = accessor.find_key::<K>(query) {
                     let (start, end) = accessor.value_range(entry_index).unwrap();
+                    if accessor.is_overflow(entry_index) {
+                        let reference = OverflowRef::from_le_bytes(&page.memory()[start..end]);
+                        let value_page = self.mem.get_page_extended(reference.page, self.hint)?;
+                        return Ok(Some(AccessGuard::with_page(
+                            value_page,
+                            reference.value_range(),
+                        )));
+                    }
                     let guard = AccessGuard::with_page(page, start..end);
                     Ok(Some(guard))
                 } else {
@@ -726,11 +938,90 @@ impl<K: Key, V: Value> Btree<K, V> {
         }
     }
 
+    // Returns the values for the queried keys, in the same order as the keys
+    pub(crate) fn get_many(
+        &self,
+        keys: &[Vec<u8>],
+    ) -> Result<Vec<Option<AccessGuard<'static, V>>>> {
+        let mut results: Vec<Option<AccessGuard<'static, V>>> =
+            (0..keys.len()).map(|_| None).collect();
+        if let Some(ref root_page) = self.cached_root {
+            // Look the keys up in sorted order, so that each page is only visited once
+            let mut queries: Vec<(usize, &[u8])> = keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| (i, key.as_slice()))
+                .collect();
+            queries.sort_by(|(_, a), (_, b)| K::compare(a, b));
+            self.get_many_helper(root_page.clone(), &queries, &mut results)?;
+        }
+
+        Ok(results)
+    }
+
+    // Stores the value for each of the queried keys, which must be sorted, in results at the
+    // key's index
+    fn get_many_helper(
+        &self,
+        page: PageImpl,
+        queries: &[(usize, &[u8])],
+        results: &mut [Option<AccessGuard<'static, V>>],
+    ) -> Result {
+        let node_mem = page.memory();
+        match node_mem[0] {
+            LEAF | LEAF_WITH_OVERFLOW => {
+                let accessor =
+                    LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
+                for (i, query) in queries {
+                    if let Some(entry_index) = accessor.find_key::<K>(query) {
+                        let (start, end) = accessor.value_range(entry_index).unwrap();
+                        results[*i] = if accessor.is_overflow(entry_index) {
+                            let reference = OverflowRef::from_le_bytes(&page.memory()[start..end]);
+                            let value_page =
+                                self.mem.get_page_extended(reference.page, self.hint)?;
+                            Some(AccessGuard::with_page(value_page, reference.value_range()))
+                        } else {
+                            Some(AccessGuard::with_page(page.clone(), start..end))
+                        };
+                    }
+                }
+            }
+            BRANCH => {
+                let accessor = BranchAccessor::new(&page, K::fixed_width());
+                // Queries are sorted, so those which belong to the same child are adjacent
+                let mut start = 0;
+                while start < queries.len() {
+                    let (child_index, child_page) = accessor.child_for_key::<K>(queries[start].1);
+                    let mut end = start + 1;
+                    while end < queries.len()
+                        && accessor.child_for_key::<K>(queries[end].1).0 == child_index
+                    {
+                        end += 1;
+                    }
+                    self.get_many_helper(
+                        self.mem.get_page_extended(child_page, self.hint)?,
+                        &queries[start..end],
+                        results,
+                    )?;
+                    start = end;
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn range<'a0, T: RangeBounds<KR>, KR: Borrow<K::SelfType<'a0>>>(
         &self,
         range: &'_ T,
     ) -> Result<BtreeRangeIter<K, V>> {
-        BtreeRangeIter::new(range, self.root.map(|x| x.root), self.mem.clone())
+        BtreeRangeIter::with_fixed_value_size(
+            range,
+            self.root.map(|x| x.root),
+            self.fixed_value_size,
+            self.mem.clone(),
+        )
     }
 
     pub(crate) fn len(&self) -> Result<u64> {
@@ -742,7 +1033,7 @@ impl<K: Key, V: Value> Btree<K, V> {
             self.root.map(|x| x.root),
             &self.mem,
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         )
     }
 
@@ -755,10 +1046,14 @@ impl<K: Key, V: Value> Btree<K, V> {
                 for page in pages.drain(..) {
                     let node_mem = page.memory();
                     match node_mem[0] {
-                        LEAF => {
+                        LEAF | LEAF_WITH_OVERFLOW => {
                             eprint!("Leaf[ (page={:?})", page.get_page_number());
-                            LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width())
-                                .print_node::<K, V>(include_values);
+                            LeafAccessor::new(
+                                page.memory(),
+                                K::fixed_width(),
+                                self.fixed_value_size,
+                            )
+                            .print_node::<K, V>(include_values);
                             eprint!("]");
                         }
                         BRANCH => {
@@ -812,18 +1107,28 @@ fn stats_helper(
     let page = mem.get_page(page_number)?;
     let node_mem = page.memory();
     match node_mem[0] {
-        LEAF => {
+        LEAF | LEAF_WITH_OVERFLOW => {
             let accessor = LeafAccessor::new(page.memory(), fixed_key_size, fixed_value_size);
-            let leaf_bytes = accessor.length_of_pairs(0, accessor.num_pairs());
-            let overhead_bytes = accessor.total_length() - leaf_bytes;
-            let fragmented_bytes = (page.memory().len() - accessor.total_length()) as u64;
+            let mut leaf_bytes = accessor.length_of_pairs(0, accessor.num_pairs());
+            let mut overhead_bytes = accessor.total_length() - leaf_bytes;
+            let mut fragmented_bytes = page.memory().len() - accessor.total_length();
+            let mut leaf_pages = 1;
+            // Overflow pages are counted as leaf pages, and their values as stored bytes
+            for (_, reference) in leaf_overflow_refs(node_mem) {
+                let overflow_page_len = mem.get_page(reference.page)?.memory().len();
+                leaf_bytes += reference.length;
+                leaf_bytes -= OverflowRef::serialized_size();
+                overhead_bytes += OverflowRef::serialized_size() + reference.value_range().start;
+                fragmented_bytes += overflow_page_len - reference.value_range().end;
+                leaf_pages += 1;
+            }
             Ok(BtreeStats {
                 tree_height: 1,
-                leaf_pages: 1,
+                leaf_pages,
                 branch_pages: 0,
                 stored_leaf_bytes: leaf_bytes.try_into().unwrap(),
                 metadata_bytes: overhead_bytes.try_into().unwrap(),
-                fragmented_bytes,
+                fragmented_bytes: fragmented_bytes.try_into().unwrap(),
             })
         }
         BRANCH => {
@@ -1,6 +1,4 @@
-use crate::tree_store::page_store::{
-    xxh3_checksum, CachePriority, Page, PageImpl, PageMut, TransactionalMemory,
-};
+use crate::tree_store::page_store::{CachePriority, Page, PageImpl, PageMut, TransactionalMemory};
 use crate::tree_store::PageNumber;
 use crate::types::{Key, MutInPlaceValue, Value};
 use crate::{Result, StorageError};
@@ -13,6 +11,15 @@ use std::thread;
 
 pub(crate) const LEAF: u8 = 1;
 pub(crate) const BRANCH: u8 = 2;
+// A page containing a single value, which was too large to store in a leaf
+pub(crate) const OVERFLOW: u8 = 3;
+// A leaf which stores some of its values in overflow pages
+pub(crate) const LEAF_WITH_OVERFLOW: u8 = 4;
+
+// Set in the reserved byte of a leaf with overflow values, if its keys are variable width
+const VARIABLE_WIDTH_KEYS: u8 = 1;
+// Length of the header of an overflow page, which precedes the value
+const OVERFLOW_HEADER_LEN: usize = 4;
 
 pub(crate) type Checksum = u128;
 // Dummy value. Final value will be computed during commit
@@ -20,6 +27,7 @@ pub(crate) const DEFERRED: Checksum = 999;
 
 pub(super) fn leaf_checksum<T: Page>(
     page: &T,
+    mem: &TransactionalMemory,
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
 ) -> Result<Checksum, StorageError> {
@@ -33,12 +41,13 @@ pub(super) fn leaf_checksum<T: Page>(
             page.memory().len()
         )))
     } else {
-        Ok(xxh3_checksum(&page.memory()[..end]))
+        Ok(mem.checksum(&page.memory()[..end]))
     }
 }
 
 pub(super) fn branch_checksum<T: Page>(
     page: &T,
+    mem: &TransactionalMemory,
     fixed_key_size: Option<usize>,
 ) -> Result<Checksum, StorageError> {
     let accessor = BranchAccessor::new(page, fixed_key_size);
@@ -51,10 +60,145 @@ pub(super) fn branch_checksum<T: Page>(
             page.memory().len()
         )))
     } else {
-        Ok(xxh3_checksum(&page.memory()[..end]))
+        Ok(mem.checksum(&page.memory()[..end]))
     }
 }
 
+// Returns true if a value of this length should be stored in an overflow page, rather than in a leaf.
+// Only variable width values are stored out of line
+pub(super) fn should_overflow(
+    mem: &TransactionalMemory,
+    fixed_value_size: Option<usize>,
+    value_len: usize,
+) -> bool {
+    fixed_value_size.is_none() && value_len > mem.get_page_size() / 2
+}
+
+// Writes `value` to a new overflow page
+// Layout is:
+// 1 byte: type
+// 3 bytes: reserved (padding to 32bits aligned)
+// n bytes: value data
+pub(super) fn write_overflow_page(
+    mem: &TransactionalMemory,
+    value: &[u8],
+) -> Result<(PageMut, OverflowRef)> {
+    let mut page = mem.allocate(OVERFLOW_HEADER_LEN + value.len(), CachePriority::Low)?;
+    let memory = page.memory_mut();
+    memory[0] = OVERFLOW;
+    memory[OVERFLOW_HEADER_LEN..(OVERFLOW_HEADER_LEN + value.len())].copy_from_slice(value);
+    let reference = OverflowRef {
+        page: page.get_page_number(),
+        length: value.len(),
+        checksum: mem.checksum(value),
+    };
+
+    Ok((page, reference))
+}
+
+// A reference from a leaf to a value stored in an overflow page
+// Layout is:
+// 8 bytes: page number
+// 8 bytes: value length
+// 16 bytes: checksum of the value
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct OverflowRef {
+    pub(crate) page: PageNumber,
+    pub(crate) length: usize,
+    pub(crate) checksum: Checksum,
+}
+
+impl OverflowRef {
+    pub(crate) const fn serialized_size() -> usize {
+        PageNumber::serialized_size() + size_of::<u64>() + size_of::<Checksum>()
+    }
+
+    pub(crate) fn from_le_bytes(bytes: &[u8]) -> Self {
+        let page =
+            PageNumber::from_le_bytes(bytes[..PageNumber::serialized_size()].try_into().unwrap());
+        let mut offset = PageNumber::serialized_size();
+        let length = u64::from_le_bytes(
+            bytes[offset..(offset + size_of::<u64>())]
+                .try_into()
+                .unwrap(),
+        );
+        offset += size_of::<u64>();
+        let checksum = Checksum::from_le_bytes(
+            bytes[offset..(offset + size_of::<Checksum>())]
+                .try_into()
+                .unwrap(),
+        );
+
+        Self {
+            page,
+            length: length.try_into().unwrap(),
+            checksum,
+        }
+    }
+
+    pub(crate) fn to_le_bytes(self) -> [u8; Self::serialized_size()] {
+        let mut result = [0; Self::serialized_size()];
+        let mut offset = PageNumber::serialized_size();
+        result[..offset].copy_from_slice(&self.page.to_le_bytes());
+        result[offset..(offset + size_of::<u64>())]
+            .copy_from_slice(&u64::try_from(self.length).unwrap().to_le_bytes());
+        offset += size_of::<u64>();
+        result[offset..].copy_from_slice(&self.checksum.to_le_bytes());
+
+        result
+    }
+
+    // Range of the value within its overflow page
+    pub(crate) fn value_range(&self) -> Range<usize> {
+        OVERFLOW_HEADER_LEN..(OVERFLOW_HEADER_LEN + self.length)
+    }
+
+    // Returns true if the overflow page contains the value this references
+    pub(crate) fn verify<T: Page>(&self, page: &T, mem: &TransactionalMemory) -> bool {
+        let memory = page.memory();
+        memory[0] == OVERFLOW
+            && self.value_range().end <= memory.len()
+            && mem.checksum(&memory[self.value_range()]) == self.checksum
+    }
+}
+
+// Returns the references to overflow pages in the given leaf, along with their offsets in the leaf.
+// Unlike LeafAccessor, this doesn't require the key and value widths
+pub(crate) fn leaf_overflow_refs(page: &[u8]) -> Vec<(usize, OverflowRef)> {
+    if page[0] != LEAF_WITH_OVERFLOW {
+        return vec![];
+    }
+    let num_pairs = usize::from(u16::from_le_bytes(page[2..4].try_into().unwrap()));
+    // Values in leaves with overflow values are always variable width
+    let mut value_ends = 4 + overflow_bitmap_len(num_pairs);
+    if page[1] & VARIABLE_WIDTH_KEYS != 0 {
+        value_ends += size_of::<u32>() * num_pairs;
+    }
+    (0..num_pairs)
+        .filter(|&i| overflow_bit(page, i))
+        .map(|i| {
+            let offset = value_ends + size_of::<u32>() * i;
+            let end = usize::try_from(u32::from_le_bytes(
+                page[offset..(offset + size_of::<u32>())]
+                    .try_into()
+                    .unwrap(),
+            ))
+            .unwrap();
+            let start = end - OverflowRef::serialized_size();
+            (start, OverflowRef::from_le_bytes(&page[start..end]))
+        })
+        .collect()
+}
+
+// Length of the bitmap of overflow values, which follows the header of a leaf with overflow values
+fn overflow_bitmap_len(num_pairs: usize) -> usize {
+    (num_pairs + 31) / 32 * size_of::<u32>()
+}
+
+fn overflow_bit(page: &[u8], n: usize) -> bool {
+    page[4 + n / 8] & (1 << (n % 8)) != 0
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub(crate) struct BtreeHeader {
     pub(crate) root: PageNumber,
@@ -116,6 +260,7 @@ enum OnDrop {
     RemoveEntry {
         position: usize,
         fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
     },
 }
 
@@ -188,6 +333,7 @@ impl<'a, V: Value + 'static> AccessGuard<'a, V> {
         len: usize,
         position: usize,
         fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
     ) -> Self {
         Self {
             page: EitherPage::Mutable(page),
@@ -196,6 +342,7 @@ impl<'a, V: Value + 'static> AccessGuard<'a, V> {
             on_drop: OnDrop::RemoveEntry {
                 position,
                 fixed_key_size,
+                fixed_value_size,
             },
             _value_type: Default::default(),
             _lifetime: Default::default(),
@@ -214,9 +361,10 @@ impl<'a, V: Value + 'static> Drop for AccessGuard<'a, V> {
             OnDrop::RemoveEntry {
                 position,
                 fixed_key_size,
+                fixed_value_size,
             } => {
                 if let EitherPage::Mutable(ref mut mut_page) = self.page {
-                    let mut mutator = LeafMutator::new(mut_page, fixed_key_size, V::fixed_width());
+                    let mut mutator = LeafMutator::new(mut_page, fixed_key_size, fixed_value_size);
                     mutator.remove(position);
                 } else if !thread::panicking() {
                     unreachable!();
@@ -245,6 +393,18 @@ impl<'a, V: Value + 'static> AccessGuardMut<'a, V> {
             _lifetime: Default::default(),
         }
     }
+
+    // Converts into a guard which only reads the value
+    pub(crate) fn into_guard(self) -> AccessGuard<'a, V> {
+        AccessGuard {
+            page: EitherPage::Mutable(self.page),
+            offset: self.offset,
+            len: self.len,
+            on_drop: OnDrop::None,
+            _value_type: Default::default(),
+            _lifetime: Default::default(),
+        }
+    }
 }
 
 impl<'a, V: MutInPlaceValue + 'static> AsMut<V::BaseRefType> for AccessGuardMut<'a, V> {
@@ -257,11 +417,16 @@ impl<'a, V: MutInPlaceValue + 'static> AsMut<V::BaseRefType> for AccessGuardMut<
 pub struct EntryAccessor<'a> {
     key: &'a [u8],
     value: &'a [u8],
+    overflow: bool,
 }
 
 impl<'a> EntryAccessor<'a> {
-    fn new(key: &'a [u8], value: &'a [u8]) -> Self {
-        EntryAccessor { key, value }
+    fn new(key: &'a [u8], value: &'a [u8], overflow: bool) -> Self {
+        EntryAccessor {
+            key,
+            value,
+            overflow,
+        }
     }
 }
 
@@ -270,9 +435,14 @@ impl<'a: 'b, 'b> EntryAccessor<'a> {
         self.key
     }
 
+    // Note: for an overflow value, this is the OverflowRef
     pub(crate) fn value(&'b self) -> &'a [u8] {
         self.value
     }
+
+    pub(crate) fn is_overflow(&'b self) -> bool {
+        self.overflow
+    }
 }
 
 // Provides a simple zero-copy way to access a leaf page
@@ -281,6 +451,7 @@ pub(crate) struct LeafAccessor<'a> {
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
     num_pairs: usize,
+    header_len: usize,
 }
 
 impl<'a> LeafAccessor<'a> {
@@ -289,13 +460,19 @@ impl<'a> LeafAccessor<'a> {
         fixed_key_size: Option<usize>,
         fixed_value_size: Option<usize>,
     ) -> Self {
-        debug_assert_eq!(page[0], LEAF);
+        debug_assert!(page[0] == LEAF || page[0] == LEAF_WITH_OVERFLOW);
         let num_pairs = u16::from_le_bytes(page[2..4].try_into().unwrap()) as usize;
+        let header_len = if page[0] == LEAF_WITH_OVERFLOW {
+            4 + overflow_bitmap_len(num_pairs)
+        } else {
+            4
+        };
         LeafAccessor {
             page,
             fixed_key_size,
             fixed_value_size,
             num_pairs,
+            header_len,
         }
     }
 
@@ -303,7 +480,10 @@ impl<'a> LeafAccessor<'a> {
         let mut i = 0;
         while let Some(entry) = self.entry(i) {
             eprint!(" key_{}={:?}", i, K::from_bytes(entry.key()));
-            if include_value {
+            if include_value && entry.is_overflow() {
+                let reference = OverflowRef::from_le_bytes(entry.value());
+                eprint!(" value_{}=(overflow page={:?})", i, reference.page);
+            } else if include_value {
                 eprint!(" value_{}={:?}", i, V::from_bytes(entry.value()));
             }
             i += 1;
@@ -344,7 +524,7 @@ impl<'a> LeafAccessor<'a> {
     }
 
     fn key_section_start(&self) -> usize {
-        let mut offset = 4;
+        let mut offset = self.header_len;
         if self.fixed_key_size.is_none() {
             offset += size_of::<u32>() * self.num_pairs;
         }
@@ -370,7 +550,7 @@ impl<'a> LeafAccessor<'a> {
             if let Some(fixed) = self.fixed_key_size {
                 return Some(self.key_section_start() + fixed * (n + 1));
             }
-            let offset = 4 + size_of::<u32>() * n;
+            let offset = self.header_len + size_of::<u32>() * n;
             let end = u32::from_le_bytes(
                 self.page[offset..(offset + size_of::<u32>())]
                     .try_into()
@@ -395,7 +575,7 @@ impl<'a> LeafAccessor<'a> {
             if let Some(fixed) = self.fixed_value_size {
                 return Some(self.key_end(self.num_pairs - 1).unwrap() + fixed * (n + 1));
             }
-            let mut offset = 4 + size_of::<u32>() * n;
+            let mut offset = self.header_len + size_of::<u32>() * n;
             if self.fixed_key_size.is_none() {
                 offset += size_of::<u32>() * self.num_pairs;
             }
@@ -412,6 +592,11 @@ impl<'a> LeafAccessor<'a> {
         self.num_pairs
     }
 
+    // Returns true if the value of the nth entry is stored in an overflow page
+    pub(crate) fn is_overflow(&self, n: usize) -> bool {
+        self.page[0] == LEAF_WITH_OVERFLOW && overflow_bit(self.page, n)
+    }
+
     pub(super) fn offset_of_first_value(&self) -> usize {
         self.offset_of_value(0).unwrap()
     }
@@ -460,7 +645,7 @@ impl<'a> LeafAccessor<'a> {
     pub(crate) fn entry(&self, n: usize) -> Option<EntryAccessor<'a>> {
         let key = &self.page[self.key_start(n)?..self.key_end(n)?];
         let value = &self.page[self.value_start(n)?..self.value_end(n)?];
-        Some(EntryAccessor::new(key, value))
+        Some(EntryAccessor::new(key, value, self.is_overflow(n)))
     }
 
     pub(crate) fn entry_ranges(&self, n: usize) -> Option<(Range<usize>, Range<usize>)> {
@@ -475,7 +660,8 @@ impl<'a> LeafAccessor<'a> {
 }
 
 pub(super) struct LeafBuilder<'a, 'b> {
-    pairs: Vec<(&'a [u8], &'a [u8])>,
+    // The key, the value, and whether the value is an OverflowRef
+    pairs: Vec<(&'a [u8], &'a [u8], bool)>,
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
     total_key_bytes: usize,
@@ -484,13 +670,23 @@ pub(super) struct LeafBuilder<'a, 'b> {
 }
 
 impl<'a, 'b> LeafBuilder<'a, 'b> {
-    pub(super) fn required_bytes(&self, num_pairs: usize, keys_values_bytes: usize) -> usize {
-        RawLeafBuilder::required_bytes(
+    pub(super) fn required_bytes(
+        &self,
+        num_pairs: usize,
+        keys_values_bytes: usize,
+        overflow: bool,
+    ) -> usize {
+        let mut result = RawLeafBuilder::required_bytes(
             num_pairs,
             keys_values_bytes,
             self.fixed_key_size,
             self.fixed_value_size,
-        )
+        );
+        if overflow {
+            result += overflow_bitmap_len(num_pairs);
+        }
+
+        result
     }
 
     pub(super) fn new(
@@ -510,9 +706,23 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
     }
 
     pub(super) fn push(&mut self, key: &'a [u8], value: &'a [u8]) {
+        self.push_helper(key, value, false);
+    }
+
+    // Pushes an entry whose value is stored in an overflow page
+    pub(super) fn push_overflow(&mut self, key: &'a [u8], reference: &'a [u8]) {
+        self.push_helper(key, reference, true);
+    }
+
+    pub(super) fn push_entry(&mut self, accessor: &'a LeafAccessor<'_>, n: usize) {
+        let entry = accessor.entry(n).unwrap();
+        self.push_helper(entry.key(), entry.value(), entry.is_overflow());
+    }
+
+    fn push_helper(&mut self, key: &'a [u8], value: &'a [u8], overflow: bool) {
         self.total_key_bytes += key.len();
         self.total_value_bytes += value.len();
-        self.pairs.push((key, value))
+        self.pairs.push((key, value, overflow))
     }
 
     pub(super) fn push_all_except(
@@ -526,8 +736,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
                     continue;
                 }
             }
-            let entry = accessor.entry(i).unwrap();
-            self.push(entry.key(), entry.value());
+            self.push_entry(accessor, i);
         }
     }
 
@@ -535,6 +744,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
         let required_size = self.required_bytes(
             self.pairs.len(),
             self.total_key_bytes + self.total_value_bytes,
+            has_overflow(&self.pairs),
         );
         required_size > self.mem.get_page_size() && self.pairs.len() > 1
     }
@@ -544,7 +754,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
         let mut division = 0;
         let mut first_split_key_bytes = 0;
         let mut first_split_value_bytes = 0;
-        for (key, value) in self.pairs.iter().take(self.pairs.len() - 1) {
+        for (key, value, _) in self.pairs.iter().take(self.pairs.len() - 1) {
             first_split_key_bytes += key.len();
             first_split_value_bytes += value.len();
             division += 1;
@@ -553,70 +763,74 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
             }
         }
 
-        let required_size =
-            self.required_bytes(division, first_split_key_bytes + first_split_value_bytes);
-        let mut page1 = self.mem.allocate(required_size, CachePriority::Low)?;
-        let mut builder = RawLeafBuilder::new(
-            page1.memory_mut(),
-            division,
-            self.fixed_key_size,
-            self.fixed_value_size,
+        let page1 = self.build_page(
+            &self.pairs[..division],
             first_split_key_bytes,
-        );
-        for (key, value) in self.pairs.iter().take(division) {
-            builder.append(key, value);
-        }
-        drop(builder);
-
-        let required_size = self.required_bytes(
-            self.pairs.len() - division,
-            self.total_key_bytes + self.total_value_bytes
-                - first_split_key_bytes
-                - first_split_value_bytes,
-        );
-        let mut page2 = self.mem.allocate(required_size, CachePriority::Low)?;
-        let mut builder = RawLeafBuilder::new(
-            page2.memory_mut(),
-            self.pairs.len() - division,
-            self.fixed_key_size,
-            self.fixed_value_size,
+            first_split_value_bytes,
+        )?;
+        let page2 = self.build_page(
+            &self.pairs[division..],
             self.total_key_bytes - first_split_key_bytes,
-        );
-        for (key, value) in self.pairs[division..].iter() {
-            builder.append(key, value);
-        }
-        drop(builder);
+            self.total_value_bytes - first_split_value_bytes,
+        )?;
 
         Ok((page1, self.pairs[division - 1].0, page2))
     }
 
     pub(super) fn build(self) -> Result<PageMut> {
-        let required_size = self.required_bytes(
-            self.pairs.len(),
-            self.total_key_bytes + self.total_value_bytes,
-        );
+        self.build_page(&self.pairs, self.total_key_bytes, self.total_value_bytes)
+    }
+
+    fn build_page(
+        &self,
+        pairs: &[(&[u8], &[u8], bool)],
+        key_bytes: usize,
+        value_bytes: usize,
+    ) -> Result<PageMut> {
+        let overflow = has_overflow(pairs);
+        let required_size = self.required_bytes(pairs.len(), key_bytes + value_bytes, overflow);
         let mut page = self.mem.allocate(required_size, CachePriority::Low)?;
-        let mut builder = RawLeafBuilder::new(
-            page.memory_mut(),
-            self.pairs.len(),
-            self.fixed_key_size,
-            self.fixed_value_size,
-            self.total_key_bytes,
-        );
-        for (key, value) in self.pairs {
-            builder.append(key, value);
+        let mut builder = if overflow {
+            RawLeafBuilder::with_overflow(
+                page.memory_mut(),
+                pairs.len(),
+                self.fixed_key_size,
+                self.fixed_value_size,
+                key_bytes,
+            )
+        } else {
+            RawLeafBuilder::new(
+                page.memory_mut(),
+                pairs.len(),
+                self.fixed_key_size,
+                self.fixed_value_size,
+                key_bytes,
+            )
+        };
+        for (key, value, overflow) in pairs {
+            if *overflow {
+                builder.append_overflow(key, value);
+            } else {
+                builder.append(key, value);
+            }
         }
         drop(builder);
         Ok(page)
     }
 }
 
+fn has_overflow(pairs: &[(&[u8], &[u8], bool)]) -> bool {
+    pairs.iter().any(|(_, _, overflow)| *overflow)
+}
+
 // Note the caller is responsible for ensuring that the buffer is large enough
 // and rewriting all fields if any dynamically sized fields are written
 // Layout is:
 // 1 byte: type
 // 1 byte: reserved (padding to 32bits aligned)
 // 2 bytes: num_entries (number of pairs)
+// (leaves with overflow values only) ceil(num_entries / 32) * 4 bytes: bitmap of the values which
+// are stored in overflow pages. The reserved byte then records whether keys are variable width
 // (optional) repeating (num_entries times):
 // 4 bytes: key_end
 // (optional) repeating (num_entries times):
@@ -630,6 +844,7 @@ pub(crate) struct RawLeafBuilder<'a> {
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
     num_pairs: usize,
+    header_len: usize,
     provisioned_key_bytes: usize,
     pairs_written: usize, // used for debugging
 }
@@ -664,17 +879,66 @@ impl<'a> RawLeafBuilder<'a> {
     ) -> Self {
         page[0] = LEAF;
         page[2..4].copy_from_slice(&u16::try_from(num_pairs).unwrap().to_le_bytes());
+        Self::new_helper(
+            page,
+            num_pairs,
+            fixed_key_size,
+            fixed_value_size,
+            key_bytes,
+            4,
+        )
+    }
+
+    // Creates a builder for a leaf which may store some of its values in overflow pages.
+    // Overflow values must be appended with append_overflow()
+    pub(super) fn with_overflow(
+        page: &'a mut [u8],
+        num_pairs: usize,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+        key_bytes: usize,
+    ) -> Self {
+        assert!(fixed_value_size.is_none());
+        page[0] = LEAF_WITH_OVERFLOW;
+        page[1] = if fixed_key_size.is_none() {
+            VARIABLE_WIDTH_KEYS
+        } else {
+            0
+        };
+        page[2..4].copy_from_slice(&u16::try_from(num_pairs).unwrap().to_le_bytes());
+        let header_len = 4 + overflow_bitmap_len(num_pairs);
+        for x in &mut page[4..header_len] {
+            *x = 0;
+        }
+        Self::new_helper(
+            page,
+            num_pairs,
+            fixed_key_size,
+            fixed_value_size,
+            key_bytes,
+            header_len,
+        )
+    }
+
+    fn new_helper(
+        page: &'a mut [u8],
+        num_pairs: usize,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+        key_bytes: usize,
+        header_len: usize,
+    ) -> Self {
         #[cfg(debug_assertions)]
         {
             // Poison all the key & value offsets, in case the caller forgets to write them
-            let mut last = 4;
+            let mut last = header_len;
             if fixed_key_size.is_none() {
                 last += size_of::<u32>() * num_pairs;
             }
             if fixed_value_size.is_none() {
                 last += size_of::<u32>() * num_pairs;
             }
-            for x in &mut page[4..last] {
+            for x in &mut page[header_len..last] {
                 *x = 0xFF;
             }
         }
@@ -683,6 +947,7 @@ impl<'a> RawLeafBuilder<'a> {
             fixed_key_size,
             fixed_value_size,
             num_pairs,
+            header_len,
             provisioned_key_bytes: key_bytes,
             pairs_written: 0,
         }
@@ -692,7 +957,7 @@ impl<'a> RawLeafBuilder<'a> {
         if let Some(fixed) = self.fixed_value_size {
             return self.key_section_start() + self.provisioned_key_bytes + fixed * (n + 1);
         }
-        let mut offset = 4 + size_of::<u32>() * n;
+        let mut offset = self.header_len + size_of::<u32>() * n;
         if self.fixed_key_size.is_none() {
             offset += size_of::<u32>() * self.num_pairs;
         }
@@ -704,7 +969,7 @@ impl<'a> RawLeafBuilder<'a> {
     }
 
     fn key_section_start(&self) -> usize {
-        let mut offset = 4;
+        let mut offset = self.header_len;
         if self.fixed_key_size.is_none() {
             offset += size_of::<u32>() * self.num_pairs;
         }
@@ -719,7 +984,7 @@ impl<'a> RawLeafBuilder<'a> {
         if let Some(fixed) = self.fixed_key_size {
             return self.key_section_start() + fixed * (n + 1);
         }
-        let offset = 4 + size_of::<u32>() * n;
+        let offset = self.header_len + size_of::<u32>() * n;
         u32::from_le_bytes(
             self.page[offset..(offset + size_of::<u32>())]
                 .try_into()
@@ -747,7 +1012,7 @@ impl<'a> RawLeafBuilder<'a> {
 
         let n = self.pairs_written;
         if self.fixed_key_size.is_none() {
-            let offset = 4 + size_of::<u32>() * n;
+            let offset = self.header_len + size_of::<u32>() * n;
             self.page[offset..(offset + size_of::<u32>())]
                 .copy_from_slice(&u32::try_from(key_offset + key.len()).unwrap().to_le_bytes());
         }
@@ -756,7 +1021,7 @@ impl<'a> RawLeafBuilder<'a> {
         assert!(written_key_len <= self.provisioned_key_bytes);
 
         if self.fixed_value_size.is_none() {
-            let mut offset = 4 + size_of::<u32>() * n;
+            let mut offset = self.header_len + size_of::<u32>() * n;
             if self.fixed_key_size.is_none() {
                 offset += size_of::<u32>() * self.num_pairs;
             }
@@ -769,6 +1034,15 @@ impl<'a> RawLeafBuilder<'a> {
         self.page[value_offset..(value_offset + value.len())].copy_from_slice(value);
         self.pairs_written += 1;
     }
+
+    // Appends an entry whose value is stored in an overflow page
+    pub(super) fn append_overflow(&mut self, key: &[u8], reference: &[u8]) {
+        assert_eq!(self.page[0], LEAF_WITH_OVERFLOW);
+        assert_eq!(reference.len(), OverflowRef::serialized_size());
+        let n = self.pairs_written;
+        self.page[4 + n / 8] |= 1 << (n % 8);
+        self.append(key, reference);
+    }
 }
 
 impl<'a> Drop for RawLeafBuilder<'a> {
@@ -812,6 +1086,10 @@ impl<'b> LeafMutator<'b> {
         new_key: &[u8],
         new_value: &[u8],
     ) -> bool {
+        // Overflow bits are not maintained by the mutator
+        if page.memory()[0] != LEAF {
+            return false;
+        }
         let accessor = LeafAccessor::new(page.memory(), fixed_key_size, fixed_value_size);
         if overwrite {
             let remaining = page.memory().len() - accessor.total_length();
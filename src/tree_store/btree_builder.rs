@@ -0,0 +1,168 @@
+use crate::tree_store::btree_base::{
+    should_overflow, write_overflow_page, BranchBuilder, BtreeHeader, Checksum, LeafBuilder,
+    RawBranchBuilder, RawLeafBuilder, DEFERRED,
+};
+use crate::tree_store::page_store::{Page, PageNumber, TransactionalMemory};
+use crate::Result;
+use std::mem;
+
+struct PendingChild {
+    page: PageNumber,
+    checksum: Checksum,
+    last_key: Vec<u8>,
+}
+
+// Builds a new btree bottom-up from a stream of entries which must already be sorted by key.
+// Leaves are packed as full as the page size allows, and each level of branches is streamed, so
+// memory use is bounded by the height of the tree rather than the number of entries. Large values
+// are written to overflow pages as they're pushed.
+//
+// The caller is responsible for ensuring the ordering of keys. All pages are allocated in the
+// current transaction, and their checksums are deferred until the tree is finalized at commit
+pub(crate) struct SortedBtreeBuilder<'a> {
+    mem: &'a TransactionalMemory,
+    fixed_key_size: Option<usize>,
+    fixed_value_size: Option<usize>,
+    // The key, the value, and whether the value is an OverflowRef
+    pending_pairs: Vec<(Vec<u8>, Vec<u8>, bool)>,
+    pending_bytes: usize,
+    // Children which have not yet been written into a branch, for each level of the tree
+    levels: Vec<Vec<PendingChild>>,
+    length: u64,
+}
+
+impl<'a> SortedBtreeBuilder<'a> {
+    pub(crate) fn new(
+        mem: &'a TransactionalMemory,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+    ) -> Self {
+        Self {
+            mem,
+            fixed_key_size,
+            fixed_value_size,
+            pending_pairs: vec![],
+            pending_bytes: 0,
+            levels: vec![],
+            length: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, key: &[u8], value: &[u8]) -> Result {
+        if should_overflow(self.mem, self.fixed_value_size, value.len()) {
+            let (_, reference) = write_overflow_page(self.mem, value)?;
+            self.push_helper(key, &reference.to_le_bytes(), true)
+        } else {
+            self.push_helper(key, value, false)
+        }
+    }
+
+    fn push_helper(&mut self, key: &[u8], value: &[u8], overflow: bool) -> Result {
+        let new_bytes = self.pending_bytes + key.len() + value.len();
+        let required = RawLeafBuilder::required_bytes(
+            self.pending_pairs.len() + 1,
+            new_bytes,
+            self.fixed_key_size,
+            self.fixed_value_size,
+        );
+        if !self.pending_pairs.is_empty() && required > self.mem.get_page_size() {
+            self.flush_leaf()?;
+        }
+        self.pending_bytes += key.len() + value.len();
+        self.pending_pairs
+            .push((key.to_vec(), value.to_vec(), overflow));
+        self.length += 1;
+
+        Ok(())
+    }
+
+    fn flush_leaf(&mut self) -> Result {
+        let pairs = mem::take(&mut self.pending_pairs);
+        self.pending_bytes = 0;
+        let mut builder = LeafBuilder::new(
+            self.mem,
+            pairs.len(),
+            self.fixed_key_size,
+            self.fixed_value_size,
+        );
+        for (key, value, overflow) in pairs.iter() {
+            if *overflow {
+                builder.push_overflow(key, value);
+            } else {
+                builder.push(key, value);
+            }
+        }
+        let page = builder.build()?;
+        let (last_key, _, _) = pairs.into_iter().last().unwrap();
+        self.push_child(
+            0,
+            PendingChild {
+                page: page.get_page_number(),
+                checksum: DEFERRED,
+                last_key,
+            },
+        )
+    }
+
+    fn push_child(&mut self, level: usize, child: PendingChild) -> Result {
+        if self.levels.len() == level {
+            self.levels.push(vec![]);
+        }
+        let children = &self.levels[level];
+        // Adding this child would add the last key of the current last child to the branch
+        let key_bytes: usize = children.iter().map(|x| x.last_key.len()).sum();
+        let required =
+            RawBranchBuilder::required_bytes(children.len(), key_bytes, self.fixed_key_size);
+        // Always leave at least two children behind, so that the final branch at this level is valid
+        if children.len() >= 3 && required > self.mem.get_page_size() {
+            let keep = self.levels[level].pop().unwrap();
+            let full = mem::replace(&mut self.levels[level], vec![keep]);
+            let branch = self.write_branch(full)?;
+            self.push_child(level + 1, branch)?;
+        }
+        self.levels[level].push(child);
+
+        Ok(())
+    }
+
+    fn write_branch(&self, children: Vec<PendingChild>) -> Result<PendingChild> {
+        let mut builder = BranchBuilder::new(self.mem, children.len(), self.fixed_key_size);
+        for child in children.iter() {
+            builder.push_child(child.page, child.checksum);
+        }
+        for child in children.iter().take(children.len() - 1) {
+            builder.push_key(&child.last_key);
+        }
+        let page = builder.build()?;
+        let last_key = children.into_iter().last().unwrap().last_key;
+
+        Ok(PendingChild {
+            page: page.get_page_number(),
+            checksum: DEFERRED,
+            last_key,
+        })
+    }
+
+    pub(crate) fn finish(mut self) -> Result<Option<BtreeHeader>> {
+        if !self.pending_pairs.is_empty() {
+            self.flush_leaf()?;
+        }
+        let mut level = 0;
+        while level < self.levels.len() {
+            let children = mem::take(&mut self.levels[level]);
+            if level == self.levels.len() - 1 && children.len() == 1 {
+                let root = children.into_iter().next().unwrap();
+                return Ok(Some(BtreeHeader::new(
+                    root.page,
+                    root.checksum,
+                    self.length,
+                )));
+            }
+            let branch = self.write_branch(children)?;
+            self.push_child(level + 1, branch)?;
+            level += 1;
+        }
+
+        Ok(None)
+    }
+}
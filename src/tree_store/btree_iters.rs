@@ -1,5 +1,7 @@
-use crate::tree_store::btree_base::{BranchAccessor, LeafAccessor};
-use crate::tree_store::btree_base::{BRANCH, LEAF};
+use crate::tree_store::btree_base::{
+    leaf_overflow_refs, BranchAccessor, LeafAccessor, OverflowRef,
+};
+use crate::tree_store::btree_base::{BRANCH, LEAF, LEAF_WITH_OVERFLOW};
 use crate::tree_store::btree_iters::RangeIterState::{Internal, Leaf};
 use crate::tree_store::btree_mutator::MutateHelper;
 use crate::tree_store::page_store::{Page, PageImpl, TransactionalMemory};
@@ -84,7 +86,7 @@ impl RangeIterState {
                     }));
                 }
                 match child_page.memory()[0] {
-                    LEAF => {
+                    LEAF | LEAF_WITH_OVERFLOW => {
                         let child_accessor = LeafAccessor::new(
                             child_page.memory(),
                             fixed_key_size,
@@ -124,7 +126,10 @@ impl RangeIterState {
         }
     }
 
-    fn get_entry<K: Key, V: Value>(&self) -> Option<EntryGuard<K, V>> {
+    fn get_entry<K: Key, V: Value>(
+        &self,
+        manager: &TransactionalMemory,
+    ) -> Option<Result<EntryGuard<K, V>>> {
         match self {
             Leaf {
                 page,
@@ -133,10 +138,16 @@ impl RangeIterState {
                 entry,
                 ..
             } => {
-                let (key, value) =
-                    LeafAccessor::new(page.memory(), *fixed_key_size, *fixed_value_size)
-                        .entry_ranges(*entry)?;
-                Some(EntryGuard::new(page.clone(), key, value))
+                let accessor = LeafAccessor::new(page.memory(), *fixed_key_size, *fixed_value_size);
+                let (key, value) = accessor.entry_ranges(*entry)?;
+                if accessor.is_overflow(*entry) {
+                    let reference = OverflowRef::from_le_bytes(&page.memory()[value]);
+                    Some(manager.get_page(reference.page).map(|value_page| {
+                        EntryGuard::new(page.clone(), key, value_page, reference.value_range())
+                    }))
+                } else {
+                    Some(Ok(EntryGuard::new(page.clone(), key, page.clone(), value)))
+                }
             }
             _ => None,
         }
@@ -146,16 +157,24 @@ impl RangeIterState {
 pub(crate) struct EntryGuard<K: Key, V: Value> {
     page: PageImpl,
     key_range: Range<usize>,
+    // The leaf, or the overflow page which stores the value
+    value_page: PageImpl,
     value_range: Range<usize>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
 
 impl<K: Key, V: Value> EntryGuard<K, V> {
-    fn new(page: PageImpl, key_range: Range<usize>, value_range: Range<usize>) -> Self {
+    fn new(
+        page: PageImpl,
+        key_range: Range<usize>,
+        value_page: PageImpl,
+        value_range: Range<usize>,
+    ) -> Self {
         Self {
             page,
             key_range,
+            value_page,
             value_range,
             _key_type: Default::default(),
             _value_type: Default::default(),
@@ -171,16 +190,19 @@ impl<K: Key, V: Value> EntryGuard<K, V> {
     }
 
     pub(crate) fn value(&self) -> V::SelfType<'_> {
-        V::from_bytes(&self.page.memory()[self.value_range.clone()])
+        V::from_bytes(&self.value_page.memory()[self.value_range.clone()])
     }
 
-    pub(crate) fn into_raw(self) -> (PageImpl, Range<usize>, Range<usize>) {
-        (self.page, self.key_range, self.value_range)
+    // Returns the key's page and range, and the value's page and range
+    pub(crate) fn into_raw(self) -> (PageImpl, Range<usize>, PageImpl, Range<usize>) {
+        (self.page, self.key_range, self.value_page, self.value_range)
     }
 }
 
 pub(crate) struct AllPageNumbersBtreeIter {
     next: Option<RangeIterState>,
+    // Overflow pages of the current leaf, which have not been returned yet
+    overflow_pages: Vec<PageNumber>,
     manager: Arc<TransactionalMemory>,
 }
 
@@ -194,7 +216,7 @@ impl AllPageNumbersBtreeIter {
         let root_page = manager.get_page(root)?;
         let node_mem = root_page.memory();
         let start = match node_mem[0] {
-            LEAF => Leaf {
+            LEAF | LEAF_WITH_OVERFLOW => Leaf {
                 page: root_page,
                 fixed_key_size,
                 fixed_value_size,
@@ -212,6 +234,7 @@ impl AllPageNumbersBtreeIter {
         };
         Ok(Self {
             next: Some(start),
+            overflow_pages: vec![],
             manager,
         })
     }
@@ -221,12 +244,25 @@ impl Iterator for AllPageNumbersBtreeIter {
     type Item = Result<PageNumber>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(page) = self.overflow_pages.pop() {
+            return Some(Ok(page));
+        }
         loop {
             let state = self.next.take()?;
             let value = state.page_number();
             // Only return each page number once
             let once = match state {
-                Leaf { entry, .. } => entry == 0,
+                Leaf {
+                    entry, ref page, ..
+                } => {
+                    if entry == 0 {
+                        self.overflow_pages = leaf_overflow_refs(page.memory())
+                            .into_iter()
+                            .map(|(_, reference)| reference.page)
+                            .collect();
+                    }
+                    entry == 0
+                }
                 Internal { child, .. } => child == 0,
             };
             match state.next(false, &self.manager) {
@@ -292,6 +328,7 @@ impl<K: Key, V: Value, F: for<'f> FnMut(K::SelfType<'f>, V::SelfType<'f>) -> boo
                     self.root,
                     self.mem.clone(),
                     &mut self.free_on_drop,
+                    self.inner.fixed_value_size,
                 );
                 match operation.delete(&entry.key()) {
                     Ok(x) => {
@@ -320,6 +357,7 @@ impl<K: Key, V: Value, F: for<'f> FnMut(K::SelfType<'f>, V::SelfType<'f>) -> boo
                     self.root,
                     self.mem.clone(),
                     &mut self.free_on_drop,
+                    self.inner.fixed_value_size,
                 );
                 match operation.delete(&entry.key()) {
                     Ok(x) => {
@@ -357,6 +395,7 @@ pub(crate) struct BtreeRangeIter<K: Key + 'static, V: Value + 'static> {
     include_left: bool,           // left is inclusive, instead of exclusive
     include_right: bool,          // right is inclusive, instead of exclusive
     manager: Arc<TransactionalMemory>,
+    fixed_value_size: Option<usize>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -366,51 +405,71 @@ impl<K: Key + 'static, V: Value + 'static> BtreeRangeIter<K, V> {
         query_range: &'_ T,
         table_root: Option<PageNumber>,
         manager: Arc<TransactionalMemory>,
+    ) -> Result<Self> {
+        Self::with_fixed_value_size(query_range, table_root, V::fixed_width(), manager)
+    }
+
+    // Iterates over a tree whose values are stored with the given width, which need not match V
+    pub(crate) fn with_fixed_value_size<'a, T: RangeBounds<KR>, KR: Borrow<K::SelfType<'a>>>(
+        query_range: &'_ T,
+        table_root: Option<PageNumber>,
+        fixed_value_size: Option<usize>,
+        manager: Arc<TransactionalMemory>,
     ) -> Result<Self> {
         if let Some(root) = table_root {
             let (include_left, left) = match query_range.start_bound() {
-                Bound::Included(k) => find_iter_left::<K, V>(
+                Bound::Included(k) => find_iter_left::<K>(
                     manager.get_page(root)?,
                     None,
                     K::as_bytes(k.borrow()).as_ref(),
                     true,
+                    fixed_value_size,
                     &manager,
                 )?,
-                Bound::Excluded(k) => find_iter_left::<K, V>(
+                Bound::Excluded(k) => find_iter_left::<K>(
                     manager.get_page(root)?,
                     None,
                     K::as_bytes(k.borrow()).as_ref(),
                     false,
+                    fixed_value_size,
                     &manager,
                 )?,
                 Bound::Unbounded => {
-                    let state = find_iter_unbounded::<K, V>(
+                    let state = find_iter_unbounded::<K>(
                         manager.get_page(root)?,
                         None,
                         false,
+                        fixed_value_size,
                         &manager,
                     )?;
                     (true, state)
                 }
             };
             let (include_right, right) = match query_range.end_bound() {
-                Bound::Included(k) => find_iter_right::<K, V>(
+                Bound::Included(k) => find_iter_right::<K>(
                     manager.get_page(root)?,
                     None,
                     K::as_bytes(k.borrow()).as_ref(),
                     true,
+                    fixed_value_size,
                     &manager,
                 )?,
-                Bound::Excluded(k) => find_iter_right::<K, V>(
+                Bound::Excluded(k) => find_iter_right::<K>(
                     manager.get_page(root)?,
                     None,
                     K::as_bytes(k.borrow()).as_ref(),
                     false,
+                    fixed_value_size,
                     &manager,
                 )?,
                 Bound::Unbounded => {
-                    let state =
-                        find_iter_unbounded::<K, V>(manager.get_page(root)?, None, true, &manager)?;
+                    let state = find_iter_unbounded::<K>(
+                        manager.get_page(root)?,
+                        None,
+                        true,
+                        fixed_value_size,
+                        &manager,
+                    )?;
                     (true, state)
                 }
             };
@@ -420,6 +479,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeRangeIter<K, V> {
                 include_left,
                 include_right,
                 manager,
+                fixed_value_size,
                 _key_type: Default::default(),
                 _value_type: Default::default(),
             })
@@ -430,6 +490,7 @@ impl<K: Key + 'static, V: Value + 'static> BtreeRangeIter<K, V> {
                 include_left: false,
                 include_right: false,
                 manager,
+                fixed_value_size,
                 _key_type: Default::default(),
                 _value_type: Default::default(),
             })
@@ -498,8 +559,8 @@ impl<K: Key, V: Value> Iterator for BtreeRangeIter<K, V> {
             }
 
             self.include_left = false;
-            if self.left.as_ref().unwrap().get_entry::<K, V>().is_some() {
-                return self.left.as_ref().map(|s| s.get_entry().unwrap()).map(Ok);
+            if let Some(entry) = self.left.as_ref().unwrap().get_entry(&self.manager) {
+                return Some(entry);
             }
         }
     }
@@ -564,28 +625,29 @@ impl<K: Key, V: Value> DoubleEndedIterator for BtreeRangeIter<K, V> {
             }
 
             self.include_right = false;
-            if self.right.as_ref().unwrap().get_entry::<K, V>().is_some() {
-                return self.right.as_ref().map(|s| s.get_entry().unwrap()).map(Ok);
+            if let Some(entry) = self.right.as_ref().unwrap().get_entry(&self.manager) {
+                return Some(entry);
             }
         }
     }
 }
 
-fn find_iter_unbounded<K: Key, V: Value>(
+fn find_iter_unbounded<K: Key>(
     page: PageImpl,
     mut parent: Option<Box<RangeIterState>>,
     reverse: bool,
+    fixed_value_size: Option<usize>,
     manager: &TransactionalMemory,
 ) -> Result<Option<RangeIterState>> {
     let node_mem = page.memory();
     match node_mem[0] {
-        LEAF => {
-            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+        LEAF | LEAF_WITH_OVERFLOW => {
+            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), fixed_value_size);
             let entry = if reverse { accessor.num_pairs() - 1 } else { 0 };
             Ok(Some(Leaf {
                 page,
                 fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_value_size,
                 entry,
                 parent,
             }))
@@ -603,13 +665,13 @@ fn find_iter_unbounded<K: Key, V: Value>(
             parent = Some(Box::new(Internal {
                 page,
                 fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_value_size,
                 child: (isize::try_from(child_index).unwrap() + direction)
                     .try_into()
                     .unwrap(),
                 parent,
             }));
-            find_iter_unbounded::<K, V>(child_page, parent, reverse, manager)
+            find_iter_unbounded::<K>(child_page, parent, reverse, fixed_value_size, manager)
         }
         _ => unreachable!(),
     }
@@ -617,17 +679,18 @@ fn find_iter_unbounded<K: Key, V: Value>(
 
 // Returns a bool indicating whether the first entry pointed to by the state is included in the
 // queried range
-fn find_iter_left<K: Key, V: Value>(
+fn find_iter_left<K: Key>(
     page: PageImpl,
     mut parent: Option<Box<RangeIterState>>,
     query: &[u8],
     include_query: bool,
+    fixed_value_size: Option<usize>,
     manager: &TransactionalMemory,
 ) -> Result<(bool, Option<RangeIterState>)> {
     let node_mem = page.memory();
     match node_mem[0] {
-        LEAF => {
-            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+        LEAF | LEAF_WITH_OVERFLOW => {
+            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), fixed_value_size);
             let (mut position, found) = accessor.position::<K>(query);
             let include = if position < accessor.num_pairs() {
                 include_query || !found
@@ -640,7 +703,7 @@ fn find_iter_left<K: Key, V: Value>(
             let result = Leaf {
                 page,
                 fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_value_size,
                 entry: position,
                 parent,
             };
@@ -654,28 +717,36 @@ fn find_iter_left<K: Key, V: Value>(
                 parent = Some(Box::new(Internal {
                     page,
                     fixed_key_size: K::fixed_width(),
-                    fixed_value_size: V::fixed_width(),
+                    fixed_value_size,
                     child: child_index + 1,
                     parent,
                 }));
             }
-            find_iter_left::<K, V>(child_page, parent, query, include_query, manager)
+            find_iter_left::<K>(
+                child_page,
+                parent,
+                query,
+                include_query,
+                fixed_value_size,
+                manager,
+            )
         }
         _ => unreachable!(),
     }
 }
 
-fn find_iter_right<K: Key, V: Value>(
+fn find_iter_right<K: Key>(
     page: PageImpl,
     mut parent: Option<Box<RangeIterState>>,
     query: &[u8],
     include_query: bool,
+    fixed_value_size: Option<usize>,
     manager: &TransactionalMemory,
 ) -> Result<(bool, Option<RangeIterState>)> {
     let node_mem = page.memory();
     match node_mem[0] {
-        LEAF => {
-            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+        LEAF | LEAF_WITH_OVERFLOW => {
+            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), fixed_value_size);
             let (mut position, found) = accessor.position::<K>(query);
             let include = if position < accessor.num_pairs() {
                 include_query && found
@@ -688,7 +759,7 @@ fn find_iter_right<K: Key, V: Value>(
             let result = Leaf {
                 page,
                 fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_value_size,
                 entry: position,
                 parent,
             };
@@ -702,12 +773,19 @@ fn find_iter_right<K: Key, V: Value>(
                 parent = Some(Box::new(Internal {
                     page,
                     fixed_key_size: K::fixed_width(),
-                    fixed_value_size: V::fixed_width(),
+                    fixed_value_size,
                     child: child_index - 1,
                     parent,
                 }));
             }
-            find_iter_right::<K, V>(child_page, parent, query, include_query, manager)
+            find_iter_right::<K>(
+                child_page,
+                parent,
+                query,
+                include_query,
+                fixed_value_size,
+                manager,
+            )
         }
         _ => unreachable!(),
     }
@@ -1,6 +1,7 @@
 use crate::tree_store::btree_base::{
-    BranchAccessor, BranchBuilder, BranchMutator, Checksum, LeafAccessor, LeafBuilder, LeafMutator,
-    BRANCH, DEFERRED, LEAF,
+    should_overflow, write_overflow_page, BranchAccessor, BranchBuilder, BranchMutator, Checksum,
+    LeafAccessor, LeafBuilder, LeafMutator, OverflowRef, BRANCH, DEFERRED, LEAF,
+    LEAF_WITH_OVERFLOW,
 };
 use crate::tree_store::btree_mutator::DeletionResult::{
     DeletedBranch, DeletedLeaf, PartialBranch, PartialLeaf, Subtree,
@@ -46,11 +47,20 @@ struct InsertionResult<'a, V: Value + 'static> {
     old_value: Option<AccessGuard<'a, V>>,
 }
 
+enum UpdateResult<'a, V: Value + 'static> {
+    // The key was not present, and no value was inserted
+    Unchanged,
+    Inserted(InsertionResult<'a, V>),
+    // The entry was removed. Includes its value
+    Removed(DeletionResult, AccessGuard<'a, V>),
+}
+
 pub(crate) struct MutateHelper<'a, 'b, K: Key, V: Value> {
     root: &'b mut Option<BtreeHeader>,
     modify_uncommitted: bool,
     mem: Arc<TransactionalMemory>,
     freed: &'b mut Vec<PageNumber>,
+    fixed_value_size: Option<usize>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
     _lifetime: PhantomData<&'a ()>,
@@ -61,12 +71,14 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
         root: &'b mut Option<BtreeHeader>,
         mem: Arc<TransactionalMemory>,
         freed: &'b mut Vec<PageNumber>,
+        fixed_value_size: Option<usize>,
     ) -> Self {
         Self {
             root,
             modify_uncommitted: true,
             mem,
             freed,
+            fixed_value_size,
             _key_type: Default::default(),
             _value_type: Default::default(),
             _lifetime: Default::default(),
@@ -79,12 +91,14 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
         root: &'b mut Option<BtreeHeader>,
         mem: Arc<TransactionalMemory>,
         freed: &'b mut Vec<PageNumber>,
+        fixed_value_size: Option<usize>,
     ) -> Self {
         Self {
             root,
             modify_uncommitted: false,
             mem,
             freed,
+            fixed_value_size,
             _key_type: Default::default(),
             _value_type: Default::default(),
             _lifetime: Default::default(),
@@ -111,45 +125,84 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
             let (deletion_result, found) =
                 self.delete_helper(self.mem.get_page(p)?, checksum, K::as_bytes(key).as_ref())?;
             let new_length = if found.is_some() { length - 1 } else { length };
-            let new_root = match deletion_result {
-                Subtree(page, checksum) => Some(BtreeHeader::new(page, checksum, new_length)),
-                DeletedLeaf => None,
-                PartialLeaf { page, deleted_pair } => {
-                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
-                    let mut builder = LeafBuilder::new(
-                        &self.mem,
-                        accessor.num_pairs() - 1,
-                        K::fixed_width(),
-                        V::fixed_width(),
-                    );
-                    builder.push_all_except(&accessor, Some(deleted_pair));
-                    let page = builder.build()?;
-                    assert_eq!(new_length, accessor.num_pairs() as u64 - 1);
-                    Some(BtreeHeader::new(
-                        page.get_page_number(),
-                        DEFERRED,
-                        new_length,
-                    ))
-                }
-                PartialBranch(page_number, checksum) => {
-                    Some(BtreeHeader::new(page_number, checksum, new_length))
-                }
-                DeletedBranch(remaining_child, checksum) => {
-                    Some(BtreeHeader::new(remaining_child, checksum, new_length))
-                }
-            };
-            *self.root = new_root;
+            *self.root = self.finish_delete(deletion_result, new_length)?;
             Ok(found)
         } else {
             Ok(None)
         }
     }
 
+    // Returns the root of the tree, after a deletion which produced `deletion_result`
+    fn finish_delete(
+        &self,
+        deletion_result: DeletionResult,
+        new_length: u64,
+    ) -> Result<Option<BtreeHeader>> {
+        Ok(match deletion_result {
+            Subtree(page, checksum) => Some(BtreeHeader::new(page, checksum, new_length)),
+            DeletedLeaf => None,
+            PartialLeaf { page, deleted_pair } => {
+                let accessor = LeafAccessor::new(&page, K::fixed_width(), self.fixed_value_size);
+                let mut builder = LeafBuilder::new(
+                    &self.mem,
+                    accessor.num_pairs() - 1,
+                    K::fixed_width(),
+                    self.fixed_value_size,
+                );
+                builder.push_all_except(&accessor, Some(deleted_pair));
+                let page = builder.build()?;
+                assert_eq!(new_length, accessor.num_pairs() as u64 - 1);
+                Some(BtreeHeader::new(
+                    page.get_page_number(),
+                    DEFERRED,
+                    new_length,
+                ))
+            }
+            PartialBranch(page_number, checksum) => {
+                Some(BtreeHeader::new(page_number, checksum, new_length))
+            }
+            DeletedBranch(remaining_child, checksum) => {
+                Some(BtreeHeader::new(remaining_child, checksum, new_length))
+            }
+        })
+    }
+
     #[allow(clippy::type_complexity)]
     pub(crate) fn insert(
         &mut self,
         key: &K::SelfType<'_>,
         value: &V::SelfType<'_>,
+    ) -> Result<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a, V>)> {
+        let value_bytes = V::as_bytes(value);
+        let value_bytes = value_bytes.as_ref();
+        if should_overflow(&self.mem, self.fixed_value_size, value_bytes.len()) {
+            let (page, reference) = write_overflow_page(&self.mem, value_bytes)?;
+            let (old_value, _) = self.insert_bytes(key, &reference.to_le_bytes(), true)?;
+            let guard = AccessGuardMut::new(page, reference.value_range().start, reference.length);
+            Ok((old_value, guard))
+        } else {
+            self.insert_bytes(key, value_bytes, false)
+        }
+    }
+
+    // Inserts the value into a leaf, even if it's large enough to be stored in an overflow page.
+    // This allows the returned guard to be used to write the value
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn insert_inline(
+        &mut self,
+        key: &K::SelfType<'_>,
+        value: &V::SelfType<'_>,
+    ) -> Result<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a, V>)> {
+        self.insert_bytes(key, V::as_bytes(value).as_ref(), false)
+    }
+
+    // If overflow is true, value is an OverflowRef
+    #[allow(clippy::type_complexity)]
+    fn insert_bytes(
+        &mut self,
+        key: &K::SelfType<'_>,
+        value: &[u8],
+        overflow: bool,
     ) -> Result<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a, V>)> {
         let (new_root, old_value, guard) = if let Some(BtreeHeader {
             root: p,
@@ -161,7 +214,8 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 self.mem.get_page(p)?,
                 checksum,
                 K::as_bytes(key).as_ref(),
-                V::as_bytes(value).as_ref(),
+                value,
+                overflow,
             )?;
 
             let new_length = if result.old_value.is_some() {
@@ -170,30 +224,30 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 length + 1
             };
 
-            let new_root = if let Some((key, page2, page2_checksum)) = result.additional_sibling {
-                let mut builder = BranchBuilder::new(&self.mem, 2, K::fixed_width());
-                builder.push_child(result.new_root, result.root_checksum);
-                builder.push_key(&key);
-                builder.push_child(page2, page2_checksum);
-                let new_page = builder.build()?;
-                BtreeHeader::new(new_page.get_page_number(), DEFERRED, new_length)
-            } else {
-                BtreeHeader::new(result.new_root, result.root_checksum, new_length)
-            };
+            let new_root = self.finish_insert(
+                result.new_root,
+                result.root_checksum,
+                result.additional_sibling,
+                new_length,
+            )?;
             (new_root, result.old_value, result.inserted_value)
         } else {
             let key_bytes = K::as_bytes(key);
-            let value_bytes = V::as_bytes(value);
             let key_bytes = key_bytes.as_ref();
-            let value_bytes = value_bytes.as_ref();
-            let mut builder = LeafBuilder::new(&self.mem, 1, K::fixed_width(), V::fixed_width());
-            builder.push(key_bytes, value_bytes);
+            let mut builder =
+                LeafBuilder::new(&self.mem, 1, K::fixed_width(), self.fixed_value_size);
+            if overflow {
+                builder.push_overflow(key_bytes, value);
+            } else {
+                builder.push(key_bytes, value);
+            }
             let page = builder.build()?;
 
-            let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+            let accessor =
+                LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
             let offset = accessor.offset_of_first_value();
             let page_num = page.get_page_number();
-            let guard = AccessGuardMut::new(page, offset, value_bytes.len());
+            let guard = AccessGuardMut::new(page, offset, value.len());
 
             (BtreeHeader::new(page_num, DEFERRED, 1), None, guard)
         };
@@ -201,234 +255,390 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
         Ok((old_value, guard))
     }
 
-    fn insert_helper(
+    // Replaces the value of the given key with the result of `f`, which is passed the current value,
+    // if any. If `f` returns None, the key is removed. The tree is only descended once, and is left
+    // unmodified if `f` returns an error.
+    //
+    // Returns the old value, if any
+    pub(crate) fn update(
         &mut self,
-        page: PageImpl,
-        page_checksum: Checksum,
-        key: &[u8],
-        value: &[u8],
-    ) -> Result<InsertionResult<'a, V>> {
-        let node_mem = page.memory();
-        Ok(match node_mem[0] {
-            LEAF => {
-                let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
-                let (position, found) = accessor.position::<K>(key);
-
-                // Fast-path to avoid re-building and splitting pages with a single large value
-                let single_large_value = accessor.num_pairs() == 1
-                    && accessor.total_length() >= self.mem.get_page_size();
-                if !found && single_large_value {
-                    let mut builder =
-                        LeafBuilder::new(&self.mem, 1, K::fixed_width(), V::fixed_width());
-                    builder.push(key, value);
-                    let new_page = builder.build()?;
-                    let new_page_number = new_page.get_page_number();
-                    let new_page_accessor =
-                        LeafAccessor::new(new_page.memory(), K::fixed_width(), V::fixed_width());
-                    let offset = new_page_accessor.offset_of_first_value();
-                    drop(new_page_accessor);
-                    let guard = AccessGuardMut::new(new_page, offset, value.len());
-                    return if position == 0 {
-                        Ok(InsertionResult {
-                            new_root: new_page_number,
-                            root_checksum: DEFERRED,
-                            additional_sibling: Some((
-                                key.to_vec(),
-                                page.get_page_number(),
-                                page_checksum,
-                            )),
-                            inserted_value: guard,
-                            old_value: None,
-                        })
-                    } else {
-                        let split_key = accessor.last_entry().key().to_vec();
-                        Ok(InsertionResult {
-                            new_root: page.get_page_number(),
-                            root_checksum: page_checksum,
-                            additional_sibling: Some((split_key, new_page_number, DEFERRED)),
-                            inserted_value: guard,
-                            old_value: None,
-                        })
-                    };
-                }
-
-                // Fast-path for uncommitted pages, that can be modified in-place
-                if self.mem.uncommitted(page.get_page_number())
-                    && self.modify_uncommitted
-                    && LeafMutator::sufficient_insert_inplace_space(
-                        &page,
-                        position,
-                        found,
-                        K::fixed_width(),
-                        V::fixed_width(),
-                        key,
-                        value,
-                    )
-                {
-                    let page_number = page.get_page_number();
-                    let existing_value = if found {
-                        let copied_value = accessor.entry(position).unwrap().value().to_vec();
-                        Some(AccessGuard::with_owned_value(copied_value))
+        key: &K::SelfType<'_>,
+        f: impl FnOnce(Option<&[u8]>) -> Result<Option<Vec<u8>>>,
+    ) -> Result<Option<AccessGuard<'a, V>>> {
+        if let Some(BtreeHeader {
+            root: p,
+            checksum,
+            length,
+        }) = *self.root
+        {
+            let page = self.mem.get_page(p)?;
+            match self.update_helper(page, checksum, K::as_bytes(key).as_ref(), f)? {
+                UpdateResult::Unchanged => Ok(None),
+                UpdateResult::Inserted(result) => {
+                    let new_length = if result.old_value.is_some() {
+                        length
                     } else {
-                        None
+                        length + 1
                     };
-                    drop(page);
-                    let mut page_mut = self.mem.get_page_mut(page_number)?;
-                    let mut mutator =
-                        LeafMutator::new(&mut page_mut, K::fixed_width(), V::fixed_width());
-                    mutator.insert(position, found, key, value);
-                    let new_page_accessor =
-                        LeafAccessor::new(page_mut.memory(), K::fixed_width(), V::fixed_width());
-                    let offset = new_page_accessor.offset_of_value(position).unwrap();
-                    drop(new_page_accessor);
-                    let guard = AccessGuardMut::new(page_mut, offset, value.len());
-                    return Ok(InsertionResult {
-                        new_root: page_number,
-                        root_checksum: DEFERRED,
-                        additional_sibling: None,
-                        inserted_value: guard,
-                        old_value: existing_value,
-                    });
-                }
-
-                let mut builder = LeafBuilder::new(
-                    &self.mem,
-                    accessor.num_pairs() + 1,
-                    K::fixed_width(),
-                    V::fixed_width(),
-                );
-                for i in 0..accessor.num_pairs() {
-                    if i == position {
-                        builder.push(key, value);
-                    }
-                    if !found || i != position {
-                        let entry = accessor.entry(i).unwrap();
-                        builder.push(entry.key(), entry.value());
-                    }
+                    *self.root = Some(self.finish_insert(
+                        result.new_root,
+                        result.root_checksum,
+                        result.additional_sibling,
+                        new_length,
+                    )?);
+                    Ok(result.old_value)
                 }
-                if accessor.num_pairs() == position {
-                    builder.push(key, value);
+                UpdateResult::Removed(result, old_value) => {
+                    *self.root = self.finish_delete(result, length - 1)?;
+                    Ok(Some(old_value))
                 }
-                if !builder.should_split() {
-                    let new_page = builder.build()?;
+            }
+        } else if let Some(value) = f(None)? {
+            let (old_value, _) = self.insert(key, &V::from_bytes(&value))?;
+            Ok(old_value)
+        } else {
+            Ok(None)
+        }
+    }
 
-                    let page_number = page.get_page_number();
-                    let existing_value = if found {
-                        let (start, end) = accessor.value_range(position).unwrap();
-                        if self.modify_uncommitted && self.mem.uncommitted(page_number) {
-                            let arc = page.to_arc();
-                            drop(page);
-                            self.mem.free(page_number);
-                            Some(AccessGuard::with_arc_page(arc, start..end))
-                        } else {
-                            self.freed.push(page_number);
-                            Some(AccessGuard::with_page(page, start..end))
-                        }
+    fn update_helper(
+        &mut self,
+        page: PageImpl,
+        checksum: Checksum,
+        key: &[u8],
+        f: impl FnOnce(Option<&[u8]>) -> Result<Option<Vec<u8>>>,
+    ) -> Result<UpdateResult<'a, V>> {
+        let node_mem = page.memory();
+        match node_mem[0] {
+            LEAF | LEAF_WITH_OVERFLOW => {
+                let accessor =
+                    LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
+                let (position, found) = accessor.position::<K>(key);
+                let new_value = if found {
+                    let (start, end) = accessor.value_range(position).unwrap();
+                    if accessor.is_overflow(position) {
+                        let reference = OverflowRef::from_le_bytes(&page.memory()[start..end]);
+                        let value_page = self.mem.get_page(reference.page)?;
+                        f(Some(&value_page.memory()[reference.value_range()]))?
                     } else {
-                        drop(page);
-                        self.conditional_free(page_number);
-                        None
-                    };
-
-                    let new_page_number = new_page.get_page_number();
-                    let accessor =
-                        LeafAccessor::new(new_page.memory(), K::fixed_width(), V::fixed_width());
-                    let offset = accessor.offset_of_value(position).unwrap();
-                    let guard = AccessGuardMut::new(new_page, offset, value.len());
-
-                    InsertionResult {
-                        new_root: new_page_number,
-                        root_checksum: DEFERRED,
-                        additional_sibling: None,
-                        inserted_value: guard,
-                        old_value: existing_value,
+                        f(Some(&page.memory()[start..end]))?
                     }
                 } else {
-                    let (new_page1, split_key, new_page2) = builder.build_split()?;
-                    let split_key = split_key.to_vec();
-                    let page_number = page.get_page_number();
-                    let existing_value = if found {
-                        let (start, end) = accessor.value_range(position).unwrap();
-                        if self.modify_uncommitted && self.mem.uncommitted(page_number) {
-                            let arc = page.to_arc();
-                            drop(page);
-                            self.mem.free(page_number);
-                            Some(AccessGuard::with_arc_page(arc, start..end))
-                        } else {
-                            self.freed.push(page_number);
-                            Some(AccessGuard::with_page(page, start..end))
-                        }
-                    } else {
-                        drop(page);
-                        self.conditional_free(page_number);
-                        None
-                    };
+                    f(None)?
+                };
+                drop(accessor);
 
-                    let new_page_number = new_page1.get_page_number();
-                    let new_page_number2 = new_page2.get_page_number();
-                    let accessor =
-                        LeafAccessor::new(new_page1.memory(), K::fixed_width(), V::fixed_width());
-                    let division = accessor.num_pairs();
-                    let guard = if position < division {
-                        let accessor = LeafAccessor::new(
-                            new_page1.memory(),
-                            K::fixed_width(),
-                            V::fixed_width(),
-                        );
-                        let offset = accessor.offset_of_value(position).unwrap();
-                        AccessGuardMut::new(new_page1, offset, value.len())
+                if let Some(value) = new_value {
+                    let result = if should_overflow(&self.mem, self.fixed_value_size, value.len()) {
+                        let (_, reference) = write_overflow_page(&self.mem, &value)?;
+                        self.insert_leaf_helper(
+                            page,
+                            checksum,
+                            key,
+                            &reference.to_le_bytes(),
+                            true,
+                        )?
                     } else {
-                        let accessor = LeafAccessor::new(
-                            new_page2.memory(),
-                            K::fixed_width(),
-                            V::fixed_width(),
-                        );
-                        let offset = accessor.offset_of_value(position - division).unwrap();
-                        AccessGuardMut::new(new_page2, offset, value.len())
+                        self.insert_leaf_helper(page, checksum, key, &value, false)?
                     };
-
-                    InsertionResult {
-                        new_root: new_page_number,
-                        root_checksum: DEFERRED,
-                        additional_sibling: Some((split_key, new_page_number2, DEFERRED)),
-                        inserted_value: guard,
-                        old_value: existing_value,
+                    Ok(UpdateResult::Inserted(result))
+                } else if found {
+                    let (result, old_value) = self.delete_leaf_helper(page, checksum, key)?;
+                    Ok(UpdateResult::Removed(result, old_value.unwrap()))
+                } else {
+                    Ok(UpdateResult::Unchanged)
+                }
+            }
+            BRANCH => {
+                let accessor = BranchAccessor::new(&page, K::fixed_width());
+                let (child_index, child_page) = accessor.child_for_key::<K>(key);
+                let child_checksum = accessor.child_checksum(child_index).unwrap();
+                drop(accessor);
+                let child = self.mem.get_page(child_page)?;
+                match self.update_helper(child, child_checksum, key, f)? {
+                    UpdateResult::Unchanged => Ok(UpdateResult::Unchanged),
+                    UpdateResult::Inserted(sub_result) => Ok(UpdateResult::Inserted(
+                        self.insert_branch_helper(page, child_index, sub_result)?,
+                    )),
+                    UpdateResult::Removed(sub_result, old_value) => {
+                        let (result, old_value) = self.finish_delete_branch(
+                            page,
+                            child_index,
+                            sub_result,
+                            Some(old_value),
+                        )?;
+                        Ok(UpdateResult::Removed(result, old_value.unwrap()))
                     }
                 }
             }
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns the root of the tree, after an insertion which replaced the root with `new_root` and
+    // possibly a sibling
+    fn finish_insert(
+        &self,
+        new_root: PageNumber,
+        root_checksum: Checksum,
+        additional_sibling: Option<(Vec<u8>, PageNumber, Checksum)>,
+        new_length: u64,
+    ) -> Result<BtreeHeader> {
+        if let Some((key, page2, page2_checksum)) = additional_sibling {
+            let mut builder = BranchBuilder::new(&self.mem, 2, K::fixed_width());
+            builder.push_child(new_root, root_checksum);
+            builder.push_key(&key);
+            builder.push_child(page2, page2_checksum);
+            let new_page = builder.build()?;
+            Ok(BtreeHeader::new(
+                new_page.get_page_number(),
+                DEFERRED,
+                new_length,
+            ))
+        } else {
+            Ok(BtreeHeader::new(new_root, root_checksum, new_length))
+        }
+    }
+
+    fn insert_helper(
+        &mut self,
+        page: PageImpl,
+        page_checksum: Checksum,
+        key: &[u8],
+        value: &[u8],
+        overflow: bool,
+    ) -> Result<InsertionResult<'a, V>> {
+        let node_mem = page.memory();
+        match node_mem[0] {
+            LEAF | LEAF_WITH_OVERFLOW => {
+                self.insert_leaf_helper(page, page_checksum, key, value, overflow)
+            }
             BRANCH => {
                 let accessor = BranchAccessor::new(&page, K::fixed_width());
                 let (child_index, child_page) = accessor.child_for_key::<K>(key);
                 let child_checksum = accessor.child_checksum(child_index).unwrap();
-                let sub_result =
-                    self.insert_helper(self.mem.get_page(child_page)?, child_checksum, key, value)?;
-
-                if sub_result.additional_sibling.is_none()
-                    && self.modify_uncommitted
-                    && self.mem.uncommitted(page.get_page_number())
-                {
-                    let page_number = page.get_page_number();
-                    drop(page);
-                    let mut mutpage = self.mem.get_page_mut(page_number)?;
-                    let mut mutator = BranchMutator::new(&mut mutpage);
-                    mutator.write_child_page(
-                        child_index,
-                        sub_result.new_root,
-                        sub_result.root_checksum,
-                    );
-                    return Ok(InsertionResult {
-                        new_root: mutpage.get_page_number(),
-                        root_checksum: DEFERRED,
-                        additional_sibling: None,
-                        inserted_value: sub_result.inserted_value,
-                        old_value: sub_result.old_value,
-                    });
+                let sub_result = self.insert_helper(
+                    self.mem.get_page(child_page)?,
+                    child_checksum,
+                    key,
+                    value,
+                    overflow,
+                )?;
+                self.insert_branch_helper(page, child_index, sub_result)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn insert_leaf_helper(
+        &mut self,
+        page: PageImpl,
+        page_checksum: Checksum,
+        key: &[u8],
+        value: &[u8],
+        overflow: bool,
+    ) -> Result<InsertionResult<'a, V>> {
+        let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
+        let (position, found) = accessor.position::<K>(key);
+
+        // Fast-path to avoid re-building and splitting pages with a single large value
+        let single_large_value =
+            accessor.num_pairs() == 1 && accessor.total_length() >= self.mem.get_page_size();
+        if !found && single_large_value {
+            let mut builder =
+                LeafBuilder::new(&self.mem, 1, K::fixed_width(), self.fixed_value_size);
+            if overflow {
+                builder.push_overflow(key, value);
+            } else {
+                builder.push(key, value);
+            }
+            let new_page = builder.build()?;
+            let new_page_number = new_page.get_page_number();
+            let new_page_accessor =
+                LeafAccessor::new(new_page.memory(), K::fixed_width(), self.fixed_value_size);
+            let offset = new_page_accessor.offset_of_first_value();
+            drop(new_page_accessor);
+            let guard = AccessGuardMut::new(new_page, offset, value.len());
+            return if position == 0 {
+                Ok(InsertionResult {
+                    new_root: new_page_number,
+                    root_checksum: DEFERRED,
+                    additional_sibling: Some((key.to_vec(), page.get_page_number(), page_checksum)),
+                    inserted_value: guard,
+                    old_value: None,
+                })
+            } else {
+                let split_key = accessor.last_entry().key().to_vec();
+                Ok(InsertionResult {
+                    new_root: page.get_page_number(),
+                    root_checksum: page_checksum,
+                    additional_sibling: Some((split_key, new_page_number, DEFERRED)),
+                    inserted_value: guard,
+                    old_value: None,
+                })
+            };
+        }
+
+        // Fast-path for uncommitted pages, that can be modified in-place
+        if !overflow
+            && self.mem.uncommitted(page.get_page_number())
+            && self.modify_uncommitted
+            && LeafMutator::sufficient_insert_inplace_space(
+                &page,
+                position,
+                found,
+                K::fixed_width(),
+                self.fixed_value_size,
+                key,
+                value,
+            )
+        {
+            let page_number = page.get_page_number();
+            let existing_value = if found {
+                let copied_value = accessor.entry(position).unwrap().value().to_vec();
+                Some(AccessGuard::with_owned_value(copied_value))
+            } else {
+                None
+            };
+            drop(page);
+            let mut page_mut = self.mem.get_page_mut(page_number)?;
+            let mut mutator =
+                LeafMutator::new(&mut page_mut, K::fixed_width(), self.fixed_value_size);
+            mutator.insert(position, found, key, value);
+            let new_page_accessor =
+                LeafAccessor::new(page_mut.memory(), K::fixed_width(), self.fixed_value_size);
+            let offset = new_page_accessor.offset_of_value(position).unwrap();
+            drop(new_page_accessor);
+            let guard = AccessGuardMut::new(page_mut, offset, value.len());
+            return Ok(InsertionResult {
+                new_root: page_number,
+                root_checksum: DEFERRED,
+                additional_sibling: None,
+                inserted_value: guard,
+                old_value: existing_value,
+            });
+        }
+
+        let mut builder = LeafBuilder::new(
+            &self.mem,
+            accessor.num_pairs() + 1,
+            K::fixed_width(),
+            self.fixed_value_size,
+        );
+        for i in 0..accessor.num_pairs() {
+            if i == position {
+                if overflow {
+                    builder.push_overflow(key, value);
+                } else {
+                    builder.push(key, value);
                 }
+            }
+            if !found || i != position {
+                builder.push_entry(&accessor, i);
+            }
+        }
+        if accessor.num_pairs() == position {
+            if overflow {
+                builder.push_overflow(key, value);
+            } else {
+                builder.push(key, value);
+            }
+        }
+        Ok(if !builder.should_split() {
+            let new_page = builder.build()?;
+            let existing_value = self.replaced_value(page, position, found)?;
+
+            let new_page_number = new_page.get_page_number();
+            let accessor =
+                LeafAccessor::new(new_page.memory(), K::fixed_width(), self.fixed_value_size);
+            let offset = accessor.offset_of_value(position).unwrap();
+            let guard = AccessGuardMut::new(new_page, offset, value.len());
+
+            InsertionResult {
+                new_root: new_page_number,
+                root_checksum: DEFERRED,
+                additional_sibling: None,
+                inserted_value: guard,
+                old_value: existing_value,
+            }
+        } else {
+            let (new_page1, split_key, new_page2) = builder.build_split()?;
+            let split_key = split_key.to_vec();
+            let existing_value = self.replaced_value(page, position, found)?;
+
+            let new_page_number = new_page1.get_page_number();
+            let new_page_number2 = new_page2.get_page_number();
+            let accessor =
+                LeafAccessor::new(new_page1.memory(), K::fixed_width(), self.fixed_value_size);
+            let division = accessor.num_pairs();
+            let guard = if position < division {
+                let accessor =
+                    LeafAccessor::new(new_page1.memory(), K::fixed_width(), self.fixed_value_size);
+                let offset = accessor.offset_of_value(position).unwrap();
+                AccessGuardMut::new(new_page1, offset, value.len())
+            } else {
+                let accessor =
+                    LeafAccessor::new(new_page2.memory(), K::fixed_width(), self.fixed_value_size);
+                let offset = accessor.offset_of_value(position - division).unwrap();
+                AccessGuardMut::new(new_page2, offset, value.len())
+            };
+
+            InsertionResult {
+                new_root: new_page_number,
+                root_checksum: DEFERRED,
+                additional_sibling: Some((split_key, new_page_number2, DEFERRED)),
+                inserted_value: guard,
+                old_value: existing_value,
+            }
+        })
+    }
+
+    // Replaces the child of the branch `page` at `child_index`, which was modified by an insertion
+    fn insert_branch_helper(
+        &mut self,
+        page: PageImpl,
+        child_index: usize,
+        sub_result: InsertionResult<'a, V>,
+    ) -> Result<InsertionResult<'a, V>> {
+        let accessor = BranchAccessor::new(&page, K::fixed_width());
 
-                // A child was added, or we couldn't use the fast-path above
-                let mut builder =
-                    BranchBuilder::new(&self.mem, accessor.count_children() + 1, K::fixed_width());
-                if child_index == 0 {
+        if sub_result.additional_sibling.is_none()
+            && self.modify_uncommitted
+            && self.mem.uncommitted(page.get_page_number())
+        {
+            let page_number = page.get_page_number();
+            drop(page);
+            let mut mutpage = self.mem.get_page_mut(page_number)?;
+            let mut mutator = BranchMutator::new(&mut mutpage);
+            mutator.write_child_page(child_index, sub_result.new_root, sub_result.root_checksum);
+            return Ok(InsertionResult {
+                new_root: mutpage.get_page_number(),
+                root_checksum: DEFERRED,
+                additional_sibling: None,
+                inserted_value: sub_result.inserted_value,
+                old_value: sub_result.old_value,
+            });
+        }
+
+        // A child was added, or we couldn't use the fast-path above
+        let mut builder =
+            BranchBuilder::new(&self.mem, accessor.count_children() + 1, K::fixed_width());
+        if child_index == 0 {
+            builder.push_child(sub_result.new_root, sub_result.root_checksum);
+            if let Some((ref index_key2, page2, page2_checksum)) = sub_result.additional_sibling {
+                builder.push_key(index_key2);
+                builder.push_child(page2, page2_checksum);
+            }
+        } else {
+            builder.push_child(
+                accessor.child_page(0).unwrap(),
+                accessor.child_checksum(0).unwrap(),
+            );
+        }
+        for i in 1..accessor.count_children() {
+            if let Some(key) = accessor.key(i - 1) {
+                builder.push_key(key);
+                if i == child_index {
                     builder.push_child(sub_result.new_root, sub_result.root_checksum);
                     if let Some((ref index_key2, page2, page2_checksum)) =
                         sub_result.additional_sibling
@@ -438,64 +648,93 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                     }
                 } else {
                     builder.push_child(
-                        accessor.child_page(0).unwrap(),
-                        accessor.child_checksum(0).unwrap(),
+                        accessor.child_page(i).unwrap(),
+                        accessor.child_checksum(i).unwrap(),
                     );
                 }
-                for i in 1..accessor.count_children() {
-                    if let Some(key) = accessor.key(i - 1) {
-                        builder.push_key(key);
-                        if i == child_index {
-                            builder.push_child(sub_result.new_root, sub_result.root_checksum);
-                            if let Some((ref index_key2, page2, page2_checksum)) =
-                                sub_result.additional_sibling
-                            {
-                                builder.push_key(index_key2);
-                                builder.push_child(page2, page2_checksum);
-                            }
-                        } else {
-                            builder.push_child(
-                                accessor.child_page(i).unwrap(),
-                                accessor.child_checksum(i).unwrap(),
-                            );
-                        }
-                    } else {
-                        unreachable!();
-                    }
-                }
-
-                let result = if builder.should_split() {
-                    let (new_page1, split_key, new_page2) = builder.build_split()?;
-                    InsertionResult {
-                        new_root: new_page1.get_page_number(),
-                        root_checksum: DEFERRED,
-                        additional_sibling: Some((
-                            split_key.to_vec(),
-                            new_page2.get_page_number(),
-                            DEFERRED,
-                        )),
-                        inserted_value: sub_result.inserted_value,
-                        old_value: sub_result.old_value,
-                    }
-                } else {
-                    let new_page = builder.build()?;
-                    InsertionResult {
-                        new_root: new_page.get_page_number(),
-                        root_checksum: DEFERRED,
-                        additional_sibling: None,
-                        inserted_value: sub_result.inserted_value,
-                        old_value: sub_result.old_value,
-                    }
-                };
-                // Free the original page, since we've replaced it
-                let page_number = page.get_page_number();
-                drop(page);
-                self.conditional_free(page_number);
+            } else {
+                unreachable!();
+            }
+        }
 
-                result
+        let result = if builder.should_split() {
+            let (new_page1, split_key, new_page2) = builder.build_split()?;
+            InsertionResult {
+                new_root: new_page1.get_page_number(),
+                root_checksum: DEFERRED,
+                additional_sibling: Some((
+                    split_key.to_vec(),
+                    new_page2.get_page_number(),
+                    DEFERRED,
+                )),
+                inserted_value: sub_result.inserted_value,
+                old_value: sub_result.old_value,
             }
-            _ => unreachable!(),
-        })
+        } else {
+            let new_page = builder.build()?;
+            InsertionResult {
+                new_root: new_page.get_page_number(),
+                root_checksum: DEFERRED,
+                additional_sibling: None,
+                inserted_value: sub_result.inserted_value,
+                old_value: sub_result.old_value,
+            }
+        };
+        // Free the original page, since we've replaced it
+        let page_number = page.get_page_number();
+        drop(page);
+        self.conditional_free(page_number);
+
+        Ok(result)
+    }
+
+    // Frees the given leaf, which has been replaced, and returns the value of its entry at
+    // `position` if that entry was overwritten
+    fn replaced_value(
+        &mut self,
+        page: PageImpl,
+        position: usize,
+        found: bool,
+    ) -> Result<Option<AccessGuard<'a, V>>> {
+        let page_number = page.get_page_number();
+        if !found {
+            drop(page);
+            self.conditional_free(page_number);
+            return Ok(None);
+        }
+        let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
+        let (start, end) = accessor.value_range(position).unwrap();
+        if accessor.is_overflow(position) {
+            let reference = OverflowRef::from_le_bytes(&page.memory()[start..end]);
+            drop(page);
+            self.conditional_free(page_number);
+            return Ok(Some(self.take_overflow_value(reference)?));
+        }
+        if self.modify_uncommitted && self.mem.uncommitted(page_number) {
+            let arc = page.to_arc();
+            drop(page);
+            self.mem.free(page_number);
+            Ok(Some(AccessGuard::with_arc_page(arc, start..end)))
+        } else {
+            self.freed.push(page_number);
+            Ok(Some(AccessGuard::with_page(page, start..end)))
+        }
+    }
+
+    // Returns a value which is being removed from the tree, and frees its overflow page
+    fn take_overflow_value(&mut self, reference: OverflowRef) -> Result<AccessGuard<'a, V>> {
+        let page = self.mem.get_page(reference.page)?;
+        if self.modify_uncommitted && self.mem.uncommitted(reference.page) {
+            let arc = page.to_arc();
+            drop(page);
+            self.mem.free(reference.page);
+            Ok(AccessGuard::with_arc_page(arc, reference.value_range()))
+        } else {
+            // Won't be freed until the end of the transaction, so returning the page
+            // in the AccessGuard below is still safe
+            self.freed.push(reference.page);
+            Ok(AccessGuard::with_page(page, reference.value_range()))
+        }
     }
 
     fn delete_leaf_helper(
@@ -504,7 +743,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
         checksum: Checksum,
         key: &[u8],
     ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
-        let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), V::fixed_width());
+        let accessor = LeafAccessor::new(page.memory(), K::fixed_width(), self.fixed_value_size);
         let (position, found) = accessor.position::<K>(key);
         if !found {
             return Ok((Subtree(page.get_page_number(), checksum), None));
@@ -515,12 +754,13 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
             accessor.num_pairs() - 1,
             new_kv_bytes,
             K::fixed_width(),
-            V::fixed_width(),
+            self.fixed_value_size,
         );
         let uncommitted = self.mem.uncommitted(page.get_page_number());
 
-        // Fast-path for dirty pages
+        // Fast-path for dirty pages. Leaves with overflow values are not modified in-place
         if uncommitted
+            && page.memory()[0] == LEAF
             && self.modify_uncommitted
             && new_required_bytes >= self.mem.get_page_size() / 2
             && accessor.num_pairs() > 1
@@ -536,6 +776,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 end - start,
                 position,
                 K::fixed_width(),
+                self.fixed_value_size,
             );
             return Ok((Subtree(page_number, DEFERRED), Some(guard)));
         }
@@ -554,19 +795,20 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 &self.mem,
                 accessor.num_pairs() - 1,
                 K::fixed_width(),
-                V::fixed_width(),
+                self.fixed_value_size,
             );
-            for i in 0..accessor.num_pairs() {
-                if i == position {
-                    continue;
-                }
-                let entry = accessor.entry(i).unwrap();
-                builder.push(entry.key(), entry.value());
-            }
+            builder.push_all_except(&accessor, Some(position));
             let new_page = builder.build()?;
             Subtree(new_page.get_page_number(), DEFERRED)
         };
         let (start, end) = accessor.value_range(position).unwrap();
+        if accessor.is_overflow(position) {
+            let reference = OverflowRef::from_le_bytes(&page.memory()[start..end]);
+            let page_number = page.get_page_number();
+            drop(page);
+            self.conditional_free(page_number);
+            return Ok((result, Some(self.take_overflow_value(reference)?)));
+        }
         drop(accessor);
         let guard = if uncommitted && self.modify_uncommitted {
             let page_number = page.get_page_number();
@@ -609,14 +851,26 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
         key: &[u8],
     ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
         let accessor = BranchAccessor::new(&page, K::fixed_width());
-        let original_page_number = page.get_page_number();
         let (child_index, child_page_number) = accessor.child_for_key::<K>(key);
         let child_checksum = accessor.child_checksum(child_index).unwrap();
         let (result, found) =
             self.delete_helper(self.mem.get_page(child_page_number)?, child_checksum, key)?;
         if found.is_none() {
-            return Ok((Subtree(original_page_number, checksum), None));
+            return Ok((Subtree(page.get_page_number(), checksum), None));
         }
+        self.finish_delete_branch(page, child_index, result, found)
+    }
+
+    // Replaces the child of the branch `page` at `child_index`, from which an entry was deleted
+    fn finish_delete_branch(
+        &mut self,
+        page: PageImpl,
+        child_index: usize,
+        result: DeletionResult,
+        found: Option<AccessGuard<'a, V>>,
+    ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
+        let accessor = BranchAccessor::new(&page, K::fixed_width());
+        let original_page_number = page.get_page_number();
         if let Subtree(new_child, new_child_checksum) = result {
             let result_page =
                 if self.mem.uncommitted(original_page_number) && self.modify_uncommitted {
@@ -675,7 +929,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 deleted_pair,
             } => {
                 let partial_child_accessor =
-                    LeafAccessor::new(&partial_child_page, K::fixed_width(), V::fixed_width());
+                    LeafAccessor::new(&partial_child_page, K::fixed_width(), self.fixed_value_size);
                 debug_assert!(partial_child_accessor.num_pairs() > 1);
 
                 let merge_with = if child_index == 0 { 1 } else { child_index - 1 };
@@ -683,8 +937,11 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                 let merge_with_page = self
                     .mem
                     .get_page(accessor.child_page(merge_with).unwrap())?;
-                let merge_with_accessor =
-                    LeafAccessor::new(merge_with_page.memory(), K::fixed_width(), V::fixed_width());
+                let merge_with_accessor = LeafAccessor::new(
+                    merge_with_page.memory(),
+                    K::fixed_width(),
+                    self.fixed_value_size,
+                );
 
                 let single_large_value = merge_with_accessor.num_pairs() == 1
                     && merge_with_accessor.total_length() >= self.mem.get_page_size();
@@ -694,7 +951,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                         &self.mem,
                         partial_child_accessor.num_pairs() - 1,
                         K::fixed_width(),
-                        V::fixed_width(),
+                        self.fixed_value_size,
                     );
                     child_builder.push_all_except(&partial_child_accessor, Some(deleted_pair));
                     let new_page = child_builder.build()?;
@@ -723,7 +980,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
                             partial_child_accessor.num_pairs() - 1
                                 + merge_with_accessor.num_pairs(),
                             K::fixed_width(),
-                            V::fixed_width(),
+                            self.fixed_value_size,
                         );
                         if child_index < merge_with {
                             child_builder
@@ -906,7 +1163,7 @@ impl<'a, 'b, K: Key, V: Value> MutateHelper<'a, 'b, K, V> {
     ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
         let node_mem = page.memory();
         match node_mem[0] {
-            LEAF => self.delete_leaf_helper(page, checksum, key),
+            LEAF | LEAF_WITH_OVERFLOW => self.delete_leaf_helper(page, checksum, key),
             BRANCH => self.delete_branch_helper(page, checksum, key),
             _ => unreachable!(),
         }
@@ -1,5 +1,6 @@
 mod btree;
 mod btree_base;
+mod btree_builder;
 mod btree_iters;
 mod btree_mutator;
 mod page_store;
@@ -9,16 +10,20 @@ mod table_tree_base;
 pub(crate) use btree::{
     btree_stats, Btree, BtreeMut, BtreeStats, PagePath, RawBtree, UntypedBtree, UntypedBtreeMut,
 };
-pub use btree_base::{AccessGuard, AccessGuardMut};
 pub(crate) use btree_base::{
-    BranchAccessor, BranchMutator, BtreeHeader, Checksum, LeafAccessor, LeafMutator,
-    RawLeafBuilder, BRANCH, DEFERRED, LEAF,
+    leaf_overflow_refs, BranchAccessor, BranchMutator, BtreeHeader, Checksum, LeafAccessor,
+    LeafMutator, RawLeafBuilder, BRANCH, DEFERRED, LEAF, LEAF_WITH_OVERFLOW, OVERFLOW,
 };
+pub use btree_base::{AccessGuard, AccessGuardMut};
+pub(crate) use btree_builder::SortedBtreeBuilder;
 pub(crate) use btree_iters::{AllPageNumbersBtreeIter, BtreeExtractIf, BtreeRangeIter};
 pub use page_store::{file_backend, InMemoryBackend, Savepoint};
 pub(crate) use page_store::{
-    CachePriority, Page, PageHint, PageNumber, SerializedSavepoint, TransactionalMemory,
-    FILE_FORMAT_VERSION2, MAX_PAIR_LENGTH, MAX_VALUE_LENGTH, PAGE_SIZE,
+    xxh3_checksum, CachePriority, ChecksumAlgorithm, Page, PageHint, PageNumber,
+    SerializedSavepoint, TransactionalMemory, FILE_FORMAT_VERSION3, MAX_PAIR_LENGTH,
+    MAX_VALUE_LENGTH, PAGE_SIZE,
 };
+#[cfg(feature = "encryption")]
+pub use page_store::{EncryptedBackend, EncryptionKey};
 pub(crate) use table_tree::{FreedPageList, FreedTableKey, TableTree, TableTreeMut};
 pub(crate) use table_tree_base::{InternalTableDefinition, TableType};
@@ -1,5 +1,6 @@
 use crate::tree_store::page_store::cached_file::WritablePage;
 use crate::tree_store::page_store::page_manager::MAX_MAX_PAGE_ORDER;
+use crate::{Key, TypeName, Value};
 use std::cmp::Ordering;
 #[cfg(debug_assertions)]
 use std::collections::HashMap;
@@ -169,6 +170,41 @@ impl Debug for PageNumber {
     }
 }
 
+impl Value for PageNumber {
+    type SelfType<'a> = PageNumber;
+    type AsBytes<'a> = [u8; PageNumber::serialized_size()];
+
+    fn fixed_width() -> Option<usize> {
+        Some(PageNumber::serialized_size())
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        PageNumber::from_le_bytes(data.try_into().unwrap())
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value.to_le_bytes()
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("redb::PageNumber")
+    }
+}
+
+impl Key for PageNumber {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        u64::from_le_bytes(data1.try_into().unwrap())
+            .cmp(&u64::from_le_bytes(data2.try_into().unwrap()))
+    }
+}
+
 pub(crate) trait Page {
     fn memory(&self) -> &[u8];
 
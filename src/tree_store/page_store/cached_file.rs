@@ -1,5 +1,5 @@
 use crate::tree_store::page_store::base::PageHint;
-use crate::tree_store::LEAF;
+use crate::tree_store::{LEAF, LEAF_WITH_OVERFLOW, OVERFLOW};
 use crate::{DatabaseError, Result, StorageBackend, StorageError};
 use std::collections::BTreeMap;
 use std::ops::{Index, IndexMut};
@@ -9,7 +9,7 @@ use std::sync::atomic::AtomicU64;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 
-// Leaf pages are cached with low priority. Everything is cached with high priority
+// Leaf and overflow pages are cached with low priority. Everything else is cached with high priority
 #[derive(Clone, Copy)]
 pub(crate) enum CachePriority {
     High,
@@ -18,7 +18,7 @@ pub(crate) enum CachePriority {
 
 impl CachePriority {
     pub(crate) fn default_btree(data: &[u8]) -> CachePriority {
-        if data[0] == LEAF {
+        if matches!(data[0], LEAF | LEAF_WITH_OVERFLOW | OVERFLOW) {
             CachePriority::Low
         } else {
             CachePriority::High
@@ -0,0 +1,1031 @@
+use crate::tree_store::page_store::header::{
+    DB_HEADER_SIZE, TRANSACTION_0_OFFSET, TRANSACTION_1_OFFSET,
+};
+use crate::{DatabaseError, StorageBackend, StorageError};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Key, Tag, XChaCha20Poly1305, XNonce};
+use std::cmp::{max, min};
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const MAGIC: [u8; 8] = *b"redb-enc";
+const FORMAT_VERSION: u8 = 1;
+
+// Functions used to derive the key encryption key
+const KDF_NONE: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+
+const KEY_SIZE: usize = 32;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+const GENERATION_SIZE: usize = 4;
+const WRAPPED_KEY_SIZE: usize = NONCE_SIZE + KEY_SIZE + TAG_SIZE;
+const KEY_SLOT_SIZE: usize = GENERATION_SIZE + WRAPPED_KEY_SIZE;
+
+// Header layout. The wrapped keys are authenticated together with everything before them, so
+// tampering with the KDF parameters is detected in the same way as an incorrect key
+const VERSION_OFFSET: usize = MAGIC.len();
+const KDF_OFFSET: usize = VERSION_OFFSET + 1;
+const KDF_PARAMS_OFFSET: usize = KDF_OFFSET + 3;
+const SALT_OFFSET: usize = KDF_PARAMS_OFFSET + 3 * 4;
+const KDF_END: usize = SALT_OFFSET + SALT_SIZE;
+const CURRENT_KEY_OFFSET: usize = KDF_END;
+const PREVIOUS_KEY_OFFSET: usize = CURRENT_KEY_OFFSET + KEY_SLOT_SIZE;
+const HEADER_LEN: usize = PREVIOUS_KEY_OFFSET + KEY_SLOT_SIZE;
+// Space reserved for the header at the start of the storage
+const RESERVED_SIZE: u64 = 4096;
+
+// Blocks are stored at 4KiB aligned offsets. The generation of the key each block was encrypted
+// with, its nonce, and its authentication tag are stored separately, in a metadata block at the
+// start of each group of blocks
+const BLOCK_SIZE: usize = 4096;
+const METADATA_SIZE: usize = GENERATION_SIZE + NONCE_SIZE + TAG_SIZE;
+const GROUP_BLOCKS: usize = BLOCK_SIZE / METADATA_SIZE;
+// Number of blocks which are processed at once, when extending or rewriting the storage
+const BATCH_BLOCKS: u64 = 256;
+
+// The first block holds the database header, and is sealed as separate records, so that the two
+// commit slots can be written independently of each other, as they are in an unencrypted file.
+// Their metadata is stored in the reserved space after the header, with two entries for each
+// record. A record's new metadata is made durable in the entry which isn't in use, before its
+// ciphertext is written, so that a record which is small enough to be written atomically can be
+// read whether or not the write of its ciphertext completed
+const HEADER_RECORDS: [(usize, usize); 4] = [
+    (0, TRANSACTION_0_OFFSET),
+    (TRANSACTION_0_OFFSET, TRANSACTION_1_OFFSET),
+    (TRANSACTION_1_OFFSET, DB_HEADER_SIZE),
+    (DB_HEADER_SIZE, BLOCK_SIZE),
+];
+const RECORD_METADATA_OFFSET: usize = 512;
+const RECORD_METADATA_LEN: usize = 2 * HEADER_RECORDS.len() * METADATA_SIZE;
+
+/// The secret used to encrypt an [`EncryptedBackend`]
+#[derive(Clone, Copy)]
+pub enum EncryptionKey<'a> {
+    /// A password, from which a key is derived using Argon2id
+    Password(&'a [u8]),
+    /// A 256-bit key, which is used directly
+    Raw(&'a [u8; KEY_SIZE]),
+}
+
+impl<'a> EncryptionKey<'a> {
+    fn as_bytes(&self) -> &'a [u8] {
+        match self {
+            EncryptionKey::Password(password) => password,
+            EncryptionKey::Raw(key) => key.as_slice(),
+        }
+    }
+}
+
+fn random<const N: usize>() -> io::Result<[u8; N]> {
+    let mut result = [0; N];
+    getrandom::getrandom(&mut result)?;
+    Ok(result)
+}
+
+fn authentication_failed(index: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Encrypted block {index} failed authentication"),
+    )
+}
+
+fn to_usize(x: u64) -> usize {
+    usize::try_from(x).unwrap()
+}
+
+fn group_offset(block: u64) -> u64 {
+    let group_len = u64::try_from((GROUP_BLOCKS + 1) * BLOCK_SIZE).unwrap();
+    RESERVED_SIZE + block / u64::try_from(GROUP_BLOCKS).unwrap() * group_len
+}
+
+fn group_index(block: u64) -> u64 {
+    block % u64::try_from(GROUP_BLOCKS).unwrap()
+}
+
+fn block_offset(block: u64) -> u64 {
+    let block_size = u64::try_from(BLOCK_SIZE).unwrap();
+    group_offset(block) + (group_index(block) + 1) * block_size
+}
+
+fn metadata_offset(block: u64) -> u64 {
+    group_offset(block) + group_index(block) * u64::try_from(METADATA_SIZE).unwrap()
+}
+
+fn record_metadata_offset(record: usize, entry: usize) -> u64 {
+    u64::try_from(RECORD_METADATA_OFFSET + (2 * record + entry) * METADATA_SIZE).unwrap()
+}
+
+// Length of the storage which holds the given number of blocks
+fn physical_len(blocks: u64) -> u64 {
+    if blocks == 0 {
+        RESERVED_SIZE
+    } else {
+        block_offset(blocks - 1) + u64::try_from(BLOCK_SIZE).unwrap()
+    }
+}
+
+// The header records which hold a commit slot. One which fails authentication is read as zeros,
+// which the database detects as a corrupted slot, so that it recovers from the other slot
+fn is_commit_slot(record: usize) -> bool {
+    matches!(record, 1 | 2)
+}
+
+// Parameters of the function which derives the key encryption key from an EncryptionKey
+struct Kdf {
+    algorithm: u8,
+    params: [u32; 3],
+    salt: [u8; SALT_SIZE],
+}
+
+impl Kdf {
+    fn new(key: EncryptionKey) -> io::Result<Self> {
+        let (algorithm, params) = match key {
+            EncryptionKey::Password(_) => (
+                KDF_ARGON2ID,
+                [
+                    Params::DEFAULT_M_COST,
+                    Params::DEFAULT_T_COST,
+                    Params::DEFAULT_P_COST,
+                ],
+            ),
+            EncryptionKey::Raw(_) => (KDF_NONE, [0; 3]),
+        };
+        Ok(Self {
+            algorithm,
+            params,
+            salt: random()?,
+        })
+    }
+
+    fn from_bytes(data: &[u8]) -> Self {
+        let mut params = [0; 3];
+        for (i, param) in params.iter_mut().enumerate() {
+            let offset = KDF_PARAMS_OFFSET + 4 * i;
+            *param = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        }
+        Self {
+            algorithm: data[KDF_OFFSET],
+            params,
+            salt: data[SALT_OFFSET..KDF_END].try_into().unwrap(),
+        }
+    }
+
+    // Serializes the start of the header, up to the key slots
+    fn to_bytes(&self) -> [u8; KDF_END] {
+        let mut result = [0; KDF_END];
+        result[..VERSION_OFFSET].copy_from_slice(&MAGIC);
+        result[VERSION_OFFSET] = FORMAT_VERSION;
+        result[KDF_OFFSET] = self.algorithm;
+        for (i, param) in self.params.iter().enumerate() {
+            let offset = KDF_PARAMS_OFFSET + 4 * i;
+            result[offset..offset + 4].copy_from_slice(&param.to_le_bytes());
+        }
+        result[SALT_OFFSET..KDF_END].copy_from_slice(&self.salt);
+        result
+    }
+
+    fn derive(&self, key: EncryptionKey) -> Result<XChaCha20Poly1305, DatabaseError> {
+        match self.algorithm {
+            KDF_NONE => XChaCha20Poly1305::new_from_slice(key.as_bytes())
+                .map_err(|_| DatabaseError::InvalidEncryptionKey),
+            KDF_ARGON2ID => {
+                let [m_cost, t_cost, p_cost] = self.params;
+                let params = Params::new(m_cost, t_cost, p_cost, Some(KEY_SIZE)).map_err(|_| {
+                    StorageError::Corrupted("Invalid Argon2 parameters in encryption header".into())
+                })?;
+                let mut result = [0; KEY_SIZE];
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(key.as_bytes(), &self.salt, &mut result)
+                    .map_err(|_| DatabaseError::InvalidEncryptionKey)?;
+                Ok(XChaCha20Poly1305::new(Key::from_slice(&result)))
+            }
+            x => {
+                Err(StorageError::Corrupted(format!("Unknown key derivation function: {x}")).into())
+            }
+        }
+    }
+}
+
+// A data key, encrypted with the key encryption key. Successfully decrypting it is the check that
+// the correct key was supplied
+struct KeySlot {
+    generation: u32,
+    wrapped: [u8; WRAPPED_KEY_SIZE],
+}
+
+impl KeySlot {
+    fn wrap(
+        kek: &XChaCha20Poly1305,
+        kdf: &Kdf,
+        generation: u32,
+        key: &[u8; KEY_SIZE],
+    ) -> io::Result<Self> {
+        let mut wrapped = [0; WRAPPED_KEY_SIZE];
+        let (nonce, rest) = wrapped.split_at_mut(NONCE_SIZE);
+        let (ciphertext, tag) = rest.split_at_mut(KEY_SIZE);
+        nonce.copy_from_slice(&random::<NONCE_SIZE>()?);
+        ciphertext.copy_from_slice(key);
+        let computed = kek
+            .encrypt_in_place_detached(
+                XNonce::from_slice(nonce),
+                &Self::associated_data(kdf, generation),
+                ciphertext,
+            )
+            .unwrap();
+        tag.copy_from_slice(&computed);
+
+        Ok(Self {
+            generation,
+            wrapped,
+        })
+    }
+
+    fn unwrap(&self, kek: &XChaCha20Poly1305, kdf: &Kdf) -> Result<DataKey, DatabaseError> {
+        let (nonce, rest) = self.wrapped.split_at(NONCE_SIZE);
+        let (ciphertext, tag) = rest.split_at(KEY_SIZE);
+        let mut key: [u8; KEY_SIZE] = ciphertext.try_into().unwrap();
+        kek.decrypt_in_place_detached(
+            XNonce::from_slice(nonce),
+            &Self::associated_data(kdf, self.generation),
+            &mut key,
+            Tag::from_slice(tag),
+        )
+        .map_err(|_| DatabaseError::InvalidEncryptionKey)?;
+
+        Ok(DataKey::new(self.generation, key))
+    }
+
+    fn associated_data(kdf: &Kdf, generation: u32) -> Vec<u8> {
+        let mut result = kdf.to_bytes().to_vec();
+        result.extend_from_slice(&generation.to_le_bytes());
+        result
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let generation = u32::from_le_bytes(data[..GENERATION_SIZE].try_into().unwrap());
+        if generation == 0 {
+            return None;
+        }
+        Some(Self {
+            generation,
+            wrapped: data[GENERATION_SIZE..KEY_SLOT_SIZE].try_into().unwrap(),
+        })
+    }
+
+    fn to_bytes(&self) -> [u8; KEY_SLOT_SIZE] {
+        let mut result = [0; KEY_SLOT_SIZE];
+        result[..GENERATION_SIZE].copy_from_slice(&self.generation.to_le_bytes());
+        result[GENERATION_SIZE..].copy_from_slice(&self.wrapped);
+        result
+    }
+}
+
+// Stored unencrypted at the start of the storage.
+//
+// During key rotation the header holds both the new key and the previous one, so that blocks which
+// haven't been rewritten yet can still be read if the rotation is interrupted
+struct Header {
+    kdf: Kdf,
+    current: KeySlot,
+    previous: Option<KeySlot>,
+}
+
+impl Header {
+    fn from_bytes(data: &[u8]) -> Result<Self, DatabaseError> {
+        if data[..VERSION_OFFSET] != MAGIC {
+            return Err(StorageError::Corrupted(
+                "Storage does not contain an encrypted database".to_string(),
+            )
+            .into());
+        }
+        if data[VERSION_OFFSET] != FORMAT_VERSION {
+            return Err(StorageError::Corrupted(format!(
+                "Unknown encryption format version: {}",
+                data[VERSION_OFFSET]
+            ))
+            .into());
+        }
+        let current = KeySlot::from_bytes(&data[CURRENT_KEY_OFFSET..]).ok_or_else(|| {
+            StorageError::Corrupted("Encryption header has no current key".to_string())
+        })?;
+
+        Ok(Self {
+            kdf: Kdf::from_bytes(data),
+            current,
+            previous: KeySlot::from_bytes(&data[PREVIOUS_KEY_OFFSET..]),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0; HEADER_LEN];
+        result[..KDF_END].copy_from_slice(&self.kdf.to_bytes());
+        result[CURRENT_KEY_OFFSET..PREVIOUS_KEY_OFFSET].copy_from_slice(&self.current.to_bytes());
+        if let Some(ref previous) = self.previous {
+            result[PREVIOUS_KEY_OFFSET..].copy_from_slice(&previous.to_bytes());
+        }
+        result
+    }
+}
+
+// A key used to encrypt blocks. The generation is stored with each block, to identify which key
+// it was encrypted with
+struct DataKey {
+    generation: u32,
+    key: [u8; KEY_SIZE],
+    cipher: XChaCha20Poly1305,
+}
+
+impl DataKey {
+    fn new(generation: u32, key: [u8; KEY_SIZE]) -> Self {
+        Self {
+            generation,
+            key,
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key)),
+        }
+    }
+
+    // Blocks are authenticated together with their index, and the records of the first block with
+    // their position in it, so that they can't be moved
+    fn associated_data(&self, index: u64, record: usize) -> [u8; 13] {
+        let mut result = [0; 13];
+        result[..8].copy_from_slice(&index.to_le_bytes());
+        result[8] = record.try_into().unwrap();
+        result[9..].copy_from_slice(&self.generation.to_le_bytes());
+        result
+    }
+}
+
+struct Keys {
+    header: Header,
+    current: DataKey,
+    previous: Option<DataKey>,
+}
+
+impl Keys {
+    // Encrypts data in place, and stores the generation, nonce & tag in metadata
+    fn seal(&self, index: u64, record: usize, nonce: &[u8], data: &mut [u8], metadata: &mut [u8]) {
+        let key = &self.current;
+        let (generation, rest) = metadata.split_at_mut(GENERATION_SIZE);
+        let (stored_nonce, tag) = rest.split_at_mut(NONCE_SIZE);
+        generation.copy_from_slice(&key.generation.to_le_bytes());
+        stored_nonce.copy_from_slice(nonce);
+        let computed = key
+            .cipher
+            .encrypt_in_place_detached(
+                XNonce::from_slice(nonce),
+                &key.associated_data(index, record),
+                data,
+            )
+            .unwrap();
+        tag.copy_from_slice(&computed);
+    }
+
+    // Decrypts data in place. data is left unmodified if it fails authentication
+    fn open(&self, index: u64, record: usize, metadata: &[u8], data: &mut [u8]) -> io::Result<()> {
+        let (generation, rest) = metadata.split_at(GENERATION_SIZE);
+        let (nonce, tag) = rest.split_at(NONCE_SIZE);
+        let generation = u32::from_le_bytes(generation.try_into().unwrap());
+        let key = if generation == self.current.generation {
+            &self.current
+        } else {
+            match self.previous {
+                Some(ref previous) if previous.generation == generation => previous,
+                _ => return Err(authentication_failed(index)),
+            }
+        };
+        key.cipher
+            .decrypt_in_place_detached(
+                XNonce::from_slice(nonce),
+                &key.associated_data(index, record),
+                data,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| authentication_failed(index))
+    }
+}
+
+// The decrypted first block of the storage
+struct HeaderBlock {
+    plaintext: Vec<u8>,
+    // The metadata entry which authenticated each record, or None if neither did
+    entries: [Option<usize>; HEADER_RECORDS.len()],
+}
+
+impl HeaderBlock {
+    fn check_readable(&self, start: usize, end: usize) -> io::Result<()> {
+        for (record, &(record_start, record_end)) in HEADER_RECORDS.iter().enumerate() {
+            if self.entries[record].is_none()
+                && !is_commit_slot(record)
+                && start < record_end
+                && record_start < end
+            {
+                return Err(authentication_failed(0));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encrypts the database stored in another [`StorageBackend`]
+///
+/// The storage is divided into blocks of 4KiB, each of which is encrypted and authenticated with
+/// XChaCha20-Poly1305 under a random key which is generated when the storage is created. Reading a
+/// block which has been modified, or moved to a different offset, fails with an error. Replacing a
+/// block with an older version of itself is not detected by the encryption, but is detected by the
+/// database's own checksums when [`crate::Durability::Paranoid`] is used.
+///
+/// The data key is stored in a header at the start of the storage, encrypted with the key supplied
+/// to [`EncryptedBackend::new`], along with the salt and parameters used to derive a key from a
+/// password. Blocks keep their 4KiB alignment in the underlying storage, and the nonces and tags of
+/// every 93 blocks are stored in an additional block, so the storage is about 1% larger than the
+/// database. The two commit slots of the database header are sealed separately, so that if one of
+/// them is corrupted, the database can still be opened from the other.
+pub struct EncryptedBackend<B: StorageBackend> {
+    inner: B,
+    keys: RwLock<Keys>,
+}
+
+impl<B: StorageBackend> EncryptedBackend<B> {
+    /// Opens the encrypted database stored in `inner`, or initializes the encryption header if
+    /// `inner` is empty.
+    ///
+    /// Returns [`DatabaseError::InvalidEncryptionKey`] if `key` is not the key that the storage was
+    /// encrypted with.
+    pub fn new(inner: B, key: EncryptionKey) -> Result<Self, DatabaseError> {
+        #[allow(clippy::assertions_on_constants)]
+        {
+            assert!(HEADER_LEN <= RECORD_METADATA_OFFSET);
+            assert!(RECORD_METADATA_OFFSET + RECORD_METADATA_LEN <= to_usize(RESERVED_SIZE));
+        }
+
+        let len = inner.len()?;
+        // An all-zero header is left behind if creation was interrupted before it was written
+        let uninitialized = len == 0
+            || (len == RESERVED_SIZE && inner.read(0, HEADER_LEN)?.iter().all(|x| *x == 0));
+        let (header, kek) = if uninitialized {
+            let kdf = Kdf::new(key)?;
+            let kek = kdf.derive(key)?;
+            let current = KeySlot::wrap(&kek, &kdf, 1, &random()?)?;
+            let header = Header {
+                kdf,
+                current,
+                previous: None,
+            };
+            inner.set_len(RESERVED_SIZE)?;
+            inner.write(0, &header.to_bytes())?;
+            inner.sync_data(false)?;
+            (header, kek)
+        } else if len < RESERVED_SIZE {
+            return Err(StorageError::Corrupted(
+                "Storage does not contain an encrypted database".to_string(),
+            )
+            .into());
+        } else {
+            let header = Header::from_bytes(&inner.read(0, HEADER_LEN)?)?;
+            let kek = header.kdf.derive(key)?;
+            (header, kek)
+        };
+
+        let current = header.current.unwrap(&kek, &header.kdf)?;
+        let previous = header
+            .previous
+            .as_ref()
+            .map(|slot| slot.unwrap(&kek, &header.kdf))
+            .transpose()?;
+
+        Ok(Self {
+            inner,
+            keys: RwLock::new(Keys {
+                header,
+                current,
+                previous,
+            }),
+        })
+    }
+
+    /// Replaces the key that the storage is encrypted with, and re-encrypts every block with a new
+    /// data key.
+    ///
+    /// If the rotation is interrupted, the storage must be opened with `new_key`, and the rotation
+    /// should be repeated.
+    pub fn rotate_key(&self, new_key: EncryptionKey) -> Result<(), DatabaseError> {
+        let mut keys = self.write_keys();
+
+        // Finish an earlier rotation which was interrupted, since the header only has room for
+        // one previous key
+        if keys.previous.is_some() {
+            self.rewrite_blocks(&keys)?;
+            keys.header.previous = None;
+            keys.previous = None;
+            self.write_header(&keys.header)?;
+        }
+
+        let generation = max(keys.current.generation.wrapping_add(1), 1);
+        let data_key = random()?;
+        let kdf = Kdf::new(new_key)?;
+        let kek = kdf.derive(new_key)?;
+        let current = KeySlot::wrap(&kek, &kdf, generation, &data_key)?;
+        let previous = KeySlot::wrap(&kek, &kdf, keys.current.generation, &keys.current.key)?;
+        keys.header = Header {
+            kdf,
+            current,
+            previous: Some(previous),
+        };
+        self.write_header(&keys.header)?;
+        let previous = std::mem::replace(&mut keys.current, DataKey::new(generation, data_key));
+        keys.previous = Some(previous);
+
+        self.rewrite_blocks(&keys)?;
+        keys.header.previous = None;
+        keys.previous = None;
+        self.write_header(&keys.header)?;
+
+        Ok(())
+    }
+
+    fn read_keys(&self) -> RwLockReadGuard<'_, Keys> {
+        self.keys.read().expect("Could not acquire read lock.")
+    }
+
+    fn write_keys(&self) -> RwLockWriteGuard<'_, Keys> {
+        self.keys.write().expect("Could not acquire write lock.")
+    }
+
+    fn write_header(&self, header: &Header) -> io::Result<()> {
+        // Blocks must be durable before the header stops referencing the key they were written with
+        self.inner.sync_data(false)?;
+        self.inner.write(0, &header.to_bytes())?;
+        self.inner.sync_data(false)
+    }
+
+    fn blocks(&self) -> io::Result<u64> {
+        let block_size = u64::try_from(BLOCK_SIZE).unwrap();
+        let group_len = u64::try_from(GROUP_BLOCKS + 1).unwrap() * block_size;
+        let len = self.inner.len()?.saturating_sub(RESERVED_SIZE);
+        // Each group begins with its metadata block
+        let partial_group = (len % group_len / block_size).saturating_sub(1);
+        Ok(len / group_len * u64::try_from(GROUP_BLOCKS).unwrap() + partial_group)
+    }
+
+    fn read_header_block(&self, keys: &Keys) -> io::Result<HeaderBlock> {
+        let physical = self.inner.read(block_offset(0), BLOCK_SIZE)?;
+        let metadata = self.inner.read(
+            u64::try_from(RECORD_METADATA_OFFSET).unwrap(),
+            RECORD_METADATA_LEN,
+        )?;
+        let mut result = HeaderBlock {
+            plaintext: vec![0; BLOCK_SIZE],
+            entries: [None; HEADER_RECORDS.len()],
+        };
+        for (record, &(start, end)) in HEADER_RECORDS.iter().enumerate() {
+            let output = &mut result.plaintext[start..end];
+            output.copy_from_slice(&physical[start..end]);
+            for entry in 0..2 {
+                let offset = (2 * record + entry) * METADATA_SIZE;
+                let entry_metadata = &metadata[offset..(offset + METADATA_SIZE)];
+                if keys.open(0, record, entry_metadata, output).is_ok() {
+                    result.entries[record] = Some(entry);
+                    break;
+                }
+            }
+            if result.entries[record].is_none() {
+                output.fill(0);
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Seals a record of the first block, using the given metadata entry
+    fn write_record(
+        &self,
+        keys: &Keys,
+        record: usize,
+        entry: usize,
+        plaintext: &[u8],
+    ) -> io::Result<()> {
+        let (start, _) = HEADER_RECORDS[record];
+        let mut ciphertext = plaintext.to_vec();
+        let mut metadata = [0; METADATA_SIZE];
+        keys.seal(
+            0,
+            record,
+            &random::<NONCE_SIZE>()?,
+            &mut ciphertext,
+            &mut metadata,
+        );
+        self.inner
+            .write(record_metadata_offset(record, entry), &metadata)?;
+        self.inner.sync_data(false)?;
+        self.inner
+            .write(block_offset(0) + u64::try_from(start).unwrap(), &ciphertext)
+    }
+
+    // Writes the records of the first block which overlap written, and whose contents changed
+    fn write_header_block(
+        &self,
+        keys: &Keys,
+        header: &HeaderBlock,
+        plaintext: &[u8],
+        written: (usize, usize),
+    ) -> io::Result<()> {
+        let overlaps = |(start, end): (usize, usize)| start < written.1 && written.0 < end;
+        // A record which failed authentication can only be replaced entirely
+        for (record, &(start, end)) in HEADER_RECORDS.iter().enumerate() {
+            if header.entries[record].is_none()
+                && overlaps((start, end))
+                && (start < written.0 || written.1 < end)
+            {
+                return Err(authentication_failed(0));
+            }
+        }
+        for (record, &(start, end)) in HEADER_RECORDS.iter().enumerate() {
+            if !overlaps((start, end)) {
+                continue;
+            }
+            let entry = match header.entries[record] {
+                Some(_) if header.plaintext[start..end] == plaintext[start..end] => continue,
+                Some(entry) => entry ^ 1,
+                None => 0,
+            };
+            self.write_record(keys, record, entry, &plaintext[start..end])?;
+        }
+
+        Ok(())
+    }
+
+    fn read_blocks(&self, keys: &Keys, first: u64, count: u64) -> io::Result<Vec<u8>> {
+        assert_ne!(first, 0);
+        let mut result = vec![0; to_usize(count) * BLOCK_SIZE];
+        let mut index = first;
+        let end = first + count;
+        while index < end {
+            // Blocks are contiguous up to the end of their group
+            let group_blocks = u64::try_from(GROUP_BLOCKS).unwrap();
+            let n = min(end, (index / group_blocks + 1) * group_blocks) - index;
+            let metadata = self
+                .inner
+                .read(metadata_offset(index), to_usize(n) * METADATA_SIZE)?;
+            let physical = self
+                .inner
+                .read(block_offset(index), to_usize(n) * BLOCK_SIZE)?;
+            let output_start = to_usize(index - first) * BLOCK_SIZE;
+            for (i, ((block, block_metadata), output)) in physical
+                .chunks(BLOCK_SIZE)
+                .zip(metadata.chunks(METADATA_SIZE))
+                .zip(result[output_start..].chunks_mut(BLOCK_SIZE))
+                .enumerate()
+            {
+                output.copy_from_slice(block);
+                keys.open(index + u64::try_from(i).unwrap(), 0, block_metadata, output)?;
+            }
+            index += n;
+        }
+
+        Ok(result)
+    }
+
+    // Encrypts the blocks in plaintext, each with a fresh nonce, and writes them starting at first
+    fn write_blocks(&self, keys: &Keys, first: u64, plaintext: &[u8]) -> io::Result<()> {
+        assert_ne!(first, 0);
+        let end = first + u64::try_from(plaintext.len() / BLOCK_SIZE).unwrap();
+        let mut index = first;
+        while index < end {
+            let group_blocks = u64::try_from(GROUP_BLOCKS).unwrap();
+            let n = to_usize(min(end, (index / group_blocks + 1) * group_blocks) - index);
+            let input_start = to_usize(index - first) * BLOCK_SIZE;
+            let mut physical = plaintext[input_start..(input_start + n * BLOCK_SIZE)].to_vec();
+            let mut metadata = vec![0; n * METADATA_SIZE];
+            let mut nonces = vec![0; n * NONCE_SIZE];
+            getrandom::getrandom(&mut nonces)?;
+            for (i, ((block, block_metadata), nonce)) in physical
+                .chunks_mut(BLOCK_SIZE)
+                .zip(metadata.chunks_mut(METADATA_SIZE))
+                .zip(nonces.chunks(NONCE_SIZE))
+                .enumerate()
+            {
+                keys.seal(
+                    index + u64::try_from(i).unwrap(),
+                    0,
+                    nonce,
+                    block,
+                    block_metadata,
+                );
+            }
+            self.inner.write(metadata_offset(index), &metadata)?;
+            self.inner.write(block_offset(index), &physical)?;
+            index += u64::try_from(n).unwrap();
+        }
+
+        Ok(())
+    }
+
+    fn rewrite_blocks(&self, keys: &Keys) -> io::Result<()> {
+        let blocks = self.blocks()?;
+        if blocks == 0 {
+            return Ok(());
+        }
+        // Each record is rewritten in turn, so that at most one of them is being written at once.
+        // Records which failed authentication are left as they are
+        let header = self.read_header_block(keys)?;
+        for (record, &(start, end)) in HEADER_RECORDS.iter().enumerate() {
+            if let Some(entry) = header.entries[record] {
+                self.write_record(keys, record, entry ^ 1, &header.plaintext[start..end])?;
+            }
+        }
+        let mut first = 1;
+        while first < blocks {
+            let count = min(BATCH_BLOCKS, blocks - first);
+            let plaintext = self.read_blocks(keys, first, count)?;
+            self.write_blocks(keys, first, &plaintext)?;
+            first += count;
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: StorageBackend> Debug for EncryptedBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedBackend")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B: StorageBackend> StorageBackend for EncryptedBackend<B> {
+    fn len(&self) -> Result<u64, io::Error> {
+        Ok(self.blocks()? * u64::try_from(BLOCK_SIZE).unwrap())
+    }
+
+    fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>, io::Error> {
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        let keys = self.read_keys();
+        let block_size = u64::try_from(BLOCK_SIZE).unwrap();
+        let first = offset / block_size;
+        let end = offset + u64::try_from(len).unwrap();
+        let last = (end + block_size - 1) / block_size;
+        let start = to_usize(offset - first * block_size);
+
+        let plaintext = if first == 0 {
+            let header = self.read_header_block(&keys)?;
+            header.check_readable(start, min(start + len, BLOCK_SIZE))?;
+            let mut plaintext = header.plaintext;
+            if last > 1 {
+                plaintext.extend_from_slice(&self.read_blocks(&keys, 1, last - 1)?);
+            }
+            plaintext
+        } else {
+            self.read_blocks(&keys, first, last - first)?
+        };
+
+        Ok(plaintext[start..start + len].to_vec())
+    }
+
+    fn set_len(&self, len: u64) -> Result<(), io::Error> {
+        let keys = self.write_keys();
+        let block_size = u64::try_from(BLOCK_SIZE).unwrap();
+        if len % block_size != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Length must be a multiple of {BLOCK_SIZE}"),
+            ));
+        }
+        let old_blocks = self.blocks()?;
+        let new_blocks = len / block_size;
+        self.inner.set_len(physical_len(new_blocks))?;
+
+        // Encrypt the new blocks immediately, so that every block in the storage is authenticated
+        let zeros = vec![0; to_usize(BATCH_BLOCKS) * BLOCK_SIZE];
+        let mut first = old_blocks;
+        if first == 0 && new_blocks > 0 {
+            for (record, &(start, end)) in HEADER_RECORDS.iter().enumerate() {
+                self.write_record(&keys, record, 0, &zeros[start..end])?;
+            }
+            first = 1;
+        }
+        while first < new_blocks {
+            let count = min(BATCH_BLOCKS, new_blocks - first);
+            self.write_blocks(&keys, first, &zeros[..to_usize(count) * BLOCK_SIZE])?;
+            first += count;
+        }
+
+        Ok(())
+    }
+
+    fn sync_data(&self, eventual: bool) -> Result<(), io::Error> {
+        self.inner.sync_data(eventual)
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let keys = self.write_keys();
+        let block_size = u64::try_from(BLOCK_SIZE).unwrap();
+        let first = offset / block_size;
+        let end = offset + u64::try_from(data.len()).unwrap();
+        let last = (end + block_size - 1) / block_size;
+        let start = to_usize(offset - first * block_size);
+
+        let mut plaintext = vec![0; to_usize(last - first) * BLOCK_SIZE];
+        // Blocks which are only partially overwritten must be read first. The first block of the
+        // storage is always read, so that only the records which changed are written
+        let header = if first == 0 {
+            Some(self.read_header_block(&keys)?)
+        } else {
+            None
+        };
+        if let Some(ref header) = header {
+            plaintext[..BLOCK_SIZE].copy_from_slice(&header.plaintext);
+        } else if start != 0 {
+            plaintext[..BLOCK_SIZE].copy_from_slice(&self.read_blocks(&keys, first, 1)?);
+        }
+        let first_read = header.is_some() || start != 0;
+        if end % block_size != 0 && (last - 1 != first || !first_read) {
+            let tail = plaintext.len() - BLOCK_SIZE;
+            plaintext[tail..].copy_from_slice(&self.read_blocks(&keys, last - 1, 1)?);
+        }
+        plaintext[start..start + data.len()].copy_from_slice(data);
+
+        if let Some(header) = header {
+            let written = (start, min(start + data.len(), BLOCK_SIZE));
+            self.write_header_block(&keys, &header, &plaintext[..BLOCK_SIZE], written)?;
+            if last > 1 {
+                self.write_blocks(&keys, 1, &plaintext[BLOCK_SIZE..])?;
+            }
+            Ok(())
+        } else {
+            self.write_blocks(&keys, first, &plaintext)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backends::InMemoryBackend;
+    use crate::tree_store::page_store::encrypted_backend::{
+        block_offset, physical_len, EncryptedBackend, EncryptionKey, BLOCK_SIZE, GROUP_BLOCKS,
+    };
+    use crate::tree_store::page_store::header::{
+        DB_HEADER_SIZE, GOD_BYTE_OFFSET, PRIMARY_BIT, TRANSACTION_0_OFFSET, TRANSACTION_1_OFFSET,
+    };
+    use crate::{Builder, StorageBackend, TableDefinition};
+    use std::io;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const KEY: EncryptionKey = EncryptionKey::Raw(&[7; 32]);
+    const X: TableDefinition<&str, &str> = TableDefinition::new("x");
+
+    // Shares its storage between backends, and can drop writes to the first block, to simulate a
+    // crash while they were in progress
+    #[derive(Debug, Default, Clone)]
+    struct SharedBackend {
+        inner: Arc<InMemoryBackend>,
+        drop_header_writes: Arc<AtomicBool>,
+    }
+
+    impl StorageBackend for SharedBackend {
+        fn len(&self) -> Result<u64, io::Error> {
+            self.inner.len()
+        }
+
+        fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>, io::Error> {
+            self.inner.read(offset, len)
+        }
+
+        fn set_len(&self, len: u64) -> Result<(), io::Error> {
+            self.inner.set_len(len)
+        }
+
+        fn sync_data(&self, eventual: bool) -> Result<(), io::Error> {
+            self.inner.sync_data(eventual)
+        }
+
+        fn write(&self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+            let header_block = block_offset(0)..(block_offset(0) + 4096);
+            if self.drop_header_writes.load(Ordering::SeqCst) && header_block.contains(&offset) {
+                return Ok(());
+            }
+            self.inner.write(offset, data)
+        }
+    }
+
+    #[test]
+    fn aligned_blocks() {
+        let storage = SharedBackend::default();
+        let backend = EncryptedBackend::new(storage.clone(), KEY).unwrap();
+        for blocks in [
+            0,
+            1,
+            2,
+            7,
+            GROUP_BLOCKS,
+            GROUP_BLOCKS + 1,
+            3 * GROUP_BLOCKS + 5,
+        ] {
+            let blocks = u64::try_from(blocks).unwrap();
+            let len = blocks * u64::try_from(BLOCK_SIZE).unwrap();
+            backend.set_len(len).unwrap();
+            assert_eq!(backend.len().unwrap(), len);
+            assert_eq!(storage.len().unwrap(), physical_len(blocks));
+            assert_eq!(storage.len().unwrap() % 4096, 0);
+        }
+
+        let data: Vec<u8> = (0..(3 * BLOCK_SIZE))
+            .map(|x| u8::try_from(x % 251).unwrap())
+            .collect();
+        let offset = u64::try_from((GROUP_BLOCKS - 1) * BLOCK_SIZE + 100).unwrap();
+        backend.write(offset, &data).unwrap();
+        backend.write(10, &data[..5000]).unwrap();
+        let backend = EncryptedBackend::new(storage, KEY).unwrap();
+        assert_eq!(backend.read(offset, data.len()).unwrap(), data);
+        assert_eq!(backend.read(10, 5000).unwrap(), &data[..5000]);
+    }
+
+    #[test]
+    fn corrupted_commit_slot() {
+        for corrupt_primary in [true, false] {
+            let storage = SharedBackend::default();
+            let db = Builder::new()
+                .create_with_backend(EncryptedBackend::new(storage.clone(), KEY).unwrap())
+                .unwrap();
+            for value in ["world", "world2"] {
+                let write_txn = db.begin_write().unwrap();
+                {
+                    let mut table = write_txn.open_table(X).unwrap();
+                    table.insert("hello", value).unwrap();
+                }
+                write_txn.commit().unwrap();
+            }
+            drop(db);
+
+            let header = EncryptedBackend::new(storage.clone(), KEY)
+                .unwrap()
+                .read(0, DB_HEADER_SIZE)
+                .unwrap();
+            let primary_is_slot1 = header[GOD_BYTE_OFFSET] & PRIMARY_BIT != 0;
+            let slot_offset = if primary_is_slot1 == corrupt_primary {
+                TRANSACTION_1_OFFSET
+            } else {
+                TRANSACTION_0_OFFSET
+            };
+            let offset = block_offset(0) + u64::try_from(slot_offset + 10).unwrap();
+            let mut byte = storage.read(offset, 1).unwrap();
+            byte[0] ^= 1;
+            storage.write(offset, &byte).unwrap();
+
+            // The rest of the header is still readable
+            let backend = EncryptedBackend::new(storage.clone(), KEY).unwrap();
+            assert_eq!(
+                backend.read(0, TRANSACTION_0_OFFSET).unwrap(),
+                &header[..TRANSACTION_0_OFFSET]
+            );
+
+            // The database is opened from the other slot, which holds the previous commit if the
+            // primary slot was corrupted
+            let mut db = Builder::new().create_with_backend(backend).unwrap();
+            assert!(db.check_integrity().unwrap());
+            let read_txn = db.begin_read().unwrap();
+            let table = read_txn.open_table(X).unwrap();
+            let expected = if corrupt_primary { "world" } else { "world2" };
+            assert_eq!(table.get("hello").unwrap().unwrap().value(), expected);
+        }
+    }
+
+    #[test]
+    fn interrupted_header_write() {
+        let storage = SharedBackend::default();
+        let backend = EncryptedBackend::new(storage.clone(), KEY).unwrap();
+        backend.set_len(4 * 4096).unwrap();
+        backend.write(0, &[1; DB_HEADER_SIZE]).unwrap();
+
+        // Only the metadata of the new commit slot is written
+        storage.drop_header_writes.store(true, Ordering::SeqCst);
+        backend
+            .write(u64::try_from(TRANSACTION_1_OFFSET).unwrap(), &[2; 128])
+            .unwrap();
+        storage.drop_header_writes.store(false, Ordering::SeqCst);
+        let backend = EncryptedBackend::new(storage.clone(), KEY).unwrap();
+        assert_eq!(
+            backend.read(0, DB_HEADER_SIZE).unwrap(),
+            vec![1; DB_HEADER_SIZE]
+        );
+
+        // The slot can be written again afterwards
+        backend
+            .write(u64::try_from(TRANSACTION_1_OFFSET).unwrap(), &[3; 128])
+            .unwrap();
+        let backend = EncryptedBackend::new(storage, KEY).unwrap();
+        let header = backend.read(0, DB_HEADER_SIZE).unwrap();
+        assert_eq!(&header[..TRANSACTION_1_OFFSET], &[1; TRANSACTION_1_OFFSET]);
+        assert_eq!(&header[TRANSACTION_1_OFFSET..], &[3; 128]);
+    }
+}
@@ -2,7 +2,7 @@ use crate::transaction_tracker::TransactionId;
 use crate::tree_store::btree_base::BtreeHeader;
 use crate::tree_store::page_store::layout::{DatabaseLayout, RegionLayout};
 use crate::tree_store::page_store::page_manager::{
-    xxh3_checksum, FILE_FORMAT_VERSION1, FILE_FORMAT_VERSION2,
+    ChecksumAlgorithm, FILE_FORMAT_VERSION1, FILE_FORMAT_VERSION2, FILE_FORMAT_VERSION3,
 };
 use crate::tree_store::{Checksum, PageNumber};
 use crate::{DatabaseError, StorageError};
@@ -21,6 +21,7 @@ use std::mem::size_of;
 // Definition of region
 // 4 bytes: region header pages
 // 4 bytes: region max data pages
+// 16 bytes: checksum key check, following the region tracker page number
 //
 // Commit slot 0 (next 128 bytes):
 // 1 byte: version
@@ -42,7 +43,7 @@ use std::mem::size_of;
 
 // Inspired by PNG's magic number
 pub(super) const MAGICNUMBER: [u8; 9] = [b'r', b'e', b'd', b'b', 0x1A, 0x0A, 0xA9, 0x0D, 0x0A];
-const GOD_BYTE_OFFSET: usize = MAGICNUMBER.len();
+pub(super) const GOD_BYTE_OFFSET: usize = MAGICNUMBER.len();
 const PAGE_SIZE_OFFSET: usize = GOD_BYTE_OFFSET + size_of::<u8>() + 2; // +2 for padding
 const REGION_HEADER_PAGES_OFFSET: usize = PAGE_SIZE_OFFSET + size_of::<u32>();
 const REGION_MAX_DATA_PAGES_OFFSET: usize = REGION_HEADER_PAGES_OFFSET + size_of::<u32>();
@@ -50,14 +51,17 @@ const NUM_FULL_REGIONS_OFFSET: usize = REGION_MAX_DATA_PAGES_OFFSET + size_of::<
 const TRAILING_REGION_DATA_PAGES_OFFSET: usize = NUM_FULL_REGIONS_OFFSET + size_of::<u32>();
 const REGION_TRACKER_PAGE_NUMBER_OFFSET: usize =
     TRAILING_REGION_DATA_PAGES_OFFSET + size_of::<u32>();
+const CHECKSUM_KEY_CHECK_OFFSET: usize =
+    REGION_TRACKER_PAGE_NUMBER_OFFSET + PageNumber::serialized_size();
 const TRANSACTION_SIZE: usize = 128;
-const TRANSACTION_0_OFFSET: usize = 64;
-const TRANSACTION_1_OFFSET: usize = TRANSACTION_0_OFFSET + TRANSACTION_SIZE;
+pub(super) const TRANSACTION_0_OFFSET: usize = 64;
+pub(super) const TRANSACTION_1_OFFSET: usize = TRANSACTION_0_OFFSET + TRANSACTION_SIZE;
 pub(super) const DB_HEADER_SIZE: usize = TRANSACTION_1_OFFSET + TRANSACTION_SIZE;
 
 // God byte flags
-const PRIMARY_BIT: u8 = 1;
+pub(super) const PRIMARY_BIT: u8 = 1;
 const RECOVERY_REQUIRED: u8 = 2;
+const KEYED_CHECKSUMS: u8 = 4;
 
 // Structure of each commit slot
 const VERSION_OFFSET: usize = 0;
@@ -95,6 +99,8 @@ pub(super) struct HeaderRepairInfo {
 pub(super) struct DatabaseHeader {
     primary_slot: usize,
     pub(super) recovery_required: bool,
+    // Used for the commit slot checksums. This is fixed when the database is created
+    checksum: ChecksumAlgorithm,
     page_size: u32,
     region_header_pages: u32,
     region_max_data_pages: u32,
@@ -110,16 +116,19 @@ impl DatabaseHeader {
         transaction_id: TransactionId,
         version: u8,
         region_tracker: PageNumber,
+        checksum: ChecksumAlgorithm,
     ) -> Self {
         #[allow(clippy::assertions_on_constants)]
         {
             assert!(TRANSACTION_LAST_FIELD <= SLOT_CHECKSUM_OFFSET);
+            assert!(CHECKSUM_KEY_CHECK_OFFSET + size_of::<Checksum>() <= TRANSACTION_0_OFFSET);
         }
 
         let slot = TransactionHeader::new(transaction_id, version);
         Self {
             primary_slot: 0,
             recovery_required: true,
+            checksum,
             page_size: layout.full_region_layout().page_size(),
             region_header_pages: layout.full_region_layout().get_header_pages(),
             region_max_data_pages: layout.full_region_layout().num_pages(),
@@ -195,9 +204,34 @@ impl DatabaseHeader {
     }
 
     // TODO: consider returning an Err with the repair info
-    pub(super) fn from_bytes(data: &[u8]) -> Result<(Self, HeaderRepairInfo), DatabaseError> {
+    pub(super) fn from_bytes(
+        data: &[u8],
+        checksum: ChecksumAlgorithm,
+    ) -> Result<(Self, HeaderRepairInfo), DatabaseError> {
         let invalid_magic_number = data[..MAGICNUMBER.len()] != MAGICNUMBER;
 
+        // Check that checksums are computed the same way that they were when the database was
+        // created, since otherwise every commit slot would appear to be corrupted. The flags can't
+        // be trusted without a valid magic number, in which case the header is reported as corrupted
+        if !invalid_magic_number {
+            let key_check = if data[GOD_BYTE_OFFSET] & KEYED_CHECKSUMS != 0 {
+                if !cfg!(feature = "keyed_checksums") {
+                    return Err(DatabaseError::ChecksumUnsupported);
+                }
+                Some(Checksum::from_le_bytes(
+                    data[CHECKSUM_KEY_CHECK_OFFSET
+                        ..(CHECKSUM_KEY_CHECK_OFFSET + size_of::<Checksum>())]
+                        .try_into()
+                        .unwrap(),
+                ))
+            } else {
+                None
+            };
+            if key_check != checksum.key_check() {
+                return Err(DatabaseError::InvalidChecksumKey);
+            }
+        }
+
         let primary_slot = usize::from(data[GOD_BYTE_OFFSET] & PRIMARY_BIT != 0);
         let recovery_required = (data[GOD_BYTE_OFFSET] & RECOVERY_REQUIRED) != 0;
         let page_size = get_u32(&data[PAGE_SIZE_OFFSET..]);
@@ -213,9 +247,11 @@ impl DatabaseHeader {
         );
         let (slot0, slot0_corrupted) = TransactionHeader::from_bytes(
             &data[TRANSACTION_0_OFFSET..(TRANSACTION_0_OFFSET + TRANSACTION_SIZE)],
+            &checksum,
         )?;
         let (slot1, slot1_corrupted) = TransactionHeader::from_bytes(
             &data[TRANSACTION_1_OFFSET..(TRANSACTION_1_OFFSET + TRANSACTION_SIZE)],
+            &checksum,
         )?;
         let (primary_corrupted, secondary_corrupted) = if primary_slot == 0 {
             (slot0_corrupted, slot1_corrupted)
@@ -226,6 +262,7 @@ impl DatabaseHeader {
         let result = Self {
             primary_slot,
             recovery_required,
+            checksum,
             page_size,
             region_header_pages,
             region_max_data_pages,
@@ -258,6 +295,11 @@ impl DatabaseHeader {
         if self.recovery_required {
             result[GOD_BYTE_OFFSET] |= RECOVERY_REQUIRED;
         }
+        if let Some(key_check) = self.checksum.key_check() {
+            result[GOD_BYTE_OFFSET] |= KEYED_CHECKSUMS;
+            result[CHECKSUM_KEY_CHECK_OFFSET..(CHECKSUM_KEY_CHECK_OFFSET + size_of::<Checksum>())]
+                .copy_from_slice(&key_check.to_le_bytes());
+        }
         result[PAGE_SIZE_OFFSET..(PAGE_SIZE_OFFSET + size_of::<u32>())]
             .copy_from_slice(&self.page_size.to_le_bytes());
         result[REGION_HEADER_PAGES_OFFSET..(REGION_HEADER_PAGES_OFFSET + size_of::<u32>())]
@@ -272,9 +314,9 @@ impl DatabaseHeader {
         result[REGION_TRACKER_PAGE_NUMBER_OFFSET
             ..(REGION_TRACKER_PAGE_NUMBER_OFFSET + PageNumber::serialized_size())]
             .copy_from_slice(&self.region_tracker.to_le_bytes());
-        let slot0 = self.transaction_slots[0].to_bytes();
+        let slot0 = self.transaction_slots[0].to_bytes(&self.checksum);
         result[TRANSACTION_0_OFFSET..(TRANSACTION_0_OFFSET + slot0.len())].copy_from_slice(&slot0);
-        let slot1 = self.transaction_slots[1].to_bytes();
+        let slot1 = self.transaction_slots[1].to_bytes(&self.checksum);
         result[TRANSACTION_1_OFFSET..(TRANSACTION_1_OFFSET + slot1.len())].copy_from_slice(&slot1);
 
         result
@@ -302,24 +344,32 @@ impl TransactionHeader {
     }
 
     // Returned bool indicates whether the checksum was corrupted
-    pub(super) fn from_bytes(data: &[u8]) -> Result<(Self, bool), DatabaseError> {
+    pub(super) fn from_bytes(
+        data: &[u8],
+        checksum: &ChecksumAlgorithm,
+    ) -> Result<(Self, bool), DatabaseError> {
+        // EncryptedBackend returns a commit slot which fails authentication as zeros, so that it's
+        // reported as corrupted, and the other slot is used
+        if data.iter().all(|x| *x == 0) {
+            return Ok((Self::new(TransactionId::new(0), FILE_FORMAT_VERSION3), true));
+        }
         let version = data[VERSION_OFFSET];
         match version {
             FILE_FORMAT_VERSION1 => return Err(DatabaseError::UpgradeRequired(version)),
-            FILE_FORMAT_VERSION2 => {}
+            FILE_FORMAT_VERSION2 | FILE_FORMAT_VERSION3 => {}
             _ => {
                 return Err(StorageError::Corrupted(format!(
-                    "Expected file format version <= {FILE_FORMAT_VERSION2}, found {version}",
+                    "Expected file format version <= {FILE_FORMAT_VERSION3}, found {version}",
                 ))
                 .into())
             }
         }
-        let checksum = Checksum::from_le_bytes(
+        let expected_checksum = Checksum::from_le_bytes(
             data[SLOT_CHECKSUM_OFFSET..(SLOT_CHECKSUM_OFFSET + size_of::<Checksum>())]
                 .try_into()
                 .unwrap(),
         );
-        let corrupted = checksum != xxh3_checksum(&data[..SLOT_CHECKSUM_OFFSET]);
+        let corrupted = expected_checksum != checksum.checksum(&data[..SLOT_CHECKSUM_OFFSET]);
 
         let user_root = if data[USER_ROOT_NON_NULL_OFFSET] != 0 {
             Some(BtreeHeader::from_le_bytes(
@@ -361,8 +411,11 @@ impl TransactionHeader {
         Ok((result, corrupted))
     }
 
-    pub(super) fn to_bytes(&self) -> [u8; TRANSACTION_SIZE] {
-        assert_eq!(self.version, FILE_FORMAT_VERSION2);
+    pub(super) fn to_bytes(&self, checksum: &ChecksumAlgorithm) -> [u8; TRANSACTION_SIZE] {
+        assert!(matches!(
+            self.version,
+            FILE_FORMAT_VERSION2 | FILE_FORMAT_VERSION3
+        ));
         let mut result = [0; TRANSACTION_SIZE];
         result[VERSION_OFFSET] = self.version;
         if let Some(header) = self.user_root {
@@ -382,7 +435,7 @@ impl TransactionHeader {
         }
         result[TRANSACTION_ID_OFFSET..(TRANSACTION_ID_OFFSET + size_of::<u64>())]
             .copy_from_slice(&self.transaction_id.raw_id().to_le_bytes());
-        let checksum = xxh3_checksum(&result[..SLOT_CHECKSUM_OFFSET]);
+        let checksum = checksum.checksum(&result[..SLOT_CHECKSUM_OFFSET]);
         result[SLOT_CHECKSUM_OFFSET..(SLOT_CHECKSUM_OFFSET + size_of::<Checksum>())]
             .copy_from_slice(&checksum.to_le_bytes());
 
@@ -395,8 +448,12 @@ mod test {
     use crate::backends::FileBackend;
     use crate::db::TableDefinition;
     use crate::tree_store::page_store::header::{
-        GOD_BYTE_OFFSET, MAGICNUMBER, PAGE_SIZE, PRIMARY_BIT, RECOVERY_REQUIRED,
-        TRANSACTION_0_OFFSET, TRANSACTION_1_OFFSET, USER_ROOT_OFFSET,
+        DatabaseHeader, DB_HEADER_SIZE, GOD_BYTE_OFFSET, KEYED_CHECKSUMS, MAGICNUMBER, PAGE_SIZE,
+        PRIMARY_BIT, RECOVERY_REQUIRED, TRANSACTION_0_OFFSET, TRANSACTION_1_OFFSET,
+        USER_ROOT_OFFSET, VERSION_OFFSET,
+    };
+    use crate::tree_store::page_store::page_manager::{
+        ChecksumAlgorithm, FILE_FORMAT_VERSION2, FILE_FORMAT_VERSION3,
     };
     use crate::tree_store::page_store::TransactionalMemory;
     #[cfg(not(target_os = "windows"))]
@@ -461,7 +518,8 @@ mod test {
             PAGE_SIZE,
             None,
             0,
-            0
+            0,
+            ChecksumAlgorithm::Xxh3
         )
         .unwrap()
         .needs_repair()
@@ -550,7 +608,8 @@ mod test {
             PAGE_SIZE,
             None,
             0,
-            0
+            0,
+            ChecksumAlgorithm::Xxh3
         )
         .unwrap()
         .needs_repair()
@@ -583,7 +642,8 @@ mod test {
             PAGE_SIZE,
             None,
             0,
-            0
+            0,
+            ChecksumAlgorithm::Xxh3
         )
         .unwrap()
         .needs_repair()
@@ -639,7 +699,8 @@ mod test {
             PAGE_SIZE,
             None,
             0,
-            0
+            0,
+            ChecksumAlgorithm::Xxh3
         )
         .unwrap()
         .needs_repair()
@@ -648,6 +709,115 @@ mod test {
         Database::open(tmpfile.path()).unwrap();
     }
 
+    #[test]
+    fn keyed_checksums_flag() {
+        let tmpfile = crate::create_tempfile();
+        let db = Database::builder().create(tmpfile.path()).unwrap();
+        drop(db);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tmpfile.path())
+            .unwrap();
+        file.seek(SeekFrom::Start(GOD_BYTE_OFFSET as u64)).unwrap();
+        let mut buffer = [0u8; 1];
+        file.read_exact(&mut buffer).unwrap();
+        file.seek(SeekFrom::Start(GOD_BYTE_OFFSET as u64)).unwrap();
+        file.write_all(&[buffer[0] | KEYED_CHECKSUMS]).unwrap();
+        drop(file);
+
+        #[cfg(not(feature = "keyed_checksums"))]
+        assert!(matches!(
+            Database::open(tmpfile.path()).err().unwrap(),
+            DatabaseError::ChecksumUnsupported
+        ));
+        #[cfg(feature = "keyed_checksums")]
+        assert!(matches!(
+            Database::open(tmpfile.path()).err().unwrap(),
+            DatabaseError::InvalidChecksumKey
+        ));
+    }
+
+    #[test]
+    fn file_format_version() {
+        // Returns the version of the primary commit slot, and of the secondary commit slot
+        fn versions(path: &std::path::Path) -> (u8, u8) {
+            let mut data = vec![0; DB_HEADER_SIZE];
+            OpenOptions::new()
+                .read(true)
+                .open(path)
+                .unwrap()
+                .read_exact(&mut data)
+                .unwrap();
+            let slot0 = data[TRANSACTION_0_OFFSET + VERSION_OFFSET];
+            let slot1 = data[TRANSACTION_1_OFFSET + VERSION_OFFSET];
+            if data[GOD_BYTE_OFFSET] & PRIMARY_BIT == 0 {
+                (slot0, slot1)
+            } else {
+                (slot1, slot0)
+            }
+        }
+
+        let tmpfile = crate::create_tempfile();
+        let db = Database::builder().create(tmpfile.path()).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(X).unwrap();
+            table.insert("hello", "world").unwrap();
+        }
+        write_txn.commit().unwrap();
+        drop(db);
+        assert_eq!(
+            versions(tmpfile.path()),
+            (FILE_FORMAT_VERSION3, FILE_FORMAT_VERSION3)
+        );
+
+        // Rewrite both commit slots as version 2, which is what older versions of redb wrote
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tmpfile.path())
+            .unwrap();
+        let mut data = vec![0; DB_HEADER_SIZE];
+        file.read_exact(&mut data).unwrap();
+        let (mut header, _) = DatabaseHeader::from_bytes(&data, ChecksumAlgorithm::Xxh3).unwrap();
+        for slot in header.transaction_slots.iter_mut() {
+            slot.version = FILE_FORMAT_VERSION2;
+        }
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(&header.to_bytes(true, false)).unwrap();
+        drop(file);
+        assert_eq!(
+            versions(tmpfile.path()),
+            (FILE_FORMAT_VERSION2, FILE_FORMAT_VERSION2)
+        );
+
+        // Version 2 files can still be read, and the next commit writes version 3
+        let db = Database::open(tmpfile.path()).unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(X).unwrap();
+        assert_eq!(table.get("hello").unwrap().unwrap().value(), "world");
+        drop(table);
+        drop(read_txn);
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(X).unwrap();
+            table.insert("hello", "world2").unwrap();
+        }
+        write_txn.commit().unwrap();
+        drop(db);
+        assert_eq!(
+            versions(tmpfile.path()),
+            (FILE_FORMAT_VERSION3, FILE_FORMAT_VERSION2)
+        );
+
+        let db = Database::open(tmpfile.path()).unwrap();
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(X).unwrap();
+        assert_eq!(table.get("hello").unwrap().unwrap().value(), "world2");
+    }
+
     #[test]
     fn magic_number() {
         // Test compliance with some, but not all, provisions recommended by
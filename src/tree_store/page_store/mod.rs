@@ -2,6 +2,8 @@ mod base;
 mod bitmap;
 mod buddy_allocator;
 mod cached_file;
+#[cfg(feature = "encryption")]
+mod encrypted_backend;
 pub mod file_backend;
 mod header;
 mod in_memory_backend;
@@ -13,9 +15,13 @@ mod savepoint;
 mod xxh3;
 
 pub(crate) use base::{Page, PageHint, PageNumber, MAX_PAIR_LENGTH, MAX_VALUE_LENGTH};
+#[cfg(feature = "encryption")]
+pub use encrypted_backend::{EncryptedBackend, EncryptionKey};
 pub(crate) use header::PAGE_SIZE;
 pub use in_memory_backend::InMemoryBackend;
-pub(crate) use page_manager::{xxh3_checksum, TransactionalMemory, FILE_FORMAT_VERSION2};
+pub(crate) use page_manager::{
+    xxh3_checksum, ChecksumAlgorithm, TransactionalMemory, FILE_FORMAT_VERSION3,
+};
 pub use savepoint::Savepoint;
 pub(crate) use savepoint::SerializedSavepoint;
 
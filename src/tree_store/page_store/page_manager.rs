@@ -36,6 +36,9 @@ pub(super) const INITIAL_REGIONS: u32 = 1000; // Enough for a 4TiB database
 pub(crate) const FILE_FORMAT_VERSION1: u8 = 1;
 // New file format. All btrees have a separate length stored in their header for constant time access
 pub(crate) const FILE_FORMAT_VERSION2: u8 = 2;
+// Large values may be stored in overflow pages. Version 2 files are upgraded by their next commit,
+// since they can't contain overflow pages
+pub(crate) const FILE_FORMAT_VERSION3: u8 = 3;
 
 fn ceil_log2(x: usize) -> u8 {
     if x.is_power_of_two() {
@@ -49,6 +52,41 @@ pub(crate) fn xxh3_checksum(data: &[u8]) -> Checksum {
     hash128_with_seed(data, 0)
 }
 
+// Function used for page and commit slot checksums
+#[derive(Clone)]
+pub(crate) enum ChecksumAlgorithm {
+    Xxh3,
+    // BLAKE3 in keyed mode, truncated to the size of a Checksum
+    #[cfg(feature = "keyed_checksums")]
+    Blake3([u8; 32]),
+}
+
+impl ChecksumAlgorithm {
+    pub(crate) fn checksum(&self, data: &[u8]) -> Checksum {
+        match self {
+            ChecksumAlgorithm::Xxh3 => xxh3_checksum(data),
+            #[cfg(feature = "keyed_checksums")]
+            ChecksumAlgorithm::Blake3(key) => {
+                let hash = blake3::keyed_hash(key, data);
+                Checksum::from_le_bytes(
+                    hash.as_bytes()[..std::mem::size_of::<Checksum>()]
+                        .try_into()
+                        .unwrap(),
+                )
+            }
+        }
+    }
+
+    // Value stored in the header to detect when a database is opened with the wrong key
+    pub(crate) fn key_check(&self) -> Option<Checksum> {
+        match self {
+            ChecksumAlgorithm::Xxh3 => None,
+            #[cfg(feature = "keyed_checksums")]
+            ChecksumAlgorithm::Blake3(_) => Some(self.checksum(b"redb checksum key check")),
+        }
+    }
+}
+
 struct InMemoryState {
     header: DatabaseHeader,
     allocators: Allocators,
@@ -85,6 +123,7 @@ pub(crate) struct TransactionalMemory {
     // TODO: maybe we can remove this flag now that CheckedBackend exists?
     needs_recovery: AtomicBool,
     storage: PagedCachedFile,
+    checksum: ChecksumAlgorithm,
     state: Mutex<InMemoryState>,
     // The number of PageMut which are outstanding
     #[cfg(debug_assertions)]
@@ -109,6 +148,7 @@ impl TransactionalMemory {
         requested_region_size: Option<u64>,
         read_cache_size_bytes: usize,
         write_cache_size_bytes: usize,
+        checksum: ChecksumAlgorithm,
     ) -> Result<Self, DatabaseError> {
         assert!(page_size.is_power_of_two() && page_size >= DB_HEADER_SIZE);
 
@@ -180,8 +220,9 @@ impl TransactionalMemory {
             let mut header = DatabaseHeader::new(
                 layout,
                 TransactionId::new(0),
-                FILE_FORMAT_VERSION2,
+                FILE_FORMAT_VERSION3,
                 tracker_page,
+                checksum.clone(),
             );
 
             header.recovery_required = false;
@@ -201,10 +242,15 @@ impl TransactionalMemory {
             storage.flush(false)?;
         }
         let header_bytes = storage.read_direct(0, DB_HEADER_SIZE)?;
-        let (mut header, repair_info) = DatabaseHeader::from_bytes(&header_bytes)?;
+        let (mut header, repair_info) =
+            DatabaseHeader::from_bytes(&header_bytes, checksum.clone())?;
 
         assert_eq!(header.page_size() as usize, page_size);
         assert!(storage.raw_file_len()? >= header.layout().len());
+        // The primary slot is only replaced by the secondary slot during recovery
+        if repair_info.primary_corrupted {
+            header.recovery_required = true;
+        }
         let needs_recovery =
             header.recovery_required || header.layout().len() != storage.raw_file_len()?;
         if needs_recovery {
@@ -250,6 +296,7 @@ impl TransactionalMemory {
             allocated_since_commit: Mutex::new(HashSet::new()),
             needs_recovery: AtomicBool::new(needs_recovery),
             storage,
+            checksum,
             state: Mutex::new(state),
             #[cfg(debug_assertions)]
             open_dirty_pages: Arc::new(Mutex::new(HashSet::new())),
@@ -262,6 +309,10 @@ impl TransactionalMemory {
         })
     }
 
+    pub(crate) fn checksum(&self, data: &[u8]) -> Checksum {
+        self.checksum.checksum(data)
+    }
+
     pub(crate) fn check_io_errors(&self) -> Result {
         self.storage.check_io_errors()
     }
@@ -287,7 +338,8 @@ impl TransactionalMemory {
         self.storage.invalidate_cache_all();
 
         let header_bytes = self.storage.read_direct(0, DB_HEADER_SIZE)?;
-        let (mut header, repair_info) = DatabaseHeader::from_bytes(&header_bytes)?;
+        let (mut header, repair_info) =
+            DatabaseHeader::from_bytes(&header_bytes, self.checksum.clone())?;
         // TODO: This ends up always being true because this is called from check_integrity() once the db is already open
         // TODO: Also we should recheck the layout
         let mut was_clean = true;
@@ -513,6 +565,7 @@ impl TransactionalMemory {
 
         let old_transaction_id = header.secondary_slot().transaction_id;
         let secondary = header.secondary_slot_mut();
+        secondary.version = FILE_FORMAT_VERSION3;
         secondary.transaction_id = transaction_id;
         secondary.user_root = data_root;
         secondary.system_root = system_root;
@@ -576,6 +629,7 @@ impl TransactionalMemory {
 
         let mut state = self.state.lock().unwrap();
         let secondary = state.header.secondary_slot_mut();
+        secondary.version = FILE_FORMAT_VERSION3;
         secondary.transaction_id = transaction_id;
         secondary.user_root = data_root;
         secondary.system_root = system_root;
@@ -1,5 +1,5 @@
 use crate::transaction_tracker::{SavepointId, TransactionId, TransactionTracker};
-use crate::tree_store::page_store::page_manager::FILE_FORMAT_VERSION2;
+use crate::tree_store::page_store::page_manager::{FILE_FORMAT_VERSION2, FILE_FORMAT_VERSION3};
 use crate::tree_store::{BtreeHeader, TransactionalMemory};
 use crate::{TypeName, Value};
 use std::fmt::Debug;
@@ -104,7 +104,10 @@ pub(crate) enum SerializedSavepoint<'a> {
 
 impl<'a> SerializedSavepoint<'a> {
     pub(crate) fn from_savepoint(savepoint: &Savepoint) -> Self {
-        assert_eq!(savepoint.version, FILE_FORMAT_VERSION2);
+        assert!(matches!(
+            savepoint.version,
+            FILE_FORMAT_VERSION2 | FILE_FORMAT_VERSION3
+        ));
         let mut result = vec![savepoint.version];
         result.extend(savepoint.id.0.to_le_bytes());
         result.extend(savepoint.transaction_id.raw_id().to_le_bytes());
@@ -164,7 +167,10 @@ impl<'a> SerializedSavepoint<'a> {
         let data = self.data();
         let mut offset = 0;
         let version = data[offset];
-        assert_eq!(version, FILE_FORMAT_VERSION2);
+        assert!(matches!(
+            version,
+            FILE_FORMAT_VERSION2 | FILE_FORMAT_VERSION3
+        ));
         offset += size_of::<u8>();
 
         let id = u64::from_le_bytes(
@@ -7,8 +7,8 @@ use crate::tree_store::btree::{btree_stats, UntypedBtreeMut};
 use crate::tree_store::btree_base::BtreeHeader;
 use crate::tree_store::page_store::{new_allocators, BuddyAllocator};
 use crate::tree_store::{
-    Btree, BtreeMut, BtreeRangeIter, InternalTableDefinition, PageHint, PageNumber, PagePath,
-    RawBtree, TableType, TransactionalMemory,
+    leaf_overflow_refs, BranchAccessor, Btree, BtreeMut, BtreeRangeIter, InternalTableDefinition,
+    Page, PageHint, PageNumber, PagePath, RawBtree, TableType, TransactionalMemory, BRANCH,
 };
 use crate::types::{Key, MutInPlaceValue, TypeName, Value};
 use crate::{DatabaseStats, Result};
@@ -217,6 +217,16 @@ impl TableTree {
         self.tree.transaction_guard()
     }
 
+    // Returns the definitions of all tables, of any type
+    pub(crate) fn list_table_definitions(&self) -> Result<Vec<(String, InternalTableDefinition)>> {
+        let mut result = vec![];
+        for entry in self.tree.range::<RangeFull, &str>(&(..))? {
+            let entry = entry?;
+            result.push((entry.key().to_string(), entry.value()));
+        }
+        Ok(result)
+    }
+
     // root_page: the root of the master table
     pub(crate) fn list_tables(&self, table_type: TableType) -> Result<Vec<String>> {
         let iter = self.tree.range::<RangeFull, &str>(&(..))?;
@@ -293,13 +303,58 @@ impl<'txn> TableTreeMut<'txn> {
 
         self.visit_all_pages(|path| {
             let page = path.page_number();
-            result[page.region as usize].record_alloc(page.page_index, page.page_order);
+            let allocator = &mut result[page.region as usize];
+            // Pages shared by cloned tables are visited once for each table
+            if !allocator.is_allocated(page.page_index, page.page_order) {
+                allocator.record_alloc(page.page_index, page.page_order);
+            }
             Ok(())
         })?;
 
         Ok(result)
     }
 
+    // Returns the number of extra references to each page that is shared by more than one
+    // normal table
+    pub(crate) fn shared_page_counts(&self) -> Result<HashMap<PageNumber, u64>> {
+        let mut references: HashMap<PageNumber, u64> = HashMap::new();
+        for entry in self.list_tables(TableType::Normal)? {
+            let definition = self
+                .get_table_untyped(&entry, TableType::Normal)
+                .map_err(|e| e.into_storage_error_or_corrupted("Internal corruption"))?
+                .unwrap();
+            let mut pending = vec![];
+            if let Some(header) = definition.get_root() {
+                pending.push(header.root);
+            }
+            while let Some(page_number) = pending.pop() {
+                let count = references.entry(page_number).or_default();
+                *count += 1;
+                // The children of a shared page were already counted, when it was first visited
+                if *count > 1 {
+                    continue;
+                }
+                let page = self.mem.get_page(page_number)?;
+                if page.memory()[0] == BRANCH {
+                    let accessor = BranchAccessor::new(&page, definition.get_fixed_key_size());
+                    for i in 0..accessor.count_children() {
+                        pending.push(accessor.child_page(i).unwrap());
+                    }
+                } else {
+                    for (_, reference) in leaf_overflow_refs(page.memory()) {
+                        pending.push(reference.page);
+                    }
+                }
+            }
+        }
+        references.retain(|_, count| {
+            *count -= 1;
+            *count > 0
+        });
+
+        Ok(references)
+    }
+
     pub(crate) fn visit_all_pages<F>(&self, mut visitor: F) -> Result
     where
         F: FnMut(&PagePath) -> Result,
@@ -526,6 +581,116 @@ impl<'txn> TableTreeMut<'txn> {
         Ok(false)
     }
 
+    // Moves the definition of a table to a new name. Data pages are not modified
+    pub(crate) fn rename_table(
+        &mut self,
+        name: &str,
+        new_name: &str,
+        table_type: TableType,
+    ) -> Result<(), TableError> {
+        if self.get_table_untyped(name, table_type)?.is_none() {
+            return Err(TableError::TableDoesNotExist(name.to_string()));
+        }
+        if self.contains_table(new_name)? {
+            return Err(TableError::TableAlreadyExists(new_name.to_string()));
+        }
+
+        // Move the stored definition, rather than the one returned by get_table_untyped(), so that
+        // any pending root update is still finalized when it is flushed
+        let definition = self.tree.remove(&name)?.unwrap().value();
+        self.tree.insert(&new_name, &definition)?;
+        if let Some(update) = self.pending_table_updates.remove(name) {
+            self.pending_table_updates
+                .insert(new_name.to_string(), update);
+        }
+
+        Ok(())
+    }
+
+    // Exchanges the contents of two tables, which must store the same types
+    pub(crate) fn swap_tables(
+        &mut self,
+        a: &str,
+        b: &str,
+        table_type: TableType,
+    ) -> Result<(), TableError> {
+        let definition_a = self
+            .get_table_untyped(a, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(a.to_string()))?;
+        let definition_b = self
+            .get_table_untyped(b, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(b.to_string()))?;
+        definition_a.check_same_types(&definition_b, b)?;
+        if a == b {
+            return Ok(());
+        }
+
+        // Swap the stored definitions, along with any pending root updates, so that the updates
+        // are still finalized when they are flushed
+        let stored_a = self.tree.get(&a)?.unwrap().value();
+        let stored_b = self.tree.get(&b)?.unwrap().value();
+        self.tree.insert(&a, &stored_b)?;
+        self.tree.insert(&b, &stored_a)?;
+        let update_a = self.pending_table_updates.remove(a);
+        let update_b = self.pending_table_updates.remove(b);
+        if let Some(update) = update_a {
+            self.pending_table_updates.insert(b.to_string(), update);
+        }
+        if let Some(update) = update_b {
+            self.pending_table_updates.insert(a.to_string(), update);
+        }
+
+        Ok(())
+    }
+
+    // Replaces the contents of `target` with those of `source`, which is removed. The pages of
+    // the previous contents of `target` are freed
+    pub(crate) fn replace_table(
+        &mut self,
+        target: &str,
+        source: &str,
+        table_type: TableType,
+    ) -> Result<(), TableError> {
+        let target_definition = self
+            .get_table_untyped(target, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(target.to_string()))?;
+        let source_definition = self
+            .get_table_untyped(source, table_type)?
+            .ok_or_else(|| TableError::TableDoesNotExist(source.to_string()))?;
+        target_definition.check_same_types(&source_definition, source)?;
+        if target == source {
+            return Ok(());
+        }
+
+        self.delete_table(target, table_type)?;
+        self.rename_table(source, target, table_type)
+    }
+
+    // Creates `destination` as a copy of `source`, which shares all of its pages.
+    // Returns the root page of the shared tree, whose reference count must be incremented
+    pub(crate) fn clone_table(
+        &mut self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<PageNumber>, TableError> {
+        let definition = self
+            .get_table_untyped(source, TableType::Normal)?
+            .ok_or_else(|| TableError::TableDoesNotExist(source.to_string()))?;
+        if self.contains_table(destination)? {
+            return Err(TableError::TableAlreadyExists(destination.to_string()));
+        }
+        // Uncommitted pages may be mutated in place, so they can't be shared
+        let root = definition.get_root().map(|header| header.root);
+        if let Some(root) = root {
+            if self.mem.uncommitted(root) {
+                return Err(TableError::TableHasUncommittedChanges(source.to_string()));
+            }
+        }
+        self.tree.insert(&destination, &definition)?;
+
+        Ok(root)
+    }
+
     pub(crate) fn get_or_create_table<K: Key, V: Value>(
         &mut self,
         name: &str,
@@ -553,6 +718,31 @@ impl<'txn> TableTreeMut<'txn> {
         }
     }
 
+    // Returns true if a table of either type has the given name
+    pub(crate) fn contains_table(&self, name: &str) -> Result<bool> {
+        Ok(self.tree.get(&name)?.is_some())
+    }
+
+    // Creates a table from a complete definition, whose tree was built in this transaction.
+    // Returns false, without modifying anything, if a table of either type already has the name
+    pub(crate) fn create_table(
+        &mut self,
+        name: &str,
+        mut definition: InternalTableDefinition,
+    ) -> Result<bool> {
+        if self.contains_table(name)? {
+            return Ok(false);
+        }
+        let root = definition.get_root();
+        let length = definition.get_length();
+        // The root is staged, so that its checksums are finalized during commit
+        definition.set_header(None, 0);
+        self.tree.insert(&name, &definition)?;
+        self.stage_update_table_root(name, root, length);
+
+        Ok(true)
+    }
+
     // Returns the paths to the n pages that are closest to the end of the database
     // The return value is sorted, according to path.page_number()'s Ord
     pub(crate) fn highest_index_pages(
@@ -75,27 +75,47 @@ impl InternalTableDefinition {
         table_type: TableType,
         table_root: Option<BtreeHeader>,
         table_length: u64,
+    ) -> Self {
+        Self::new_untyped(
+            table_type,
+            table_root,
+            table_length,
+            K::fixed_width(),
+            V::fixed_width(),
+            K::type_name(),
+            V::type_name(),
+        )
+    }
+
+    pub(crate) fn new_untyped(
+        table_type: TableType,
+        table_root: Option<BtreeHeader>,
+        table_length: u64,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+        key_type: TypeName,
+        value_type: TypeName,
     ) -> Self {
         match table_type {
             TableType::Normal => InternalTableDefinition::Normal {
                 table_root,
                 table_length,
-                fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_key_size,
+                fixed_value_size,
                 key_alignment: ALIGNMENT,
                 value_alignment: ALIGNMENT,
-                key_type: K::type_name(),
-                value_type: V::type_name(),
+                key_type,
+                value_type,
             },
             TableType::Multimap => InternalTableDefinition::Multimap {
                 table_root,
                 table_length,
-                fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_key_size,
+                fixed_value_size,
                 key_alignment: ALIGNMENT,
                 value_alignment: ALIGNMENT,
-                key_type: K::type_name(),
-                value_type: V::type_name(),
+                key_type,
+                value_type,
             },
         }
     }
@@ -133,18 +153,18 @@ impl InternalTableDefinition {
                 Err(TableError::TableIsNotMultimap(name.to_string()))
             };
         }
-        if self.private_get_key_alignment() != ALIGNMENT {
+        if self.get_key_alignment() != ALIGNMENT {
             return Err(TableError::TypeDefinitionChanged {
-                name: self.private_key_type(),
-                alignment: self.private_get_key_alignment(),
-                width: self.private_get_fixed_key_size(),
+                name: self.key_type(),
+                alignment: self.get_key_alignment(),
+                width: self.get_fixed_key_size(),
             });
         }
-        if self.private_get_value_alignment() != ALIGNMENT {
+        if self.get_value_alignment() != ALIGNMENT {
             return Err(TableError::TypeDefinitionChanged {
-                name: self.private_value_type(),
-                alignment: self.private_get_value_alignment(),
-                width: self.private_get_fixed_value_size(),
+                name: self.value_type(),
+                alignment: self.get_value_alignment(),
+                width: self.get_fixed_value_size(),
             });
         }
 
@@ -158,26 +178,47 @@ impl InternalTableDefinition {
     ) -> Result<(), TableError> {
         self.check_match_untyped(table_type, name)?;
 
-        if self.private_key_type() != K::type_name() || self.private_value_type() != V::type_name()
-        {
+        if self.key_type() != K::type_name() || self.value_type() != V::type_name() {
             return Err(TableError::TableTypeMismatch {
                 table: name.to_string(),
-                key: self.private_key_type(),
-                value: self.private_value_type(),
+                key: self.key_type(),
+                value: self.value_type(),
             });
         }
-        if self.private_get_fixed_key_size() != K::fixed_width() {
+        if self.get_fixed_key_size() != K::fixed_width() {
             return Err(TableError::TypeDefinitionChanged {
                 name: K::type_name(),
-                alignment: self.private_get_key_alignment(),
-                width: self.private_get_fixed_key_size(),
+                alignment: self.get_key_alignment(),
+                width: self.get_fixed_key_size(),
             });
         }
-        if self.private_get_fixed_value_size() != V::fixed_width() {
+        if self.get_fixed_value_size() != V::fixed_width() {
             return Err(TableError::TypeDefinitionChanged {
                 name: V::type_name(),
-                alignment: self.private_get_value_alignment(),
-                width: self.private_get_fixed_value_size(),
+                alignment: self.get_value_alignment(),
+                width: self.get_fixed_value_size(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Checks that a table named `other_name` with the definition `other` stores the same key and
+    // value types as this table
+    pub(super) fn check_same_types(
+        &self,
+        other: &InternalTableDefinition,
+        other_name: &str,
+    ) -> Result<(), TableError> {
+        if self.key_type() != other.key_type()
+            || self.value_type() != other.value_type()
+            || self.get_fixed_key_size() != other.get_fixed_key_size()
+            || self.get_fixed_value_size() != other.get_fixed_value_size()
+        {
+            return Err(TableError::TableTypeMismatch {
+                table: other_name.to_string(),
+                key: other.key_type(),
+                value: other.value_type(),
             });
         }
 
@@ -219,7 +260,7 @@ impl InternalTableDefinition {
         freed_pages: Arc<Mutex<Vec<PageNumber>>>,
         relocation_map: &HashMap<PageNumber, PageNumber>,
     ) -> Result<Option<Option<BtreeHeader>>> {
-        let original_root = self.private_get_root();
+        let original_root = self.get_root();
         let relocated_root = match self {
             InternalTableDefinition::Normal { table_root, .. } => *table_root,
             InternalTableDefinition::Multimap {
@@ -247,8 +288,8 @@ impl InternalTableDefinition {
             relocated_root,
             mem,
             freed_pages,
-            self.private_get_fixed_key_size(),
-            self.private_get_fixed_value_size(),
+            self.get_fixed_key_size(),
+            self.get_fixed_value_size(),
         );
         tree.relocate(relocation_map)?;
         if tree.get_root() != original_root {
@@ -259,7 +300,7 @@ impl InternalTableDefinition {
         }
     }
 
-    fn private_get_root(&self) -> Option<BtreeHeader> {
+    pub(crate) fn get_root(&self) -> Option<BtreeHeader> {
         match self {
             InternalTableDefinition::Normal { table_root, .. } => *table_root,
             InternalTableDefinition::Multimap { table_root, .. } => *table_root,
@@ -273,14 +314,14 @@ impl InternalTableDefinition {
         }
     }
 
-    fn private_get_fixed_key_size(&self) -> Option<usize> {
+    pub(crate) fn get_fixed_key_size(&self) -> Option<usize> {
         match self {
             InternalTableDefinition::Normal { fixed_key_size, .. } => *fixed_key_size,
             InternalTableDefinition::Multimap { fixed_key_size, .. } => *fixed_key_size,
         }
     }
 
-    fn private_get_fixed_value_size(&self) -> Option<usize> {
+    pub(crate) fn get_fixed_value_size(&self) -> Option<usize> {
         match self {
             InternalTableDefinition::Normal {
                 fixed_value_size, ..
@@ -291,14 +332,14 @@ impl InternalTableDefinition {
         }
     }
 
-    fn private_get_key_alignment(&self) -> usize {
+    fn get_key_alignment(&self) -> usize {
         match self {
             InternalTableDefinition::Normal { key_alignment, .. } => *key_alignment,
             InternalTableDefinition::Multimap { key_alignment, .. } => *key_alignment,
         }
     }
 
-    fn private_get_value_alignment(&self) -> usize {
+    fn get_value_alignment(&self) -> usize {
         match self {
             InternalTableDefinition::Normal {
                 value_alignment, ..
@@ -316,14 +357,14 @@ impl InternalTableDefinition {
         }
     }
 
-    fn private_key_type(&self) -> TypeName {
+    pub(crate) fn key_type(&self) -> TypeName {
         match self {
             InternalTableDefinition::Normal { key_type, .. } => key_type.clone(),
             InternalTableDefinition::Multimap { key_type, .. } => key_type.clone(),
         }
     }
 
-    fn private_value_type(&self) -> TypeName {
+    pub(crate) fn value_type(&self) -> TypeName {
         match self {
             InternalTableDefinition::Normal { value_type, .. } => value_type.clone(),
             InternalTableDefinition::Multimap { value_type, .. } => value_type.clone(),
@@ -451,21 +492,21 @@ impl Value for InternalTableDefinition {
     {
         let mut result = vec![value.get_type().into()];
         result.extend_from_slice(&value.get_length().to_le_bytes());
-        if let Some(header) = value.private_get_root() {
+        if let Some(header) = value.get_root() {
             result.push(1);
             result.extend_from_slice(&header.to_le_bytes());
         } else {
             result.push(0);
             result.extend_from_slice(&[0; BtreeHeader::serialized_size()]);
         }
-        if let Some(fixed) = value.private_get_fixed_key_size() {
+        if let Some(fixed) = value.get_fixed_key_size() {
             result.push(1);
             result.extend_from_slice(&u32::try_from(fixed).unwrap().to_le_bytes());
         } else {
             result.push(0);
             result.extend_from_slice(&[0; size_of::<u32>()])
         }
-        if let Some(fixed) = value.private_get_fixed_value_size() {
+        if let Some(fixed) = value.get_fixed_value_size() {
             result.push(1);
             result.extend_from_slice(&u32::try_from(fixed).unwrap().to_le_bytes());
         } else {
@@ -473,19 +514,19 @@ impl Value for InternalTableDefinition {
             result.extend_from_slice(&[0; size_of::<u32>()])
         }
         result.extend_from_slice(
-            &u32::try_from(value.private_get_key_alignment())
+            &u32::try_from(value.get_key_alignment())
                 .unwrap()
                 .to_le_bytes(),
         );
         result.extend_from_slice(
-            &u32::try_from(value.private_get_value_alignment())
+            &u32::try_from(value.get_value_alignment())
                 .unwrap()
                 .to_le_bytes(),
         );
-        let key_type_bytes = value.private_key_type().to_bytes();
+        let key_type_bytes = value.key_type().to_bytes();
         result.extend_from_slice(&u32::try_from(key_type_bytes.len()).unwrap().to_le_bytes());
         result.extend_from_slice(&key_type_bytes);
-        result.extend_from_slice(&value.private_value_type().to_bytes());
+        result.extend_from_slice(&value.value_type().to_bytes());
 
         result
     }
@@ -26,6 +26,16 @@ fn serialize_tuple_elements_fixed(slices: &[&[u8]]) -> Vec<u8> {
     output
 }
 
+// Serializes the elements of a tuple, or the fields of a type implemented with redb-derive
+#[doc(hidden)]
+pub fn serialize_tuple_elements(fixed_width: bool, slices: &[&[u8]]) -> Vec<u8> {
+    if fixed_width {
+        serialize_tuple_elements_fixed(slices)
+    } else {
+        serialize_tuple_elements_variable(slices)
+    }
+}
+
 fn parse_lens<const N: usize>(data: &[u8]) -> [usize; N] {
     let mut result = [0; N];
     for i in 0..N {
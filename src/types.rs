@@ -1,7 +1,11 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::convert::TryInto;
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::mem::size_of;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 enum TypeClassification {
@@ -66,7 +70,8 @@ impl TypeName {
         }
     }
 
-    pub(crate) fn name(&self) -> &str {
+    /// The name of the type
+    pub fn name(&self) -> &str {
         &self.name
     }
 }
@@ -278,6 +283,51 @@ impl<T: Key> Key for Option<T> {
     }
 }
 
+/// Orders keys of type `K` in reverse
+///
+/// Values are read and written as `K`, so `Reverse` can be used to iterate a table in descending
+/// order, or in a tuple to make one of its elements descending, such as `(u64, Reverse<u64>)`.
+#[derive(Debug)]
+pub struct Reverse<K>(PhantomData<K>);
+
+impl<K: Key> Value for Reverse<K> {
+    type SelfType<'a> = K::SelfType<'a>
+    where
+        Self: 'a;
+    type AsBytes<'a> = K::AsBytes<'a>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        K::fixed_width()
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> K::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        K::from_bytes(data)
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> K::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        K::as_bytes(value)
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal(&format!("redb::Reverse<{}>", K::type_name().name()))
+    }
+}
+
+impl<K: Key> Key for Reverse<K> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        K::compare(data2, data1)
+    }
+}
+
 impl Value for &[u8] {
     type SelfType<'a> = &'a [u8]
     where
@@ -529,6 +579,84 @@ impl Key for String {
     }
 }
 
+impl Value for Box<[u8]> {
+    type SelfType<'a> = Box<[u8]>
+    where
+        Self: 'a;
+    type AsBytes<'a> = &'a [u8]
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Box<[u8]>
+    where
+        Self: 'a,
+    {
+        data.into()
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> &'a [u8]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value
+    }
+
+    fn type_name() -> TypeName {
+        // Uses the same type name as &[u8] so that tables are compatible with &[u8] types
+        TypeName::internal("&[u8]")
+    }
+}
+
+impl Key for Box<[u8]> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
+
+impl Value for Cow<'_, str> {
+    type SelfType<'a> = Cow<'a, str>
+    where
+        Self: 'a;
+    type AsBytes<'a> = &'a str
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Cow<'a, str>
+    where
+        Self: 'a,
+    {
+        Cow::Borrowed(std::str::from_utf8(data).unwrap())
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> &'a str
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value
+    }
+
+    fn type_name() -> TypeName {
+        // Uses the same type name as &str so that tables are compatible with &str types
+        TypeName::internal("&str")
+    }
+}
+
+impl Key for Cow<'_, str> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        <&str>::compare(data1, data2)
+    }
+}
+
 impl Value for char {
     type SelfType<'a> = char;
     type AsBytes<'a> = [u8; 3] where Self: 'a;
@@ -620,5 +748,236 @@ le_impl!(i16);
 le_impl!(i32);
 le_impl!(i64);
 le_impl!(i128);
-le_value!(f32);
-le_value!(f64);
+
+macro_rules! float_impl {
+    ($t:ty) => {
+        le_value!($t);
+
+        // Floats are ordered by the IEEE 754 totalOrder predicate, so that NaN has a consistent
+        // position, and -0.0 is ordered before +0.0
+        impl Key for $t {
+            fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+                Self::from_bytes(data1).total_cmp(&Self::from_bytes(data2))
+            }
+        }
+    };
+}
+
+float_impl!(f32);
+float_impl!(f64);
+
+impl Value for Duration {
+    type SelfType<'a> = Duration;
+    type AsBytes<'a> = [u8; 12] where Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        Some(12)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Duration
+    where
+        Self: 'a,
+    {
+        let secs = u64::from_le_bytes(data[..8].try_into().unwrap());
+        let nanos = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        Duration::new(secs, nanos)
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> [u8; 12]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut result = [0; 12];
+        result[..8].copy_from_slice(&value.as_secs().to_le_bytes());
+        result[8..].copy_from_slice(&value.subsec_nanos().to_le_bytes());
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("Duration")
+    }
+}
+
+impl Key for Duration {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        Self::from_bytes(data1).cmp(&Self::from_bytes(data2))
+    }
+}
+
+// SystemTime is stored as the number of whole seconds since the Unix epoch, rounded down, followed
+// by the nanoseconds past that second, so that times before the epoch can be represented
+impl Value for SystemTime {
+    type SelfType<'a> = SystemTime;
+    type AsBytes<'a> = [u8; 12] where Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        Some(12)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> SystemTime
+    where
+        Self: 'a,
+    {
+        let secs = i64::from_le_bytes(data[..8].try_into().unwrap());
+        let nanos = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        let whole_secs = if secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(secs.unsigned_abs())
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+        };
+        whole_secs + Duration::from_nanos(nanos.into())
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> [u8; 12]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let (secs, nanos): (i64, u32) = match value.duration_since(UNIX_EPOCH) {
+            Ok(after) => (after.as_secs().try_into().unwrap(), after.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                let secs: i64 = before.as_secs().try_into().unwrap();
+                if before.subsec_nanos() == 0 {
+                    (-secs, 0)
+                } else {
+                    (-secs - 1, 1_000_000_000 - before.subsec_nanos())
+                }
+            }
+        };
+        let mut result = [0; 12];
+        result[..8].copy_from_slice(&secs.to_le_bytes());
+        result[8..].copy_from_slice(&nanos.to_le_bytes());
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("SystemTime")
+    }
+}
+
+impl Key for SystemTime {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        Self::from_bytes(data1).cmp(&Self::from_bytes(data2))
+    }
+}
+
+impl Value for Ipv4Addr {
+    type SelfType<'a> = Ipv4Addr;
+    type AsBytes<'a> = [u8; 4] where Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        Some(4)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Ipv4Addr
+    where
+        Self: 'a,
+    {
+        <[u8; 4]>::try_from(data).unwrap().into()
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> [u8; 4]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value.octets()
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("Ipv4Addr")
+    }
+}
+
+impl Key for Ipv4Addr {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
+
+impl Value for Ipv6Addr {
+    type SelfType<'a> = Ipv6Addr;
+    type AsBytes<'a> = [u8; 16] where Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        Some(16)
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Ipv6Addr
+    where
+        Self: 'a,
+    {
+        <[u8; 16]>::try_from(data).unwrap().into()
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> [u8; 16]
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value.octets()
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("Ipv6Addr")
+    }
+}
+
+impl Key for Ipv6Addr {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
+
+// IpAddr is stored as a tag byte, 4 for V4 and 6 for V6, followed by the octets of the address
+impl Value for IpAddr {
+    type SelfType<'a> = IpAddr;
+    type AsBytes<'a> = Vec<u8> where Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> IpAddr
+    where
+        Self: 'a,
+    {
+        match data[0] {
+            4 => IpAddr::V4(Ipv4Addr::from_bytes(&data[1..])),
+            6 => IpAddr::V6(Ipv6Addr::from_bytes(&data[1..])),
+            _ => unreachable!(),
+        }
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Vec<u8>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let mut result = vec![];
+        match value {
+            IpAddr::V4(addr) => {
+                result.push(4);
+                result.extend_from_slice(&addr.octets());
+            }
+            IpAddr::V6(addr) => {
+                result.push(6);
+                result.extend_from_slice(&addr.octets());
+            }
+        }
+        result
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::internal("IpAddr")
+    }
+}
+
+impl Key for IpAddr {
+    // V4 addresses are ordered before V6 addresses, by their tag, and then by their octets
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
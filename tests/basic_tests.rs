@@ -1,10 +1,14 @@
 use rand::random;
 use redb::backends::InMemoryBackend;
 use redb::{
-    Database, Key, MultimapTableDefinition, MultimapTableHandle, Range, ReadableTable,
-    ReadableTableMetadata, TableDefinition, TableError, TableHandle, TypeName, Value,
+    merge, BlobTableDefinition, BulkLoadError, Database, DatabaseError, DynValue, Entry, Error,
+    ImportError, Key, KeyOrderings, Memcmp, MultimapTableDefinition, MultimapTableHandle, Range,
+    ReadableTable, ReadableTableMetadata, Reverse, Schema, SchemaError, StorageError,
+    TableDefinition, TableError, TableHandle, TypeName, Value,
 };
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
 #[cfg(not(target_os = "wasi"))]
 use std::sync;
 
@@ -861,6 +865,351 @@ fn insert_overwrite() {
     assert_eq!("replaced", table.get("hello").unwrap().unwrap().value());
 }
 
+#[test]
+fn entry() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for _ in 0..3 {
+            let value = table
+                .entry(0)
+                .unwrap()
+                .and_modify(|x| x + 1)
+                .unwrap()
+                .or_insert(1)
+                .unwrap();
+            assert!(value.value() <= 3);
+        }
+        assert_eq!(table.get(0).unwrap().unwrap().value(), 3);
+
+        let value = table.entry(1).unwrap().or_insert_with(|| 10).unwrap();
+        assert_eq!(value.value(), 10);
+        drop(value);
+        let value = table.entry(1).unwrap().or_insert_with(|| 20).unwrap();
+        assert_eq!(value.value(), 10);
+        drop(value);
+
+        match table.entry(2).unwrap() {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), 2);
+                assert_eq!(entry.insert(5).unwrap().value(), 5);
+            }
+        }
+        match table.entry(2).unwrap() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), 2);
+                assert_eq!(entry.get(), 5);
+                assert_eq!(entry.insert(6).unwrap().value(), 5);
+                assert_eq!(entry.get(), 6);
+                assert_eq!(entry.remove().unwrap().value(), 6);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(table.get(2).unwrap().is_none());
+        assert_eq!(table.len().unwrap(), 2);
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), 3);
+    assert_eq!(table.get(1).unwrap().unwrap().value(), 10);
+}
+
+#[test]
+fn entry_large_values() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let definition: TableDefinition<u64, Vec<u8>> = TableDefinition::new("x");
+    let large = vec![1u8; 100_000];
+    let small = vec![2u8; 100];
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for i in 0..1000u64 {
+            let value = if i % 100 == 0 { &large } else { &small };
+            let inserted = table.entry(i).unwrap().or_insert(value).unwrap();
+            assert_eq!(&inserted.value(), value);
+        }
+        for i in (0..1000u64).step_by(50) {
+            match table.entry(i).unwrap() {
+                Entry::Occupied(mut entry) => {
+                    let (old_value, new_value) = if i % 100 == 0 {
+                        (&large, &small)
+                    } else {
+                        (&small, &large)
+                    };
+                    assert_eq!(&entry.insert(new_value).unwrap().value(), old_value);
+                    assert_eq!(&entry.get(), new_value);
+                }
+                Entry::Vacant(_) => unreachable!(),
+            }
+        }
+        let value = table
+            .entry(50)
+            .unwrap()
+            .and_modify(|value| value[..10].to_vec())
+            .unwrap()
+            .or_insert(vec![])
+            .unwrap();
+        assert_eq!(value.value(), &large[..10]);
+        drop(value);
+        for i in (0..1000u64).step_by(3) {
+            match table.entry(i).unwrap() {
+                Entry::Occupied(entry) => {
+                    entry.remove().unwrap();
+                }
+                Entry::Vacant(_) => unreachable!(),
+            }
+        }
+        assert_eq!(table.len().unwrap(), 666);
+    }
+    write_txn.commit().unwrap();
+
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+fn conditional_insert() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        assert!(table.insert_if_absent("hello", "world").unwrap());
+        assert!(!table.insert_if_absent("hello", "world2").unwrap());
+        assert_eq!(table.get("hello").unwrap().unwrap().value(), "world");
+
+        assert!(!table.compare_and_swap("hello", None, "world2").unwrap());
+        assert!(!table
+            .compare_and_swap("hello", Some(&"world2"), "world3")
+            .unwrap());
+        assert!(table
+            .compare_and_swap("hello", Some(&"world"), "world3")
+            .unwrap());
+        assert_eq!(table.get("hello").unwrap().unwrap().value(), "world3");
+
+        assert!(!table
+            .compare_and_swap("hi", Some(&"world"), "world")
+            .unwrap());
+        assert!(table.compare_and_swap("hi", None, "world").unwrap());
+        assert_eq!(table.get("hi").unwrap().unwrap().value(), "world");
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn update() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for _ in 0..3 {
+            table.update(0, |x| Some(x.unwrap_or(0) + 1)).unwrap();
+        }
+        assert_eq!(table.get(0).unwrap().unwrap().value(), 3);
+
+        let old = table.update(0, |x| x.filter(|x| *x < 3)).unwrap();
+        assert_eq!(old.unwrap().value(), 3);
+        assert!(table.get(0).unwrap().is_none());
+        assert!(table.update(1, |_| None::<u64>).unwrap().is_none());
+        assert!(table.is_empty().unwrap());
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn update_large_table() {
+    const TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("x");
+
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE).unwrap();
+        for i in 0..1000u64 {
+            let value = i.to_le_bytes();
+            table.update(i, |_| Some(value.as_slice())).unwrap();
+        }
+        let big = vec![7u8; 100_000];
+        for i in (0..1000u64).step_by(100) {
+            let old = table.update(i, |_| Some(big.as_slice())).unwrap();
+            assert_eq!(old.unwrap().value(), i.to_le_bytes());
+        }
+        for i in (0..1000u64).filter(|i| i % 3 == 0) {
+            table.update(i, |_| None::<&[u8]>).unwrap();
+        }
+        assert_eq!(table.len().unwrap(), 666);
+        for i in 0..1000u64 {
+            let value = table.get(i).unwrap();
+            if i % 3 == 0 {
+                assert!(value.is_none());
+            } else if i % 100 == 0 {
+                assert_eq!(value.unwrap().value(), big.as_slice());
+            } else {
+                assert_eq!(value.unwrap().value(), i.to_le_bytes());
+            }
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 666);
+    drop(table);
+    drop(read_txn);
+    let mut db = db;
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+fn merge() {
+    const ADD_TABLE: TableDefinition<&str, u64> =
+        TableDefinition::new("add").with_merge_operator(&merge::Add);
+    const MAX_TABLE: TableDefinition<u64, i32> =
+        TableDefinition::new("max").with_merge_operator(&merge::Max);
+    const APPEND_TABLE: TableDefinition<u64, &str> =
+        TableDefinition::new("append").with_merge_operator(&merge::Append);
+
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(ADD_TABLE).unwrap();
+        table.merge("a", 1).unwrap();
+        table.merge("a", 2).unwrap();
+        table.merge("b", 5).unwrap();
+        assert_eq!(table.get("a").unwrap().unwrap().value(), 3);
+        assert_eq!(table.get("b").unwrap().unwrap().value(), 5);
+
+        let mut table = write_txn.open_table(MAX_TABLE).unwrap();
+        for x in [-5, 3, -1, 2] {
+            table.merge(0, x).unwrap();
+        }
+        assert_eq!(table.get(0).unwrap().unwrap().value(), 3);
+
+        let mut table = write_txn.open_table(APPEND_TABLE).unwrap();
+        table.merge(0, "hello").unwrap();
+        table.merge(0, " world").unwrap();
+        assert_eq!(table.get(0).unwrap().unwrap().value(), "hello world");
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn merge_without_operator() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    let mut table = write_txn.open_table(U64_TABLE).unwrap();
+    assert!(matches!(
+        table.merge(0, 1),
+        Err(StorageError::MissingMergeOperator(_))
+    ));
+    assert!(table.is_empty().unwrap());
+}
+
+#[test]
+fn bulk_load() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.bulk_load((0..10_000u64).map(|i| (i, i * 2))).unwrap();
+        assert_eq!(table.len().unwrap(), 10_000);
+        assert!(matches!(
+            table.bulk_load([(0u64, 0u64)]),
+            Err(BulkLoadError::TableNotEmpty(_))
+        ));
+
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        let large = vec![1u8; 100_000];
+        let result = table.bulk_load([
+            (b"a".as_slice(), large.as_slice()),
+            (b"c".as_slice(), b"c".as_slice()),
+            (b"b".as_slice(), b"b".as_slice()),
+        ]);
+        assert!(matches!(result, Err(BulkLoadError::UnsortedInput)));
+        assert!(table.is_empty().unwrap());
+        let result = table.bulk_load([
+            (b"a".as_slice(), b"a".as_slice()),
+            (b"a".as_slice(), b"a".as_slice()),
+        ]);
+        assert!(matches!(result, Err(BulkLoadError::UnsortedInput)));
+
+        table
+            .bulk_load([
+                (b"a".as_slice(), large.as_slice()),
+                (b"b".as_slice(), b"b".as_slice()),
+            ])
+            .unwrap();
+        table.insert(b"c".as_slice(), b"c".as_slice()).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(U64_TABLE).unwrap();
+        assert_eq!(table.len().unwrap(), 10_000);
+        for (i, entry) in table.iter().unwrap().enumerate() {
+            let (key, value) = entry.unwrap();
+            assert_eq!(key.value(), i as u64);
+            assert_eq!(value.value(), i as u64 * 2);
+        }
+        assert_eq!(table.get(1234).unwrap().unwrap().value(), 2468);
+        let table = read_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(table.len().unwrap(), 3);
+        assert_eq!(
+            table.get(b"a".as_slice()).unwrap().unwrap().value().len(),
+            100_000
+        );
+    }
+
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+fn get_many() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let keys: Vec<u64> = (0..1000).map(|_| random::<u64>() % 20_000).collect();
+    let expected: Vec<Option<u64>> = keys
+        .iter()
+        .map(|x| if x % 2 == 0 { Some(x * 3) } else { None })
+        .collect();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in (0..20_000u64).step_by(2) {
+            table.insert(i, i * 3).unwrap();
+        }
+        let values: Vec<Option<u64>> = table
+            .get_many(&keys)
+            .unwrap()
+            .iter()
+            .map(|x| x.as_ref().map(|x| x.value()))
+            .collect();
+        assert_eq!(values, expected);
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let values: Vec<Option<u64>> = table
+        .get_many(&keys)
+        .unwrap()
+        .iter()
+        .map(|x| x.as_ref().map(|x| x.value()))
+        .collect();
+    assert_eq!(values, expected);
+    assert!(table.get_many([0u64; 0]).unwrap().is_empty());
+}
+
 #[test]
 fn insert_reserve() {
     let tmpfile = create_tempfile();
@@ -1073,7 +1422,7 @@ fn u64_type() {
             .range(0..2)
             .unwrap()
             .map(|item| item.unwrap().1.value())
-            .sum()
+            .sum::<u64>()
     );
     assert_eq!(1, table.get(&0).unwrap().unwrap().value());
 }
@@ -1298,33 +1647,190 @@ fn vec_fixed_width_value_type() {
 }
 
 #[test]
-fn vec_var_width_value_type() {
+fn dyn_value_type() {
     let tmpfile = create_tempfile();
     let db = Database::create(tmpfile.path()).unwrap();
 
-    let definition: TableDefinition<u8, Vec<&str>> = TableDefinition::new("x");
+    let definition: TableDefinition<DynValue, DynValue> = TableDefinition::new("x");
+
+    let mut document = BTreeMap::new();
+    document.insert("name".to_string(), DynValue::from("hello\0world"));
+    document.insert(
+        "tags".to_string(),
+        DynValue::List(vec![DynValue::Null, true.into(), (-1.5).into()]),
+    );
+    document.insert("data".to_string(), DynValue::from([0u8, 255, 0].as_slice()));
+    let document = DynValue::Map(document);
+
+    // In the order that they should be sorted
+    let keys = vec![
+        DynValue::Null,
+        DynValue::Bool(false),
+        DynValue::Bool(true),
+        DynValue::Integer(i64::MIN),
+        DynValue::Integer(-1),
+        DynValue::Integer(0),
+        DynValue::Integer(i64::MAX),
+        DynValue::Float(f64::NEG_INFINITY),
+        DynValue::Float(-0.0),
+        DynValue::Float(0.0),
+        DynValue::Float(f64::INFINITY),
+        DynValue::from(""),
+        DynValue::from("a"),
+        DynValue::from("a\0"),
+        DynValue::from("b"),
+        DynValue::from([0u8].as_slice()),
+        DynValue::List(vec![]),
+        DynValue::List(vec![DynValue::from("a"), DynValue::Integer(5)]),
+        DynValue::List(vec![DynValue::from("a\0")]),
+        DynValue::Map(BTreeMap::new()),
+        document.clone(),
+    ];
 
-    let value = vec!["hello", "world"];
     let write_txn = db.begin_write().unwrap();
     {
         let mut table = write_txn.open_table(definition).unwrap();
-        table.insert(0, &value).unwrap();
+        for key in keys.iter().rev() {
+            table.insert(key, &document).unwrap();
+        }
     }
     write_txn.commit().unwrap();
 
     let read_txn = db.begin_read().unwrap();
     let table = read_txn.open_table(definition).unwrap();
-    assert_eq!(value, table.get(0).unwrap().unwrap().value());
+    let stored: Vec<DynValue> = table
+        .iter()
+        .unwrap()
+        .map(|x| {
+            let (key, value) = x.unwrap();
+            assert_eq!(value.value(), document);
+            key.value()
+        })
+        .collect();
+    assert_eq!(stored, keys);
 }
 
+#[cfg(all(feature = "bincode", feature = "json", feature = "postcard"))]
 #[test]
-fn vec_vec_type() {
+fn serde_value_types() {
+    use redb::serde::{Bincode, Json, Postcard, Versioned};
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct User {
+        name: String,
+        age: u32,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct OldUser {
+        name: String,
+    }
+
     let tmpfile = create_tempfile();
     let db = Database::create(tmpfile.path()).unwrap();
 
-    let definition: TableDefinition<u8, Vec<Vec<&str>>> = TableDefinition::new("x");
+    let bincode_def: TableDefinition<u64, Bincode<User>> = TableDefinition::new("bincode");
+    let json_def: TableDefinition<u64, Json<User>> = TableDefinition::new("json");
+    let postcard_def: TableDefinition<u64, Postcard<User>> = TableDefinition::new("postcard");
+    let versioned_def: TableDefinition<u64, Versioned<Bincode<User>>> =
+        TableDefinition::new("versioned");
+    let raw_def: TableDefinition<u64, Versioned<&[u8]>> = TableDefinition::new("raw");
 
-    let value = vec![vec!["hello", "world"], vec!["this", "is", "a", "test"]];
+    let user = User {
+        name: "alice".to_string(),
+        age: 30,
+    };
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        write_txn
+            .open_table(bincode_def)
+            .unwrap()
+            .insert(0, &user)
+            .unwrap();
+        write_txn
+            .open_table(json_def)
+            .unwrap()
+            .insert(0, &user)
+            .unwrap();
+        write_txn
+            .open_table(postcard_def)
+            .unwrap()
+            .insert(0, &user)
+            .unwrap();
+        write_txn
+            .open_table(versioned_def)
+            .unwrap()
+            .insert(0, &(1, user.clone()))
+            .unwrap();
+
+        // Values written with different versions of the encoding
+        let old_user = bincode::serialize(&OldUser {
+            name: "bob".to_string(),
+        })
+        .unwrap();
+        let new_user = bincode::serialize(&user).unwrap();
+        let mut table = write_txn.open_table(raw_def).unwrap();
+        table.insert(0, (1, old_user.as_slice())).unwrap();
+        table.insert(1, (2, new_user.as_slice())).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(bincode_def).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), user);
+    let table = read_txn.open_table(json_def).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), user);
+    let table = read_txn.open_table(postcard_def).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), user);
+    let table = read_txn.open_table(versioned_def).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), (1, user.clone()));
+
+    let bytes = <Versioned<Bincode<User>>>::as_bytes(&(1, user.clone()));
+    assert_eq!(bytes[0], 1);
+    assert_eq!(&bytes[1..], <Bincode<User>>::as_bytes(&user).as_slice());
+
+    // Each value is decoded according to the version it was written with
+    let table = read_txn.open_table(raw_def).unwrap();
+    let decoded: Vec<User> = table
+        .iter()
+        .unwrap()
+        .map(|entry| {
+            let (_, value) = entry.unwrap();
+            let (version, data) = value.value();
+            match version {
+                1 => {
+                    let old: OldUser = bincode::deserialize(data).unwrap();
+                    User {
+                        name: old.name,
+                        age: 0,
+                    }
+                }
+                2 => bincode::deserialize(data).unwrap(),
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![
+            User {
+                name: "bob".to_string(),
+                age: 0
+            },
+            user
+        ]
+    );
+}
+
+#[test]
+fn vec_var_width_value_type() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<u8, Vec<&str>> = TableDefinition::new("x");
+
+    let value = vec!["hello", "world"];
     let write_txn = db.begin_write().unwrap();
     {
         let mut table = write_txn.open_table(definition).unwrap();
@@ -1337,88 +1843,329 @@ fn vec_vec_type() {
     assert_eq!(value, table.get(0).unwrap().unwrap().value());
 }
 
-#[test]
-fn range_lifetime() {
+// Inserts the keys in reverse, and checks that they're iterated in the order given
+fn check_key_order<K: Key + 'static>(keys: &[K::SelfType<'_>]) {
     let tmpfile = create_tempfile();
     let db = Database::create(tmpfile.path()).unwrap();
-
-    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+    let definition: TableDefinition<K, u64> = TableDefinition::new("x");
 
     let write_txn = db.begin_write().unwrap();
     {
         let mut table = write_txn.open_table(definition).unwrap();
-        table.insert("hello", "world").unwrap();
+        for (i, key) in keys.iter().enumerate().rev() {
+            table.insert(key, &(i as u64)).unwrap();
+        }
     }
     write_txn.commit().unwrap();
 
     let read_txn = db.begin_read().unwrap();
     let table = read_txn.open_table(definition).unwrap();
+    for (i, entry) in table.iter().unwrap().enumerate() {
+        assert_eq!(entry.unwrap().1.value(), i as u64);
+    }
+    assert_eq!(table.len().unwrap(), keys.len() as u64);
+}
 
-    let mut iter = {
-        let start = "hello".to_string();
-        table.range::<&str>(start.as_str()..).unwrap()
-    };
-    assert_eq!(iter.next().unwrap().unwrap().1.value(), "world");
-    assert!(iter.next().is_none());
+#[test]
+fn std_key_types() {
+    use std::borrow::Cow;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    check_key_order::<f64>(&[
+        f64::NEG_INFINITY,
+        -1.5,
+        -0.0,
+        0.0,
+        f64::MIN_POSITIVE,
+        2.5,
+        f64::INFINITY,
+    ]);
+    check_key_order::<f32>(&[-1.0, 0.0, 1.0]);
+    check_key_order::<Vec<&str>>(&[vec![], vec!["a"], vec!["a", ""], vec!["a", "b"], vec!["b"]]);
+    check_key_order::<Vec<u32>>(&[vec![1], vec![1, 0], vec![2]]);
+    check_key_order::<Duration>(&[
+        Duration::ZERO,
+        Duration::from_nanos(1),
+        Duration::from_secs(1),
+        Duration::new(1, 5),
+    ]);
+    check_key_order::<SystemTime>(&[
+        UNIX_EPOCH - Duration::new(10, 1),
+        UNIX_EPOCH - Duration::from_secs(10),
+        UNIX_EPOCH - Duration::from_nanos(1),
+        UNIX_EPOCH,
+        UNIX_EPOCH + Duration::new(1, 1),
+    ]);
+    for time in [
+        UNIX_EPOCH - Duration::new(3, 7),
+        UNIX_EPOCH + Duration::new(3, 7),
+    ] {
+        assert_eq!(SystemTime::from_bytes(&SystemTime::as_bytes(&time)), time);
+    }
+    check_key_order::<Ipv4Addr>(&[Ipv4Addr::new(9, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 1)]);
+    check_key_order::<Ipv6Addr>(&[Ipv6Addr::LOCALHOST, Ipv6Addr::new(1, 0, 0, 0, 0, 0, 0, 0)]);
+    check_key_order::<IpAddr>(&[
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V4(Ipv4Addr::BROADCAST),
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    ]);
+    check_key_order::<Box<[u8]>>(&[vec![].into(), vec![0].into(), vec![1].into()]);
+    check_key_order::<Cow<str>>(&[Cow::Borrowed("a"), Cow::Owned("b".to_string())]);
 }
 
 #[test]
-fn range_arc() {
+fn memcmp_key_type() {
     let tmpfile = create_tempfile();
     let db = Database::create(tmpfile.path()).unwrap();
 
-    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+    let definition: TableDefinition<Memcmp<(u32, &str, i64)>, u64> = TableDefinition::new("x");
+
+    // In the order that they should be sorted
+    let keys = [
+        (0, "", i64::MIN),
+        (0, "", -1),
+        (0, "", 0),
+        (0, "a", 5),
+        (0, "a\0", -5),
+        (0, "b", 0),
+        (1, "", 0),
+        (u32::MAX, "z", i64::MAX),
+    ];
 
     let write_txn = db.begin_write().unwrap();
     {
         let mut table = write_txn.open_table(definition).unwrap();
-        table.insert("hello", "world").unwrap();
+        for (i, key) in keys.iter().enumerate().rev() {
+            table.insert(key, &(i as u64)).unwrap();
+        }
+    }
+    {
+        // The key type is unknown, but keys are still ordered correctly
+        let table = write_txn.open_untyped_table(definition).unwrap();
+        let encoded: Vec<Vec<u8>> = table
+            .iter()
+            .unwrap()
+            .map(|x| x.unwrap().0.value().to_vec())
+            .collect();
+        let expected: Vec<Vec<u8>> = keys
+            .iter()
+            .map(<Memcmp<(u32, &str, i64)>>::as_bytes)
+            .collect();
+        assert_eq!(encoded, expected);
     }
     write_txn.commit().unwrap();
 
-    let mut iter = {
-        let read_txn = db.begin_read().unwrap();
-        let table = read_txn.open_table(definition).unwrap();
-        let start = "hello".to_string();
-        table.range::<&str>(start.as_str()..).unwrap()
-    };
-    assert_eq!(iter.next().unwrap().unwrap().1.value(), "world");
-    assert!(iter.next().is_none());
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    for (i, entry) in table.iter().unwrap().enumerate() {
+        let (key, value) = entry.unwrap();
+        assert_eq!(key.value(), keys[i]);
+        assert_eq!(value.value(), i as u64);
+    }
+    let start = (0, "a", i64::MIN);
+    let end = (1, "", 0);
+    let found: Vec<u64> = table
+        .range(start..end)
+        .unwrap()
+        .map(|x| x.unwrap().1.value())
+        .collect();
+    assert_eq!(found, vec![3, 4, 5]);
 }
 
 #[test]
-fn range_clone() {
+fn reverse_key_type() {
     let tmpfile = create_tempfile();
     let db = Database::create(tmpfile.path()).unwrap();
 
-    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+    let definition: TableDefinition<(&str, Reverse<u64>), u64> = TableDefinition::new("x");
 
     let write_txn = db.begin_write().unwrap();
     {
         let mut table = write_txn.open_table(definition).unwrap();
-        table.insert("hello", "world").unwrap();
-        let mut iter1 = table.iter().unwrap();
-        let mut iter2 = iter1.clone();
-        let (k1, v1) = iter1.next().unwrap().unwrap();
-        let (k2, v2) = iter2.next().unwrap().unwrap();
-        assert_eq!(k1.value(), k2.value());
-        assert_eq!(v1.value(), v2.value());
+        for (user, time) in [("a", 1), ("a", 3), ("b", 2), ("a", 2), ("b", 5)] {
+            table.insert((user, time), &time).unwrap();
+        }
     }
     write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let all: Vec<(String, u64)> = table
+        .iter()
+        .unwrap()
+        .map(|x| {
+            let (key, _) = x.unwrap();
+            let (user, time) = key.value();
+            (user.to_string(), time)
+        })
+        .collect();
+    assert_eq!(
+        all,
+        vec![
+            ("a".to_string(), 3),
+            ("a".to_string(), 2),
+            ("a".to_string(), 1),
+            ("b".to_string(), 5),
+            ("b".to_string(), 2),
+        ]
+    );
+
+    // Newest first, starting at time 2
+    let found: Vec<u64> = table
+        .range(("a", 2)..=("a", 0))
+        .unwrap()
+        .map(|x| x.unwrap().1.value())
+        .collect();
+    assert_eq!(found, vec![2, 1]);
+
+    assert_eq!(
+        <Reverse<u64>>::compare(&1u64.to_le_bytes(), &2u64.to_le_bytes()),
+        Ordering::Greater
+    );
+    let key1 = <Memcmp<(u8, Reverse<&str>)>>::as_bytes(&(0, "a"));
+    let key2 = <Memcmp<(u8, Reverse<&str>)>>::as_bytes(&(0, "ab"));
+    assert!(key1 > key2);
+    assert_eq!(<Memcmp<(u8, Reverse<&str>)>>::from_bytes(&key2), (0, "ab"));
 }
 
+#[cfg(feature = "compression")]
 #[test]
-fn custom_ordering() {
-    #[derive(Debug)]
-    struct ReverseKey(Vec<u8>);
+fn compressed_value_type() {
+    use redb::Compressed;
 
-    impl Value for ReverseKey {
-        type SelfType<'a> = ReverseKey
-        where
-        Self: 'a;
-        type AsBytes<'a> = &'a [u8]
-        where
-        Self: 'a;
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<u64, Compressed<String>> = TableDefinition::new("x");
+
+    let large = "hello world ".repeat(100);
+    let small = "hello".to_string();
+    let compressed = <Compressed<String>>::as_bytes(&large);
+    assert!(compressed.len() < large.len() / 5);
+    // Below the minimum size, values are stored uncompressed after the header byte
+    assert_eq!(
+        <Compressed<String>>::as_bytes(&small).len(),
+        small.len() + 1
+    );
+    assert!(<Compressed<String, 0>>::as_bytes(&"a".repeat(100)).len() < 100);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(0, &large).unwrap();
+        table.insert(1, &small).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), large);
+    assert_eq!(table.get(1).unwrap().unwrap().value(), small);
+}
+
+#[test]
+fn vec_vec_type() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<u8, Vec<Vec<&str>>> = TableDefinition::new("x");
+
+    let value = vec![vec!["hello", "world"], vec!["this", "is", "a", "test"]];
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(0, &value).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(value, table.get(0).unwrap().unwrap().value());
+}
+
+#[test]
+fn range_lifetime() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert("hello", "world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+
+    let mut iter = {
+        let start = "hello".to_string();
+        table.range::<&str>(start.as_str()..).unwrap()
+    };
+    assert_eq!(iter.next().unwrap().unwrap().1.value(), "world");
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn range_arc() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert("hello", "world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut iter = {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(definition).unwrap();
+        let start = "hello".to_string();
+        table.range::<&str>(start.as_str()..).unwrap()
+    };
+    assert_eq!(iter.next().unwrap().unwrap().1.value(), "world");
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn range_clone() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+
+    let definition: TableDefinition<&str, &str> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert("hello", "world").unwrap();
+        let mut iter1 = table.iter().unwrap();
+        let mut iter2 = iter1.clone();
+        let (k1, v1) = iter1.next().unwrap().unwrap();
+        let (k2, v2) = iter2.next().unwrap().unwrap();
+        assert_eq!(k1.value(), k2.value());
+        assert_eq!(v1.value(), v2.value());
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn custom_ordering() {
+    #[derive(Debug)]
+    struct ReverseKey(Vec<u8>);
+
+    impl Value for ReverseKey {
+        type SelfType<'a> = ReverseKey
+        where
+        Self: 'a;
+        type AsBytes<'a> = &'a [u8]
+        where
+        Self: 'a;
 
         fn fixed_width() -> Option<usize> {
             None
@@ -1475,6 +2222,25 @@ fn custom_ordering() {
         assert_eq!("value", value.value());
     }
     assert!(iter.next().is_none());
+    drop(iter);
+    drop(table);
+    drop(read_txn);
+
+    // The ordering of a user defined key type must be supplied to open it untyped
+    let write_txn = db.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.open_untyped_table(definition),
+        Err(TableError::UnorderableKeyType { .. })
+    ));
+    {
+        let mut table = write_txn
+            .open_untyped_table_ordered_by::<ReverseKey>(definition)
+            .unwrap();
+        table.insert(&[20], b"value").unwrap();
+        let (key, _) = table.iter().unwrap().next().unwrap().unwrap();
+        assert_eq!(key.value(), &[20]);
+    }
+    write_txn.commit().unwrap();
 }
 
 #[test]
@@ -1739,3 +2505,869 @@ fn u8_array_serialization() {
         assert_eq!(ref_order, generic_order);
     }
 }
+
+#[test]
+fn export_import() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..100_000u64 {
+            table.insert(i, i * 2).unwrap();
+        }
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+        table.insert("large", "x".repeat(10_000).as_str()).unwrap();
+        write_txn.open_table(SLICE_TABLE).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut data = vec![];
+    db.begin_read().unwrap().export(&mut data).unwrap();
+
+    let tmpfile2 = create_tempfile();
+    let db2 = Database::create(tmpfile2.path()).unwrap();
+    let write_txn = db2.begin_write().unwrap();
+    write_txn.import(data.as_slice()).unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db2.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 100_000);
+    for (i, entry) in table.iter().unwrap().enumerate() {
+        let (key, value) = entry.unwrap();
+        assert_eq!(key.value(), i as u64);
+        assert_eq!(value.value(), i as u64 * 2);
+    }
+    assert_eq!(table.get(5000).unwrap().unwrap().value(), 10_000);
+    drop(table);
+    let table = read_txn.open_table(STR_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 2);
+    assert_eq!(table.get("hello").unwrap().unwrap().value(), "world");
+    assert_eq!(table.get("large").unwrap().unwrap().value().len(), 10_000);
+    drop(table);
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert!(table.is_empty().unwrap());
+    drop(table);
+    drop(read_txn);
+
+    // The imported tables can be modified
+    let write_txn = db2.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(100_000, 0).unwrap();
+        table.remove(0).unwrap();
+    }
+    write_txn.commit().unwrap();
+    let read_txn = db2.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 100_000);
+    assert_eq!(table.first().unwrap().unwrap().0.value(), 1);
+    drop(table);
+    drop(read_txn);
+
+    let mut db2 = db2;
+    assert!(db2.check_integrity().unwrap());
+}
+
+#[test]
+fn import_errors() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut data = vec![];
+    db.begin_read().unwrap().export(&mut data).unwrap();
+
+    // Importing into a database which already contains the table fails
+    let write_txn = db.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.import(data.as_slice()).unwrap_err(),
+        ImportError::TableAlreadyExists(_)
+    ));
+    write_txn.abort().unwrap();
+
+    let tmpfile2 = create_tempfile();
+    let db2 = Database::create(tmpfile2.path()).unwrap();
+
+    // Corruption is detected by the checksums
+    let mut corrupted = data.clone();
+    let offset = corrupted.len() / 2;
+    corrupted[offset] ^= 0xFF;
+    let write_txn = db2.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.import(corrupted.as_slice()).unwrap_err(),
+        ImportError::InvalidFormat(_)
+    ));
+    write_txn.abort().unwrap();
+
+    // Truncated exports are rejected
+    let write_txn = db2.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.import(&data[..(data.len() - 1)]).unwrap_err(),
+        ImportError::InvalidFormat(_)
+    ));
+    write_txn.abort().unwrap();
+
+    let write_txn = db2.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.import(&b"not a redb export"[..]).unwrap_err(),
+        ImportError::InvalidFormat(_)
+    ));
+    write_txn.abort().unwrap();
+
+    // Tables whose key type is not builtin can be imported, and their order checked if it is
+    // provided
+    let tmpfile3 = create_tempfile();
+    let db3 = Database::create(tmpfile3.path()).unwrap();
+    let definition: TableDefinition<Reverse<u64>, u64> = TableDefinition::new("reverse");
+    let write_txn = db3.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for i in 0..100 {
+            table.insert(i, i).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+    let mut data = vec![];
+    db3.begin_read().unwrap().export(&mut data).unwrap();
+    let write_txn = db2.begin_write().unwrap();
+    write_txn.import(data.as_slice()).unwrap();
+    write_txn.abort().unwrap();
+    let mut orderings = KeyOrderings::new();
+    orderings.add::<Reverse<u64>>();
+    let write_txn = db2.begin_write().unwrap();
+    write_txn.import_with(data.as_slice(), &orderings).unwrap();
+    write_txn.commit().unwrap();
+    let read_txn = db2.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let keys: Vec<u64> = table
+        .iter()
+        .unwrap()
+        .map(|entry| entry.unwrap().0.value())
+        .collect();
+    assert_eq!(keys, (0..100).rev().collect::<Vec<u64>>());
+}
+
+#[test]
+fn rename_table() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let renamed: TableDefinition<&str, &str> = TableDefinition::new("renamed");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+        write_txn.open_table(U64_TABLE).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        // Uncommitted changes are carried over to the new name
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        table.insert("hello2", "world2").unwrap();
+    }
+    assert!(matches!(
+        write_txn.rename_table(STR_TABLE, U64_TABLE.name()),
+        Err(TableError::TableAlreadyExists(_))
+    ));
+    assert!(matches!(
+        write_txn.rename_table(SLICE_TABLE, "other"),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    let table = write_txn.open_table(U64_TABLE).unwrap();
+    assert!(matches!(
+        write_txn.rename_table(STR_TABLE, U64_TABLE.name()),
+        Err(TableError::TableAlreadyOpen(_, _))
+    ));
+    drop(table);
+    write_txn.rename_table(STR_TABLE, renamed.name()).unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(STR_TABLE),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    let table = read_txn.open_table(renamed).unwrap();
+    assert_eq!(table.len().unwrap(), 2);
+    assert_eq!(table.get("hello").unwrap().unwrap().value(), "world");
+    assert_eq!(table.get("hello2").unwrap().unwrap().value(), "world2");
+}
+
+#[test]
+fn swap_tables() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let other: TableDefinition<&str, &str> = TableDefinition::new("other");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+        let mut table = write_txn.open_table(other).unwrap();
+        table.insert("a", "b").unwrap();
+        table.insert("c", "d").unwrap();
+        write_txn.open_table(U64_TABLE).unwrap();
+    }
+    assert!(matches!(
+        write_txn.swap_tables(STR_TABLE, U64_TABLE),
+        Err(TableError::TableTypeMismatch { .. })
+    ));
+    write_txn.swap_tables(STR_TABLE, other).unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(STR_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 2);
+    assert_eq!(table.get("a").unwrap().unwrap().value(), "b");
+    let table = read_txn.open_table(other).unwrap();
+    assert_eq!(table.len().unwrap(), 1);
+    assert_eq!(table.get("hello").unwrap().unwrap().value(), "world");
+}
+
+#[test]
+fn replace_table() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let scratch: TableDefinition<u64, u64> = TableDefinition::new("scratch");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(i, i).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(scratch).unwrap();
+        for i in 0..10 {
+            table.insert(i, i + 1).unwrap();
+        }
+    }
+    let table = write_txn.open_table(U64_TABLE).unwrap();
+    assert!(matches!(
+        write_txn.replace_table(U64_TABLE, scratch),
+        Err(TableError::TableAlreadyOpen(_, _))
+    ));
+    drop(table);
+    write_txn.replace_table(U64_TABLE, scratch).unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(scratch),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 10);
+    assert_eq!(table.get(9).unwrap().unwrap().value(), 10);
+    drop(table);
+    drop(read_txn);
+
+    // The pages of the old table were freed
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+fn clone_table() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let clone: TableDefinition<u64, u64> = TableDefinition::new("clone");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10_000 {
+            table.insert(i, i).unwrap();
+        }
+    }
+    assert!(matches!(
+        write_txn.clone_table(U64_TABLE, clone),
+        Err(TableError::TableHasUncommittedChanges(_))
+    ));
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.clone_table(U64_TABLE, clone).unwrap();
+    assert!(matches!(
+        write_txn.clone_table(U64_TABLE, clone),
+        Err(TableError::TableAlreadyExists(_))
+    ));
+    {
+        let mut table = write_txn.open_table(clone).unwrap();
+        table.insert(0, 100).unwrap();
+        table.remove(5000).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(1, 101).unwrap();
+        table.insert(20_000, 0).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 10_001);
+    assert_eq!(table.get(0).unwrap().unwrap().value(), 0);
+    assert_eq!(table.get(1).unwrap().unwrap().value(), 101);
+    assert_eq!(table.get(5000).unwrap().unwrap().value(), 5000);
+    drop(table);
+    let table = read_txn.open_table(clone).unwrap();
+    assert_eq!(table.len().unwrap(), 9_999);
+    assert_eq!(table.get(0).unwrap().unwrap().value(), 100);
+    assert_eq!(table.get(1).unwrap().unwrap().value(), 1);
+    assert!(table.get(5000).unwrap().is_none());
+    assert!(table.get(20_000).unwrap().is_none());
+    drop(table);
+    drop(read_txn);
+    assert!(db.check_integrity().unwrap());
+
+    // Deleting either table only frees the pages that aren't shared
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(U64_TABLE).unwrap();
+    write_txn.commit().unwrap();
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(clone).unwrap();
+    assert_eq!(table.len().unwrap(), 9_999);
+    for i in 1..5000 {
+        assert_eq!(table.get(i).unwrap().unwrap().value(), i);
+    }
+    drop(table);
+    drop(read_txn);
+    assert!(db.check_integrity().unwrap());
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(clone).unwrap();
+    write_txn.commit().unwrap();
+    db.compact().unwrap();
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+fn clone_table_savepoint() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let clone: TableDefinition<u64, u64> = TableDefinition::new("clone");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10_000 {
+            table.insert(i, i).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    let savepoint = write_txn.ephemeral_savepoint().unwrap();
+    write_txn.clone_table(U64_TABLE, clone).unwrap();
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(0, 1).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut write_txn = db.begin_write().unwrap();
+    write_txn.restore_savepoint(&savepoint).unwrap();
+    write_txn.commit().unwrap();
+    drop(savepoint);
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(clone),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.get(0).unwrap().unwrap().value(), 0);
+    drop(table);
+    drop(read_txn);
+    assert!(db.check_integrity().unwrap());
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.clone_table(U64_TABLE, clone).unwrap();
+    write_txn.commit().unwrap();
+    db.compact().unwrap();
+    assert!(db.check_integrity().unwrap());
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(clone).unwrap();
+    assert_eq!(table.len().unwrap(), 10_000);
+    assert_eq!(table.get(9_999).unwrap().unwrap().value(), 9_999);
+}
+
+#[test]
+fn migrate_table() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let migrated: TableDefinition<u64, &str> = TableDefinition::new("u64");
+    let other: TableDefinition<u64, u64> = TableDefinition::new("other");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10_000 {
+            table.insert(i, i % 3).unwrap();
+        }
+        write_txn.open_table(other).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.migrate_table(STR_TABLE, migrated, |_, _| (0, "")),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    assert!(matches!(
+        write_txn.migrate_table(U64_TABLE, other, |k, v| (k, v)),
+        Err(TableError::TableAlreadyExists(_))
+    ));
+    write_txn
+        .migrate_table(U64_TABLE, migrated, |key, value| {
+            let value = match value {
+                0 => "zero",
+                1 => "one",
+                _ => "two",
+            };
+            (key + 1, value)
+        })
+        .unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(U64_TABLE),
+        Err(TableError::TableTypeMismatch { .. })
+    ));
+    let table = read_txn.open_table(migrated).unwrap();
+    assert_eq!(table.len().unwrap(), 10_000);
+    assert!(table.get(0).unwrap().is_none());
+    assert_eq!(table.get(1).unwrap().unwrap().value(), "zero");
+    assert_eq!(table.get(10_000).unwrap().unwrap().value(), "zero");
+    assert_eq!(table.get(6).unwrap().unwrap().value(), "two");
+    drop(table);
+    // The migrated table was built under a temporary name, which is gone
+    let mut names: Vec<String> = read_txn
+        .list_tables()
+        .unwrap()
+        .map(|x| x.name().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["other".to_string(), "u64".to_string()]);
+    drop(read_txn);
+
+    // The pages of the old table were freed
+    assert!(db.check_integrity().unwrap());
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn schema_migrations() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let v1: TableDefinition<u64, u64> = TableDefinition::new("data");
+    let v2: TableDefinition<u64, &str> = TableDefinition::new("data");
+    let v3: TableDefinition<u64, &str> = TableDefinition::new("renamed");
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.open_table(v1).unwrap();
+    write_txn.commit().unwrap();
+
+    let mut schema = Schema::new(1);
+    schema
+        .add_table(v1)
+        .add_multimap_table(MultimapTableDefinition::<u64, u64>::new("index"));
+    // An existing database without a stored version is at version 0
+    assert!(matches!(
+        db.apply_schema(&schema),
+        Err(SchemaError::MissingMigration(0))
+    ));
+    schema.set_initial_version(1);
+    db.apply_schema(&schema).unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(v1).unwrap();
+        table.insert(1, 1).unwrap();
+        table.insert(2, 2).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    // A table that doesn't match its definition
+    let mut schema = Schema::new(1);
+    schema.add_table(v2);
+    assert!(matches!(
+        db.apply_schema(&schema),
+        Err(SchemaError::Table(TableError::TableTypeMismatch { .. }))
+    ));
+
+    let mut schema = Schema::new(3);
+    schema.add_table(v3).add_migration(1, |txn| {
+        txn.migrate_table(v1, v2, |key, value| {
+            (key, if value == 1 { "one" } else { "other" })
+        })?;
+        Ok(())
+    });
+    assert!(matches!(
+        db.apply_schema(&schema),
+        Err(SchemaError::MissingMigration(2))
+    ));
+    schema.add_migration(2, |txn| {
+        txn.rename_table(v2, v3.name())?;
+        Ok(())
+    });
+    db.apply_schema(&schema).unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(v1),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    let table = read_txn.open_table(v3).unwrap();
+    assert_eq!(table.get(1).unwrap().unwrap().value(), "one");
+    assert_eq!(table.get(2).unwrap().unwrap().value(), "other");
+    drop(table);
+    drop(read_txn);
+
+    // Applying the same version again does nothing
+    db.apply_schema(&schema).unwrap();
+
+    let mut schema = Schema::new(2);
+    schema.add_table(v2);
+    assert!(matches!(
+        db.apply_schema(&schema),
+        Err(SchemaError::UnsupportedVersion {
+            stored: 3,
+            supported: 2
+        })
+    ));
+
+    // A failed migration leaves the database unchanged
+    let mut schema = Schema::new(4);
+    schema.add_migration(3, |txn| {
+        txn.delete_table(v3)?;
+        Err(Error::Corrupted("failed".to_string()))
+    });
+    assert!(matches!(
+        db.apply_schema(&schema),
+        Err(SchemaError::MigrationFailed { from: 3, .. })
+    ));
+    let read_txn = db.begin_read().unwrap();
+    assert_eq!(read_txn.open_table(v3).unwrap().len().unwrap(), 2);
+    drop(read_txn);
+    drop(db);
+
+    // A schema set on the builder is applied when the database is opened
+    let mut schema = Schema::new(4);
+    schema.add_migration(3, move |txn| {
+        txn.delete_table(v3)?;
+        Ok(())
+    });
+    let db = Database::builder()
+        .set_schema(schema)
+        .open(tmpfile.path())
+        .unwrap();
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(v3),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    drop(read_txn);
+    drop(db);
+
+    let mut schema = Schema::new(3);
+    schema.add_table(v3);
+    assert!(matches!(
+        Database::builder().set_schema(schema).open(tmpfile.path()),
+        Err(DatabaseError::Schema(SchemaError::UnsupportedVersion {
+            stored: 4,
+            supported: 3
+        }))
+    ));
+}
+
+#[test]
+#[allow(clippy::result_large_err)]
+fn schema_new_database() {
+    let tmpfile = create_tempfile();
+    const USERS: TableDefinition<u64, &str> = TableDefinition::new("users");
+    const EMAILS: TableDefinition<&str, u64> = TableDefinition::new("emails");
+
+    let schema = || {
+        let mut schema = Schema::new(2);
+        schema
+            .add_table(USERS)
+            .add_table(EMAILS)
+            .add_migration(0, |txn| {
+                txn.delete_table(TableDefinition::<u64, u64>::new("legacy"))?;
+                Ok(())
+            })
+            .add_migration(1, |txn| {
+                // Fails if it is run, since the table doesn't exist in a new database
+                txn.rename_table(TableDefinition::<u64, &str>::new("people"), USERS.name())?;
+                Ok(())
+            });
+        schema
+    };
+
+    // A new database is created at the schema's version, without running any migrations
+    let db = Database::builder()
+        .set_schema(schema())
+        .create(tmpfile.path())
+        .unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(USERS).unwrap();
+        table.insert(1, "alice").unwrap();
+    }
+    write_txn.commit().unwrap();
+    drop(db);
+
+    let db = Database::builder()
+        .set_schema(schema())
+        .open(tmpfile.path())
+        .unwrap();
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(USERS).unwrap();
+    assert_eq!(table.get(1).unwrap().unwrap().value(), "alice");
+    assert!(read_txn.open_table(EMAILS).unwrap().is_empty().unwrap());
+    drop(table);
+    drop(read_txn);
+
+    // Only the migrations after the stamped version are run
+    let mut schema = Schema::new(3);
+    schema.add_table(USERS).add_migration(2, |txn| {
+        txn.delete_table(EMAILS)?;
+        Ok(())
+    });
+    db.apply_schema(&schema).unwrap();
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_table(EMAILS),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+}
+
+#[test]
+fn untyped_table() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    write_txn.open_table(U64_TABLE).unwrap();
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_untyped_table(U64_TABLE).unwrap();
+        for i in [300u64, 2, 1000, 1] {
+            table
+                .insert(&i.to_le_bytes(), &(i * 2).to_le_bytes())
+                .unwrap();
+        }
+        let old = table.remove(&1u64.to_le_bytes()).unwrap().unwrap();
+        assert_eq!(old.value(), &2u64.to_le_bytes());
+        drop(old);
+        let value = table.get(&300u64.to_le_bytes()).unwrap().unwrap();
+        assert_eq!(value.value(), &600u64.to_le_bytes());
+        drop(value);
+        assert_eq!(table.len().unwrap(), 3);
+
+        // Keys are ordered as u64, not as bytes
+        let start = 2u64.to_le_bytes();
+        let end = 1000u64.to_le_bytes();
+        let keys: Vec<Vec<u8>> = table
+            .range(start.as_slice()..end.as_slice())
+            .unwrap()
+            .map(|x| x.unwrap().0.value().to_vec())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![2u64.to_le_bytes().to_vec(), 300u64.to_le_bytes().to_vec()]
+        );
+        let (key, _) = table.iter().unwrap().next_back().unwrap().unwrap();
+        assert_eq!(key.value(), &1000u64.to_le_bytes());
+        drop(key);
+
+        assert!(matches!(
+            table.insert(&[1, 2, 3], &0u64.to_le_bytes()),
+            Err(TableError::InvalidKeyWidth {
+                expected: 8,
+                actual: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            table.insert(&5u64.to_le_bytes(), &[]),
+            Err(TableError::InvalidValueWidth {
+                expected: 8,
+                actual: 0,
+                ..
+            })
+        ));
+        assert_eq!(table.len().unwrap(), 3);
+
+        assert!(matches!(
+            write_txn.open_untyped_table(U64_TABLE),
+            Err(TableError::TableAlreadyOpen(..))
+        ));
+    }
+    assert!(matches!(
+        write_txn.open_untyped_table(STR_TABLE),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+    assert!(matches!(
+        write_txn.open_untyped_table_ordered_by::<u32>(U64_TABLE),
+        Err(TableError::TableTypeMismatch { .. })
+    ));
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let entries: Vec<(u64, u64)> = table
+        .iter()
+        .unwrap()
+        .map(|x| {
+            let (key, value) = x.unwrap();
+            (key.value(), value.value())
+        })
+        .collect();
+    assert_eq!(entries, vec![(2, 4), (300, 600), (1000, 2000)]);
+}
+
+#[test]
+fn table_info() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let multimap_def: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new("multi");
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(1, 2).unwrap();
+        table.insert(3, 4).unwrap();
+        write_txn.open_table(STR_TABLE).unwrap();
+        let mut multimap = write_txn.open_multimap_table(multimap_def).unwrap();
+        multimap.insert("a", 1).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let info = read_txn.table_info(U64_TABLE).unwrap();
+    assert_eq!(info.name(), U64_TABLE.name());
+    assert!(!info.is_multimap());
+    assert_eq!(info.key_type(), &<u64 as Value>::type_name());
+    assert_eq!(info.value_type().name(), "u64");
+    assert_eq!(info.fixed_key_width(), Some(8));
+    assert_eq!(info.fixed_value_width(), Some(8));
+    assert_eq!(info.len(), 2);
+    assert!(info.root_page().is_some());
+
+    let info = read_txn.table_info(STR_TABLE).unwrap();
+    assert_eq!(info.key_type().name(), "&str");
+    assert_eq!(info.fixed_key_width(), None);
+    assert!(info.is_empty());
+    assert!(info.root_page().is_none());
+
+    let info = read_txn.multimap_table_info(multimap_def).unwrap();
+    assert!(info.is_multimap());
+    assert_eq!(info.len(), 1);
+    assert!(matches!(
+        read_txn.table_info(TableDefinition::<&str, u64>::new("multi")),
+        Err(TableError::TableIsMultimap(_))
+    ));
+    assert!(matches!(
+        read_txn.table_info(SLICE_TABLE),
+        Err(TableError::TableDoesNotExist(_))
+    ));
+
+    let names: Vec<String> = read_txn
+        .list_table_info()
+        .unwrap()
+        .map(|info| info.name().to_string())
+        .collect();
+    assert_eq!(names, vec!["multi", "u64", "x"]);
+}
+
+#[test]
+fn blob_table() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let definition: BlobTableDefinition<&str> = BlobTableDefinition::new("blobs");
+
+    // Spans several chunks, and isn't a multiple of the chunk size
+    let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_blob_table(definition).unwrap();
+        let mut writer = table.writer("a").unwrap();
+        for part in data.chunks(100_000) {
+            writer.write_all(part).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), data.len() as u64);
+        table.writer("empty").unwrap().finish().unwrap();
+        table.writer("b").unwrap().write_all(b"hello").unwrap();
+        assert_eq!(table.blob_len("b").unwrap(), Some(5));
+        assert!(table.remove("b").unwrap());
+        assert!(!table.remove("b").unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_blob_table(definition).unwrap();
+    assert_eq!(table.blob_len("a").unwrap(), Some(data.len() as u64));
+    assert_eq!(table.blob_len("empty").unwrap(), Some(0));
+    assert!(table.reader("b").unwrap().is_none());
+
+    let mut reader = table.reader("a").unwrap().unwrap();
+    let mut contents = vec![];
+    reader.read_to_end(&mut contents).unwrap();
+    assert!(contents == data);
+
+    let mut buf = [0; 10];
+    reader.seek(SeekFrom::Start(1_048_570)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, data[1_048_570..1_048_580]);
+    reader.seek(SeekFrom::End(-10)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, data[data.len() - 10..]);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    assert!(reader.seek(SeekFrom::Current(-4_000_000)).is_err());
+    drop(reader);
+    drop(table);
+    drop(read_txn);
+
+    // Overwriting replaces the whole blob
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_blob_table(definition).unwrap();
+        let mut writer = table.writer("a").unwrap();
+        writer.write_all(b"short").unwrap();
+        writer.finish().unwrap();
+        let mut contents = vec![];
+        table
+            .reader("a")
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"short");
+    }
+    write_txn.commit().unwrap();
+}
@@ -43,6 +43,138 @@ fn gen_data(count: usize, key_size: usize, value_size: usize) -> Vec<(Vec<u8>, V
     pairs
 }
 
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_backend() {
+    use redb::backends::{EncryptedBackend, EncryptionKey};
+
+    const KEY: [u8; 32] = [7; 32];
+    const SECRET: &str = "attack at dawn";
+
+    let tmpfile = create_tempfile();
+    let open = |key: EncryptionKey| {
+        EncryptedBackend::new(FileBackend::new(tmpfile.reopen().unwrap()).unwrap(), key)
+    };
+
+    let db = Builder::new()
+        .create_with_backend(open(EncryptionKey::Raw(&KEY)).unwrap())
+        .unwrap();
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(STR_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(format!("{i}").as_str(), SECRET).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+    drop(db);
+
+    let contents = fs::read(tmpfile.path()).unwrap();
+    assert!(!contents
+        .windows(SECRET.len())
+        .any(|x| x == SECRET.as_bytes()));
+
+    assert!(matches!(
+        open(EncryptionKey::Raw(&[8; 32])),
+        Err(DatabaseError::InvalidEncryptionKey)
+    ));
+    assert!(matches!(
+        open(EncryptionKey::Password(b"password")),
+        Err(DatabaseError::InvalidEncryptionKey)
+    ));
+
+    open(EncryptionKey::Raw(&KEY))
+        .unwrap()
+        .rotate_key(EncryptionKey::Password(b"password"))
+        .unwrap();
+    assert!(matches!(
+        open(EncryptionKey::Raw(&KEY)),
+        Err(DatabaseError::InvalidEncryptionKey)
+    ));
+
+    let mut db = Builder::new()
+        .create_with_backend(open(EncryptionKey::Password(b"password")).unwrap())
+        .unwrap();
+    assert!(db.check_integrity().unwrap());
+    let txn = db.begin_read().unwrap();
+    let table = txn.open_table(STR_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 1000);
+    for i in 0..1000 {
+        let key = format!("{i}");
+        assert_eq!(table.get(key.as_str()).unwrap().unwrap().value(), SECRET);
+    }
+    drop(table);
+    drop(txn);
+    drop(db);
+
+    // Modified blocks fail authentication,
+    let backend = open(EncryptionKey::Password(b"password")).unwrap();
+    let mut contents = fs::read(tmpfile.path()).unwrap();
+    // The second block follows the encryption header, the first metadata block, and the first block
+    contents[3 * 4096 + 1000] ^= 1;
+    fs::write(tmpfile.path(), contents).unwrap();
+    assert_eq!(backend.read(0, 100).unwrap().len(), 100);
+    assert_eq!(
+        backend.read(5000, 100).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+    // until they're overwritten
+    backend.write(4096, &[1; 4096]).unwrap();
+    assert_eq!(backend.read(5000, 100).unwrap(), vec![1; 100]);
+}
+
+#[cfg(feature = "keyed_checksums")]
+#[test]
+fn keyed_checksums() {
+    const KEY: [u8; 32] = [7; 32];
+
+    let tmpfile = create_tempfile();
+    let db = Builder::new()
+        .set_checksum_key(KEY)
+        .create(tmpfile.path())
+        .unwrap();
+    let mut txn = db.begin_write().unwrap();
+    txn.set_durability(Durability::Paranoid);
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(i, i * 2).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+    drop(db);
+
+    assert!(matches!(
+        Database::open(tmpfile.path()),
+        Err(DatabaseError::InvalidChecksumKey)
+    ));
+    assert!(matches!(
+        Builder::new()
+            .set_checksum_key([8; 32])
+            .open(tmpfile.path()),
+        Err(DatabaseError::InvalidChecksumKey)
+    ));
+
+    let mut db = Builder::new()
+        .set_checksum_key(KEY)
+        .open(tmpfile.path())
+        .unwrap();
+    assert!(db.check_integrity().unwrap());
+    let txn = db.begin_read().unwrap();
+    let table = txn.open_table(U64_TABLE).unwrap();
+    for i in 0..1000 {
+        assert_eq!(table.get(i).unwrap().unwrap().value(), i * 2);
+    }
+
+    // A key can't be used with a database which was created without one
+    let tmpfile = create_tempfile();
+    drop(Database::create(tmpfile.path()).unwrap());
+    assert!(matches!(
+        Builder::new().set_checksum_key(KEY).open(tmpfile.path()),
+        Err(DatabaseError::InvalidChecksumKey)
+    ));
+}
+
 #[test]
 fn previous_io_error() {
     #[derive(Debug)]
@@ -1743,8 +1875,7 @@ fn compaction() {
     let txn = db.begin_write().unwrap();
     txn.commit().unwrap();
 
-    // The values are > 1 page, so shouldn't get relocated. Therefore there should be a bunch of fragmented space,
-    // since we left the last 100 values in the db.
+    // There should be a bunch of fragmented space, since we left the last 10 values in the db.
     drop(db);
     let file_size = tmpfile.as_file().metadata().unwrap().len();
     let mut db = Database::open(tmpfile.path()).unwrap();
@@ -1755,6 +1886,91 @@ fn compaction() {
     assert!(file_size2 < file_size);
 }
 
+#[test]
+fn overflow_values() {
+    let tmpfile = create_tempfile();
+    let mut db = Database::create(tmpfile.path()).unwrap();
+    let definition: TableDefinition<u32, &[u8]> = TableDefinition::new("x");
+    let clone: TableDefinition<u32, &[u8]> = TableDefinition::new("clone");
+
+    let big_value = |i: u32| vec![u8::try_from(i).unwrap(); 100 * 1024 + 7 * i as usize];
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        // Interleave large values with small ones, which share their leaves
+        for i in 0..10 {
+            table.insert(&(2 * i), big_value(i).as_slice()).unwrap();
+            table.insert(&(2 * i + 1), [1, 2, 3].as_slice()).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let mut txn = db.begin_write().unwrap();
+    let savepoint = txn.ephemeral_savepoint().unwrap();
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        let old = table.insert(&0, [4].as_slice()).unwrap().unwrap();
+        assert_eq!(old.value(), big_value(0));
+        drop(old);
+        let old = table.insert(&2, big_value(5).as_slice()).unwrap().unwrap();
+        assert_eq!(old.value(), big_value(1));
+        drop(old);
+        let old = table.insert(&1, big_value(6).as_slice()).unwrap().unwrap();
+        assert_eq!(old.value(), [1, 2, 3]);
+        drop(old);
+        assert_eq!(table.remove(&4).unwrap().unwrap().value(), big_value(2));
+        assert_eq!(table.get(&2).unwrap().unwrap().value(), big_value(5));
+        assert_eq!(table.get(&1).unwrap().unwrap().value(), big_value(6));
+        assert!(table.get(&4).unwrap().is_none());
+    }
+    txn.restore_savepoint(&savepoint).unwrap();
+    drop(savepoint);
+    txn.clone_table(definition, clone).unwrap();
+    {
+        let mut table = txn.open_table(clone).unwrap();
+        table.insert(&101, big_value(9).as_slice()).unwrap();
+        table.retain(|key, _| key % 4 != 0).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_read().unwrap();
+    {
+        let table = txn.open_table(definition).unwrap();
+        assert_eq!(table.len().unwrap(), 20);
+        for (i, entry) in table.iter().unwrap().rev().enumerate() {
+            let (key, value) = entry.unwrap();
+            assert_eq!(key.value(), 19 - u32::try_from(i).unwrap());
+            if key.value() % 2 == 0 {
+                assert_eq!(value.value(), big_value(key.value() / 2));
+            } else {
+                assert_eq!(value.value(), [1, 2, 3]);
+            }
+        }
+        let table = txn.open_table(clone).unwrap();
+        assert_eq!(table.len().unwrap(), 16);
+        assert_eq!(table.get(&101).unwrap().unwrap().value(), big_value(9));
+        assert_eq!(table.get(&6).unwrap().unwrap().value(), big_value(3));
+    }
+    drop(txn);
+    assert!(db.check_integrity().unwrap());
+
+    let txn = db.begin_write().unwrap();
+    txn.delete_table(definition).unwrap();
+    txn.delete_table(clone).unwrap();
+    txn.commit().unwrap();
+    // Second commit to free the pages of the deleted tables
+    let txn = db.begin_write().unwrap();
+    txn.commit().unwrap();
+    assert!(db.check_integrity().unwrap());
+
+    let file_size = tmpfile.as_file().metadata().unwrap().len();
+    while db.compact().unwrap() {}
+    drop(db);
+    let file_size2 = tmpfile.as_file().metadata().unwrap().len();
+    assert!(file_size2 < file_size);
+}
+
 fn require_send<T: Send>(_: &T) {}
 fn require_sync<T: Sync + Send>(_: &T) {}
 
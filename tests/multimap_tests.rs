@@ -90,6 +90,34 @@ fn insert() {
     assert_eq!(table.len().unwrap(), 2);
 }
 
+#[test]
+fn conditional_insert() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(STR_TABLE).unwrap();
+        assert!(table.insert_if_absent("hello", "world").unwrap());
+        assert!(!table.insert_if_absent("hello", "world2").unwrap());
+
+        assert!(!table
+            .compare_and_swap("hello", Some(&"world2"), "world3")
+            .unwrap());
+        assert!(table
+            .compare_and_swap("hello", Some(&"world"), "world3")
+            .unwrap());
+        assert!(!table.compare_and_swap("hello", None, "world4").unwrap());
+        assert!(table.compare_and_swap("hi", None, "world4").unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(STR_TABLE).unwrap();
+    assert_eq!(vec!["world3".to_string()], get_vec(&table, "hello"));
+    assert_eq!(vec!["world4".to_string()], get_vec(&table, "hi"));
+    assert_eq!(table.len().unwrap(), 2);
+}
+
 #[test]
 fn range_query() {
     let tmpfile = create_tempfile();
@@ -416,3 +444,122 @@ fn multimap_signature_lifetimes() {
     }
     write_txn.commit().unwrap();
 }
+
+#[test]
+fn export_import() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(U64_TABLE).unwrap();
+        // Enough values for some keys to be stored in subtrees
+        for i in 0..100u64 {
+            for j in 0..(i * 10) {
+                table.insert(i, j).unwrap();
+            }
+        }
+        let mut table = write_txn.open_multimap_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+        table.insert("hello", "world2").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut data = vec![];
+    db.begin_read().unwrap().export(&mut data).unwrap();
+
+    let tmpfile2 = create_tempfile();
+    let mut db2 = Database::create(tmpfile2.path()).unwrap();
+    let write_txn = db2.begin_write().unwrap();
+    write_txn.import(data.as_slice()).unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db2.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), (0..100).map(|i| i * 10).sum::<u64>());
+    for i in 0..100u64 {
+        let values: Vec<u64> = table.get(i).unwrap().map(|x| x.unwrap().value()).collect();
+        assert_eq!(values, (0..(i * 10)).collect::<Vec<u64>>());
+    }
+    drop(table);
+    let table = read_txn.open_multimap_table(STR_TABLE).unwrap();
+    assert_eq!(get_vec(&table, "hello"), vec!["world", "world2"]);
+    drop(table);
+    drop(read_txn);
+
+    let write_txn = db2.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(U64_TABLE).unwrap();
+        assert!(table.remove(99, 0).unwrap());
+        table.insert(1, 100).unwrap();
+    }
+    write_txn.commit().unwrap();
+    assert!(db2.check_integrity().unwrap());
+}
+
+#[test]
+fn rename_table() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let renamed: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new("renamed");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(STR_TABLE).unwrap();
+        table.insert("hello", "world").unwrap();
+        table.insert("hello", "world2").unwrap();
+    }
+    assert!(matches!(
+        write_txn.rename_table(
+            redb::TableDefinition::<&str, &str>::new("str_to_str"),
+            "renamed"
+        ),
+        Err(TableError::TableIsMultimap(_))
+    ));
+    write_txn
+        .rename_multimap_table(STR_TABLE, "renamed")
+        .unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(read_txn.open_multimap_table(STR_TABLE).is_err());
+    let table = read_txn.open_multimap_table(renamed).unwrap();
+    assert_eq!(get_vec(&table, "hello"), vec!["world", "world2"]);
+}
+
+#[test]
+fn migrate_table() {
+    let tmpfile = create_tempfile();
+    let db = Database::create(tmpfile.path()).unwrap();
+    let migrated: MultimapTableDefinition<u64, u64> = MultimapTableDefinition::new("str_to_str");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(STR_TABLE).unwrap();
+        for i in 0..100u64 {
+            for j in 0..100u64 {
+                table
+                    .insert(i.to_string().as_str(), j.to_string().as_str())
+                    .unwrap();
+            }
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn
+        .migrate_multimap_table(STR_TABLE, migrated, |key, value| {
+            (key.parse().unwrap(), value.parse::<u64>().unwrap() * 2)
+        })
+        .unwrap();
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.open_multimap_table(STR_TABLE),
+        Err(TableError::TableTypeMismatch { .. })
+    ));
+    let table = read_txn.open_multimap_table(migrated).unwrap();
+    assert_eq!(table.len().unwrap(), 10_000);
+    let values: Vec<u64> = table.get(42).unwrap().map(|x| x.unwrap().value()).collect();
+    assert_eq!(values, (0..100).map(|x| x * 2).collect::<Vec<u64>>());
+}